@@ -0,0 +1,5 @@
+-- Add down migration script here
+ALTER TABLE `order_items`
+  DROP COLUMN thumbnail_url,
+  DROP COLUMN preview_url,
+  DROP COLUMN preview_unavailable;
@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE `order_items`
+  ADD COLUMN thumbnail_url text null,
+  ADD COLUMN preview_url text null,
+  ADD COLUMN preview_unavailable boolean not null default false
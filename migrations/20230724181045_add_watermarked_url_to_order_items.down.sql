@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE `order_items`
+  DROP COLUMN watermarked_url;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE `order_items`
+  ADD COLUMN watermarked_url text null
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE `order_items`
+  ADD COLUMN delivery_file_name varchar(255) null
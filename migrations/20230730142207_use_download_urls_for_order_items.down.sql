@@ -0,0 +1,2 @@
+-- Add down migration script here
+-- Presigned urls are minted on demand and cannot be restored
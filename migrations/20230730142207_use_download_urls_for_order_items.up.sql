@@ -0,0 +1,3 @@
+-- Add up migration script here
+UPDATE `order_items`
+  SET get_url = CONCAT('/download/', id)
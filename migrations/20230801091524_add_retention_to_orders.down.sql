@@ -0,0 +1,5 @@
+-- Add down migration script here
+ALTER TABLE `orders`
+  DROP COLUMN delivered_at,
+  DROP COLUMN original_warning_sent_at,
+  DROP COLUMN processed_warning_sent_at;
@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE `orders`
+  ADD COLUMN delivered_at datetime null,
+  ADD COLUMN original_warning_sent_at datetime null,
+  ADD COLUMN processed_warning_sent_at datetime null
@@ -0,0 +1,3 @@
+-- Add down migration script here
+UPDATE `orders`
+  SET delivered_at = NULL;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+UPDATE `orders`
+  SET delivered_at = NOW()
+  WHERE status = 9 AND delivered_at IS NULL
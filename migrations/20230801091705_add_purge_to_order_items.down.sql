@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE `order_items`
+  DROP COLUMN file_size,
+  DROP COLUMN purged_at;
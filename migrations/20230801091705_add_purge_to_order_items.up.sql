@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE `order_items`
+  ADD COLUMN file_size bigint unsigned null,
+  ADD COLUMN purged_at datetime null
@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE `order_items`
+  DROP INDEX order_items_checksum,
+  DROP COLUMN checksum;
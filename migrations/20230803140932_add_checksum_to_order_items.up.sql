@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE `order_items`
+  ADD COLUMN checksum char(64) null,
+  ADD UNIQUE INDEX order_items_checksum (order_id, mode, checksum);
@@ -0,0 +1,13 @@
+-- Add up migration script here
+CREATE TABLE `user_tokens` (
+  id bigint unsigned auto_increment not null,
+  user_id bigint unsigned not null,
+  purpose tinyint not null,
+  token_hash char(64) not null,
+  expires_at datetime not null,
+  used_at datetime null,
+  created_at datetime not null,
+  primary key (id),
+  unique (token_hash),
+  foreign key (user_id) references users (id) on delete cascade
+) ENGINE=InnoDB DEFAULT CHARSET=utf8
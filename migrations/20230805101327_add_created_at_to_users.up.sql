@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE `users`
+  ADD COLUMN created_at datetime not null default current_timestamp
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DELETE FROM `axum_sessions`;
+DROP TABLE `user_sessions`;
@@ -0,0 +1,13 @@
+-- Add up migration script here
+CREATE TABLE `user_sessions` (
+  id bigint unsigned auto_increment not null,
+  user_id bigint unsigned not null,
+  created_at datetime not null,
+  revoked_at datetime null,
+  primary key (id),
+  foreign key (user_id) references users (id) on delete cascade
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+
+-- Logins now point at a user_sessions row instead of the user, so existing
+-- logins would resolve to the wrong account and everyone signs in again
+DELETE FROM `axum_sessions`;
@@ -0,0 +1,15 @@
+-- Add up migration script here
+CREATE TABLE `invitations` (
+  id bigint unsigned auto_increment not null,
+  email varchar(255) not null,
+  role tinyint not null,
+  token_hash char(64) not null,
+  invited_by bigint unsigned not null,
+  expires_at datetime not null,
+  accepted_at datetime null,
+  revoked_at datetime null,
+  created_at datetime not null,
+  primary key (id),
+  unique (token_hash),
+  foreign key (invited_by) references users (id) on delete cascade
+) ENGINE=InnoDB DEFAULT CHARSET=utf8
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE `account_lockouts`;
+DROP TABLE `login_attempts`
@@ -0,0 +1,29 @@
+-- Add up migration script here
+CREATE TABLE `login_attempts` (
+  id bigint unsigned auto_increment not null,
+  user_id bigint unsigned null,
+  ip varchar(45) not null,
+  kind tinyint not null,
+  succeeded boolean not null,
+  created_at datetime not null,
+  primary key (id),
+  index (user_id, created_at),
+  index (ip, created_at),
+  foreign key (user_id) references users (id) on delete cascade
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+
+CREATE TABLE `account_lockouts` (
+  id bigint unsigned auto_increment not null,
+  user_id bigint unsigned null,
+  ip varchar(45) null,
+  failed_attempts int unsigned not null,
+  locked_until datetime not null,
+  unlocked_by bigint unsigned null,
+  unlocked_at datetime null,
+  created_at datetime not null,
+  primary key (id),
+  index (user_id, locked_until),
+  index (ip, locked_until),
+  foreign key (user_id) references users (id) on delete cascade,
+  foreign key (unlocked_by) references users (id) on delete set null
+) ENGINE=InnoDB DEFAULT CHARSET=utf8
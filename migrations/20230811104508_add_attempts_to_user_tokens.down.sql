@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE `user_tokens` DROP COLUMN `attempts`
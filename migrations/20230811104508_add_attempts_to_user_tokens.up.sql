@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE `user_tokens` ADD COLUMN `attempts` int unsigned not null default 0 AFTER `expires_at`;
+
+-- Login codes are no longer derived from a per-user secret
+UPDATE `users` SET `otp_secret` = NULL;
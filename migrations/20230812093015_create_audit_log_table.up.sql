@@ -0,0 +1,14 @@
+-- Add up migration script here
+CREATE TABLE `audit_log` (
+  id bigint unsigned auto_increment not null,
+  actor_id bigint unsigned null,
+  target_user_id bigint unsigned null,
+  action tinyint not null,
+  details varchar(500) not null,
+  created_at datetime not null,
+  primary key (id),
+  index (target_user_id, created_at),
+  index (actor_id, created_at),
+  foreign key (actor_id) references users (id) on delete set null,
+  foreign key (target_user_id) references users (id) on delete set null
+) ENGINE=InnoDB DEFAULT CHARSET=utf8
@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE `user_tokens`
+  DROP COLUMN new_email
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE `user_tokens`
+  ADD COLUMN new_email varchar(255) null
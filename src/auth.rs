@@ -1,4 +1,4 @@
-use crate::models::user::*;
+use crate::models::{user::*, user_session::UserSession};
 use axum::async_trait;
 use axum_session::SessionMySqlPool;
 use axum_session_auth::Authentication;
@@ -21,16 +21,29 @@ pub fn auth(cx: Scope) -> Result<AuthSession, ServerFnError> {
         .map_err(|e| ServerFnError::ServerError(e.to_string()))
 }
 
+pub async fn login(auth: &AuthSession, user_id: u64, pool: &MySqlPool) -> Result<(), ServerFnError> {
+    let session_id = UserSession::create(user_id, pool).await?;
+    auth.logout_user();
+    auth.login_user(session_id);
+    Ok(())
+}
+
 #[async_trait]
 impl Authentication<User, u64, MySqlPool> for User {
     async fn load_user(userid: u64, pool: Option<&MySqlPool>) -> Result<User, anyhow::Error> {
+        // The session id is a user_sessions row, revoked rows fall back to anonymous
         if userid == 0 {
-            Ok(User::anonymous())
-        } else {
-            let pool = pool.unwrap();
-            User::get_by_id(userid, pool)
+            return Ok(User::anonymous());
+        }
+        let pool = pool.unwrap();
+        let user_id = UserSession::get_user_id(userid, pool)
+            .await
+            .map_err(|e| anyhow::Error::msg(e.to_string()))?;
+        match user_id {
+            Some(user_id) => User::get_by_id(user_id, pool)
                 .await
-                .map_err(|e| anyhow::Error::msg(e.to_string()))
+                .map_err(|e| anyhow::Error::msg(e.to_string())),
+            None => Ok(User::anonymous()),
         }
     }
 
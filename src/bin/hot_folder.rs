@@ -0,0 +1,95 @@
+// Watches the folder a tethered camera writes to and uploads new photos to an order
+// Usage: hot-folder <watch dir> [order number or scanned QR]
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use portrait_booth::models::{ingest::IngestOrder, order_item::Mode};
+use portrait_booth::server::client::{FolderWatcher, IngestClient};
+
+fn read_order_reference() -> Option<String> {
+    println!("Scan the order QR code or enter the order number:");
+    std::io::stdin().lock().lines().next()?.ok()
+}
+
+#[tokio::main]
+async fn main() {
+    let mut args = std::env::args().skip(1);
+    let Some(watch_dir) = args.next().map(PathBuf::from) else {
+        eprintln!("Usage: hot-folder <watch dir> [order number or scanned QR]");
+        std::process::exit(2);
+    };
+    let env = |key: &str| {
+        dotenvy::var(key).unwrap_or_else(|_| {
+            eprintln!("{key} env variable should be present");
+            std::process::exit(2);
+        })
+    };
+    let base_url = dotenvy::var("HOT_FOLDER_URL").unwrap_or_else(|_| env("APP_URL"));
+    let poll_interval = dotenvy::var("HOT_FOLDER_POLL_INTERVAL")
+        .ok()
+        .and_then(|interval| interval.parse().ok())
+        .unwrap_or(2);
+
+    let client = match IngestClient::login(
+        &base_url,
+        env("HOT_FOLDER_USERNAME"),
+        env("HOT_FOLDER_PASSWORD"),
+    )
+    .await
+    {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Unable to log in: {e}");
+            std::process::exit(1);
+        }
+    };
+    let Some(reference) = args.next().or_else(read_order_reference) else {
+        std::process::exit(2);
+    };
+    let mut order = match client
+        .post::<IngestOrder>(format!("/ingest/orders/{}", reference.trim()), None)
+        .await
+    {
+        Ok(order) => order,
+        Err(e) => {
+            eprintln!("Unable to bind to order {reference}: {e}");
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "Watching {} for order {:0>6}, {} of {} photos remaining",
+        watch_dir.display(),
+        order.order_id,
+        order.remaining,
+        order.no_of_photos
+    );
+
+    // Files already in the folder belong to earlier sessions
+    let mut watcher = FolderWatcher::new(watch_dir, Mode::Original).skip_existing();
+    while order.remaining > 0 {
+        tokio::time::sleep(Duration::from_secs(poll_interval)).await;
+        for path in watcher.poll() {
+            match client.upload("/ingest", order.order_id, &path).await {
+                Ok(_) => {
+                    order.remaining -= 1;
+                    println!("Uploaded {}, {} remaining", path.display(), order.remaining);
+                }
+                Err(e) => eprintln!("Unable to upload {}: {e}", path.display()),
+            }
+            if order.remaining == 0 {
+                break;
+            }
+        }
+    }
+    match client
+        .post::<IngestOrder>(format!("/ingest/orders/{}/uploaded", order.order_id), None)
+        .await
+    {
+        Ok(order) => println!("Order {:0>6} is now {:?}", order.order_id, order.status),
+        Err(e) => {
+            eprintln!("Unable to mark order uploaded: {e}");
+            std::process::exit(1);
+        }
+    }
+}
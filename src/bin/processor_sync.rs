@@ -0,0 +1,159 @@
+// Mirrors a processor's claimed orders into a local folder and sends edits back
+// Usage: processor-sync <work dir>
+//   <work dir>/<order>/originals  originals downloaded for editing
+//   <work dir>/<order>/processed  export finished photos here
+//   <work dir>/<order>/uploaded   processed photos that were sent
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use portrait_booth::models::{
+    ingest::SyncOrder,
+    order::OrderStatus,
+    order_item::{Mode, OrderItem},
+};
+use portrait_booth::server::client::{FolderWatcher, IngestClient};
+
+fn order_dir(work_dir: &Path, order_id: u64) -> PathBuf {
+    work_dir.join(format!("{:0>6}", order_id))
+}
+
+async fn download_originals(
+    client: &IngestClient,
+    dir: &Path,
+    originals: &[OrderItem],
+) -> Result<(), String> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|e| e.to_string())?;
+    for order_item in originals {
+        let dest = dir.join(&order_item.file_name);
+        if dest.exists() {
+            continue;
+        }
+        client.download(order_item, &dest).await?;
+        println!("Downloaded {}", dest.display());
+    }
+    Ok(())
+}
+
+async fn push_processed(
+    client: &IngestClient,
+    order: &mut SyncOrder,
+    watcher: &mut FolderWatcher,
+    uploaded_dir: &Path,
+) {
+    for path in watcher.poll() {
+        if order.remaining == 0 {
+            break;
+        }
+        if let Err(e) = client.upload("/sync", order.order_id, &path).await {
+            eprintln!("Unable to upload {}: {e}", path.display());
+            continue;
+        }
+        order.remaining -= 1;
+        println!("Uploaded {}, {} remaining", path.display(), order.remaining);
+        // Moved aside so a restart does not send it again
+        if let Some(file_name) = path.file_name() {
+            let moved = match tokio::fs::create_dir_all(uploaded_dir).await {
+                Ok(_) => tokio::fs::rename(&path, uploaded_dir.join(file_name)).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = moved {
+                eprintln!("Unable to move {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+async fn sync(
+    client: &IngestClient,
+    work_dir: &Path,
+    watchers: &mut HashMap<u64, FolderWatcher>,
+) -> Result<(), String> {
+    let mut orders = client
+        .get::<Vec<SyncOrder>>("/sync/orders".to_string())
+        .await?;
+    // A new order is only claimed once nothing is left in progress
+    if !orders
+        .iter()
+        .any(|order| order.status == OrderStatus::InProcess)
+    {
+        if let Some(order) = client
+            .post::<Option<SyncOrder>>("/sync/orders/claim".to_string(), None)
+            .await?
+        {
+            println!("Claimed order {:0>6}", order.order_id);
+            orders.retain(|claimed| claimed.order_id != order.order_id);
+            orders.push(order);
+        }
+    }
+    for mut order in orders {
+        let dir = order_dir(work_dir, order.order_id);
+        download_originals(client, &dir.join("originals"), &order.originals).await?;
+        if order.status == OrderStatus::InProcess {
+            let processed_dir = dir.join("processed");
+            tokio::fs::create_dir_all(&processed_dir)
+                .await
+                .map_err(|e| e.to_string())?;
+            let watcher = watchers
+                .entry(order.order_id)
+                .or_insert_with(|| FolderWatcher::new(processed_dir, Mode::Processed));
+            push_processed(client, &mut order, watcher, &dir.join("uploaded")).await;
+        }
+        if order.remaining == 0 {
+            match client
+                .post::<SyncOrder>(format!("/sync/orders/{}/complete", order.order_id), None)
+                .await
+            {
+                Ok(order) => {
+                    watchers.remove(&order.order_id);
+                    println!("Order {:0>6} is now {:?}", order.order_id, order.status);
+                }
+                Err(e) => eprintln!("Unable to complete order {:0>6}: {e}", order.order_id),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let Some(work_dir) = std::env::args().nth(1).map(PathBuf::from) else {
+        eprintln!("Usage: processor-sync <work dir>");
+        std::process::exit(2);
+    };
+    let env = |key: &str| {
+        dotenvy::var(key).unwrap_or_else(|_| {
+            eprintln!("{key} env variable should be present");
+            std::process::exit(2);
+        })
+    };
+    let base_url = dotenvy::var("PROCESSOR_SYNC_URL").unwrap_or_else(|_| env("APP_URL"));
+    let poll_interval = dotenvy::var("PROCESSOR_SYNC_POLL_INTERVAL")
+        .ok()
+        .and_then(|interval| interval.parse().ok())
+        .unwrap_or(10);
+
+    let client = match IngestClient::login(
+        &base_url,
+        env("PROCESSOR_SYNC_USERNAME"),
+        env("PROCESSOR_SYNC_PASSWORD"),
+    )
+    .await
+    {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Unable to log in: {e}");
+            std::process::exit(1);
+        }
+    };
+    println!("Syncing claimed orders into {}", work_dir.display());
+    let mut watchers = HashMap::new();
+    loop {
+        if let Err(e) = sync(&client, &work_dir, &mut watchers).await {
+            eprintln!("Sync failed: {e}");
+        }
+        tokio::time::sleep(Duration::from_secs(poll_interval)).await;
+    }
+}
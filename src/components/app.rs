@@ -3,10 +3,13 @@ use leptos_meta::*;
 use leptos_router::*;
 use serde::{Deserialize, Serialize};
 
-use crate::components::orders::confirmation::Confirmation;
+use crate::components::orders::{confirmation::Confirmation, ticket::Ticket};
 use crate::{
     components::{
-        auth::login::Login, auth::login_otp::LoginOtp, auth::signup::Signup, home_page::HomePage,
+        auth::accept_invitation::AcceptInvitation, auth::login::Login, auth::login_otp::LoginOtp,
+        auth::password_reset::{ForgotPassword, ResetPassword}, auth::profile::ConfirmEmailChange,
+        auth::signup::Signup,
+        auth::verify_email::VerifyEmail, home_page::HomePage,
     },
     models::user::User,
 };
@@ -83,7 +86,37 @@ pub fn App(cx: Scope) -> impl IntoView {
                     <Route
                         path="/signup"
                         view=|cx| {
-                            view! { cx, <Signup otp_on_success=true/> }
+                            view! { cx, <Signup/> }
+                        }
+                    />
+                    <Route
+                        path="/forgot-password"
+                        view=|cx| {
+                            view! { cx, <ForgotPassword/> }
+                        }
+                    />
+                    <Route
+                        path="/reset-password/:token"
+                        view=|cx| {
+                            view! { cx, <ResetPassword/> }
+                        }
+                    />
+                    <Route
+                        path="/invite/:token"
+                        view=|cx| {
+                            view! { cx, <AcceptInvitation/> }
+                        }
+                    />
+                    <Route
+                        path="/verify/:token"
+                        view=|cx| {
+                            view! { cx, <VerifyEmail/> }
+                        }
+                    />
+                    <Route
+                        path="/change-email/:token"
+                        view=|cx| {
+                            view! { cx, <ConfirmEmailChange/> }
                         }
                     />
                     <Route
@@ -92,6 +125,12 @@ pub fn App(cx: Scope) -> impl IntoView {
                             view! { cx, <Confirmation/> }
                         }
                     />
+                    <Route
+                        path="/ticket/:order_id"
+                        view=|cx| {
+                            view! { cx, <Ticket/> }
+                        }
+                    />
                 </Routes>
             </main>
         </Router>
@@ -1,4 +1,8 @@
+pub mod accept_invitation;
 pub mod login;
 pub mod login_otp;
 pub mod logout;
+pub mod password_reset;
+pub mod profile;
 pub mod signup;
+pub mod verify_email;
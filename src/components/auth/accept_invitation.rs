@@ -0,0 +1,146 @@
+use leptos::{ev::SubmitEvent, html::Input, *};
+use leptos_router::*;
+
+use crate::{
+    components::util::{loading::Loading, show_error::ShowError},
+    models::invitation::Invitation,
+};
+
+#[server(GetInvitationRequest, "/api")]
+pub async fn get_invitation_request(
+    cx: Scope,
+    token: String,
+) -> Result<Option<Invitation>, ServerFnError> {
+    let pool = crate::pool(cx)?;
+    crate::server::invitations::get_invitation(&token, &pool).await
+}
+
+#[server(AcceptInvitationRequest, "/api")]
+pub async fn accept_invitation_request(
+    cx: Scope,
+    token: String,
+    name: String,
+    password: String,
+    confirm_password: String,
+) -> Result<(), ServerFnError> {
+    let (pool, auth) = crate::server::pool_and_auth(cx)?;
+    let user = crate::server::invitations::accept_invitation(
+        &token,
+        name,
+        password,
+        confirm_password,
+        &pool,
+    )
+    .await?;
+    crate::auth::login(&auth, user.id, &pool).await
+}
+
+#[component]
+pub fn AcceptInvitation(cx: Scope) -> impl IntoView {
+    let params = use_params_map(cx);
+    let token = move || params.with(|params| params.get("token").cloned().unwrap_or_default());
+    let invitation = create_resource(cx, token, move |token| {
+        get_invitation_request(cx, token)
+    });
+    let name_input = create_node_ref::<Input>(cx);
+    let password_input = create_node_ref::<Input>(cx);
+    let confirm_password_input = create_node_ref::<Input>(cx);
+    let accept_action = create_server_action::<AcceptInvitationRequest>(cx);
+    let on_submit = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        let name = name_input
+            .get()
+            .expect("Name element should be present")
+            .value();
+        let password = password_input
+            .get()
+            .expect("Password element should be present")
+            .value();
+        let confirm_password = confirm_password_input
+            .get()
+            .expect("Confirm password element should be present")
+            .value();
+        accept_action.dispatch(AcceptInvitationRequest {
+            token: token(),
+            name,
+            password,
+            confirm_password,
+        });
+    };
+    let error = move || match accept_action.value().get() {
+        Some(Err(e)) => e.to_string(),
+        _ => "".to_string(),
+    };
+    let disable_control = move || accept_action.pending().get();
+    view! { cx,
+        <div class="container">
+            <h2 class="header">"Accept Invitation"</h2>
+            {move || match (accept_action.value().get(), invitation.read(cx)) {
+                (Some(Ok(_)), _) => {
+                    view! { cx,
+                        <div>"Your account is ready."</div>
+                        <a href="/" rel="external">"Continue"</a>
+                    }
+                        .into_view(cx)
+                }
+                (_, None) => view! { cx, <Loading/> },
+                (_, Some(Err(e))) => view! { cx, <ShowError error=e.to_string()/> },
+                (_, Some(Ok(None))) => {
+                    view! { cx, <div>"This invitation is invalid or has expired."</div> }
+                        .into_view(cx)
+                }
+                (_, Some(Ok(Some(invitation)))) => {
+                    view! { cx,
+                        <div>
+                            "You have been invited as " {format!("{:?}", invitation.role)} " with "
+                            {invitation.email}
+                        </div>
+                        <form on:submit=on_submit>
+                            <div class="flex flex-col text-left">
+                                <div class="flex flex-col mt-2">
+                                    <label for="invite_name">"Name"</label>
+                                    <input
+                                        id="invite_name"
+                                        node_ref=name_input
+                                        disabled=disable_control
+                                        required
+                                    />
+                                </div>
+                                <div class="flex flex-col mt-2">
+                                    <label for="invite_password">"Password"</label>
+                                    <input
+                                        id="invite_password"
+                                        type="password"
+                                        node_ref=password_input
+                                        disabled=disable_control
+                                        required
+                                    />
+                                </div>
+                                <div class="flex flex-col mt-2">
+                                    <label for="invite_confirm_password">"Confirm Password"</label>
+                                    <input
+                                        id="invite_confirm_password"
+                                        type="password"
+                                        node_ref=confirm_password_input
+                                        disabled=disable_control
+                                        required
+                                    />
+                                    <div class="hint">
+                                        "Minimum 8 characters. Include at least one of each: lowercase, uppercase, number, and special characters !@#$%^&*"
+                                    </div>
+                                    <div class="error">{error}</div>
+                                </div>
+                                <div class="text-center mt-8">
+                                    <button class="w-40" type="submit" disabled=disable_control>
+                                        "Create Account"
+                                    </button>
+                                </div>
+                            </div>
+                        </form>
+                    }
+                        .into_view(cx)
+                }
+            }}
+        </div>
+    }
+}
@@ -2,7 +2,10 @@ use leptos::{ev::SubmitEvent, html::Input, *};
 use leptos_router::use_navigate;
 use serde::{Deserialize, Serialize};
 
-use crate::models::user::User;
+use crate::{
+    components::{auth::verify_email::ResendVerificationRequest, util::empty_view::EmptyView},
+    models::user::User,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoginForm {
@@ -18,46 +21,74 @@ pub enum LoginResponse {
     NotActivated,
 }
 
-#[server(LoginRequest, "/api")]
-async fn login_request(
-    cx: Scope,
+#[cfg(feature = "ssr")]
+pub async fn authenticate(
     username: String,
     password: String,
+    ip: &str,
+    pool: &sqlx::MySqlPool,
 ) -> Result<LoginResponse, ServerFnError> {
-    use crate::models::user::UserStatus;
-    let (pool, auth) = crate::server::pool_and_auth(cx)?;
-    let Ok(user) = User::get_by_username(username, &pool).await else {
-                return Ok(LoginResponse::InvalidCredentials);
-            };
+    use crate::{
+        models::login_attempt::AttemptKind, models::user::UserStatus, server::login_guard,
+    };
+    let user = User::get_by_username(username, pool).await.ok();
+    if login_guard::is_locked(user.as_ref().map(|user| user.id), ip, pool).await? {
+        return Ok(LoginResponse::LockedOut);
+    }
+    let Some(user) = user else {
+        login_guard::record_failure(None, ip, AttemptKind::Password, pool).await?;
+        return Ok(LoginResponse::InvalidCredentials);
+    };
     let Ok(true) = bcrypt::verify(
-                password,
-                &user.clone().password_hash.unwrap_or("".to_string()),
-            ) else {
-                return Ok(LoginResponse::InvalidCredentials);
-            };
+        password,
+        &user.clone().password_hash.unwrap_or("".to_string()),
+    ) else {
+        login_guard::record_failure(Some(user.id), ip, AttemptKind::Password, pool).await?;
+        return Ok(LoginResponse::InvalidCredentials);
+    };
+    login_guard::record_success(user.id, ip, AttemptKind::Password, pool).await?;
 
     let response = match user.status {
         UserStatus::Disabled => LoginResponse::LockedOut,
         UserStatus::NotActivatedYet => LoginResponse::NotActivated,
-        UserStatus::Active => {
-            auth.login_user(user.id);
-            LoginResponse::LoggedIn(user)
-        }
+        UserStatus::Active => LoginResponse::LoggedIn(user),
     };
     Ok(response)
 }
 
+#[server(LoginRequest, "/api")]
+async fn login_request(
+    cx: Scope,
+    username: String,
+    password: String,
+) -> Result<LoginResponse, ServerFnError> {
+    let (pool, auth) = crate::server::pool_and_auth(cx)?;
+    let ip = crate::server::login_guard::current_ip(cx)?;
+    let response = authenticate(username, password, &ip, &pool).await?;
+    if let LoginResponse::LoggedIn(user) = &response {
+        crate::auth::login(&auth, user.id, &pool).await?;
+    }
+    Ok(response)
+}
+
 #[component]
 pub fn Login(cx: Scope, #[prop(optional)] completed: Option<Action<(), ()>>) -> impl IntoView {
     let username_input: NodeRef<Input> = create_node_ref(cx);
     let password_input: NodeRef<Input> = create_node_ref(cx);
     let login_request_action = create_server_action::<LoginRequest>(cx);
+    let resend_action = create_server_action::<ResendVerificationRequest>(cx);
+    let not_activated = move || {
+        matches!(
+            login_request_action.value().get(),
+            Some(Ok(LoginResponse::NotActivated))
+        )
+    };
     let error = move || match login_request_action.value().get() {
         Some(response) => match response {
             Ok(response) => match response {
                 LoginResponse::InvalidCredentials => "Invalid Credentials".to_string(),
                 LoginResponse::NotActivated => "Account not activated yet".to_string(),
-                LoginResponse::LockedOut => "Account is locked".to_string(),
+                LoginResponse::LockedOut => "Account is locked, try again later".to_string(),
                 _ => "".to_string(),
             },
             Err(e) => e.to_string(),
@@ -120,11 +151,36 @@ pub fn Login(cx: Scope, #[prop(optional)] completed: Option<Action<(), ()>>) ->
                         />
                     </div>
                 <div class="error" inner_html=error></div>
+                    {move || {
+                        if not_activated() {
+                            view! { cx,
+                                <button
+                                    type="button"
+                                    disabled=move || resend_action.pending().get()
+                                    on:click=move |_| {
+                                        let email = username_input
+                                            .get()
+                                            .expect("Username element should be present")
+                                            .value();
+                                        resend_action.dispatch(ResendVerificationRequest { email });
+                                    }
+                                >
+                                    {move || match resend_action.value().get() {
+                                        Some(_) => "Verification email sent",
+                                        None => "Resend verification email",
+                                    }}
+                                </button>
+                            }
+                                .into_view(cx)
+                        } else {
+                            view! { cx, <EmptyView/> }
+                        }
+                    }}
                     <div class="flex flex-row text-center justify-between mt-8">
                         <button class="w-40" type="submit" disabled=disable_control>
                             {login_button_text}
                         </button>
-                        <a class="disabled">"Forgot password?"</a>
+                        <a href="/forgot-password">"Forgot password?"</a>
                     </div>
                 </div>
             </form>
@@ -4,26 +4,22 @@ use leptos_router::*;
 
 #[server(LoginOtpRequest, "/api")]
 pub async fn login_otp_request(cx: Scope, email: String) -> Result<(), ServerFnError> {
-    use totp_rs::*;
+    use crate::models::user::{User, UserStatus};
     let pool = crate::pool(cx)?;
-    let result = sqlx::query_scalar!("SELECT otp_secret FROM users WHERE email = ?", email)
-        .fetch_one(&pool)
-        .await;
     log!("Received Email: {email:?}");
-    if let Ok(Some(otp_secret)) = result {
-        let totp_dur = crate::get_totp_duration();
-        let totp = TOTP::new(
-            Algorithm::SHA256,
-            6,
-            1,
-            totp_dur,
-            otp_secret.as_bytes().into(),
-        )
-        .expect("Unable to Initialize TOTP");
-        let otp = totp.generate_current().expect("Unable to generate OTP");
-        log!("OTP Code: {:#?}, ttl: {:#?}s", otp, totp.ttl());
-        let email_response = server::mailer::send_otp(email, otp.clone()).await;
-        log!("Email: {:#?}", email_response);
+    let Ok(user) = User::get_by_username(email.clone(), &pool).await else {
+        return Ok(());
+    };
+    match user.status {
+        UserStatus::NotActivatedYet => {
+            _ = server::verification::send_verification_email(&user, &pool).await;
+        }
+        UserStatus::Active => {
+            let code = server::login_codes::issue_login_code(user.id, &pool).await?;
+            let email_response = server::mailer::send_otp(email, code).await;
+            log!("Email: {:#?}", email_response);
+        }
+        UserStatus::Disabled => {}
     }
     Ok(())
 }
@@ -33,26 +29,32 @@ pub async fn login_otp_verify_request(
     email: String,
     otp: String,
 ) -> Result<bool, ServerFnError> {
-    use totp_rs::*;
+    use crate::{
+        models::{login_attempt::AttemptKind, user::User},
+        server::login_guard,
+    };
     log!("Received Email: {email:?}");
     let (pool, auth) = crate::server::pool_and_auth(cx)?;
-    let result =
-        sqlx::query_as::<_, crate::models::user::User>("SELECT * FROM users WHERE email = ?")
-            .bind(email)
-            .fetch_one(&pool)
-            .await;
+    let ip = login_guard::current_ip(cx)?;
+    let result = User::get_by_username(email, &pool).await;
+    let user_id = result.as_ref().ok().map(|user| user.id);
+    if login_guard::is_locked(user_id, &ip, &pool).await? {
+        return Err(ServerFnError::ServerError(
+            "Too many attempts, try again later".to_string(),
+        ));
+    }
     if let Ok(user) = result {
-        let secret = user.clone().otp_secret.unwrap_or_default();
-        let secret = secret.as_bytes();
-        let totp_dur = crate::get_totp_duration();
-        let totp = TOTP::new(Algorithm::SHA256, 6, 1, totp_dur, secret.into())
-            .expect("Unable to Initialize TOTP");
-        if totp.check_current(otp.as_str()).ok().unwrap_or_default() {
-            auth.logout_user();
-            auth.login_user(user.id);
+        // Unverified accounts have to open their verification link first
+        if user.status != crate::models::user::UserStatus::Active {
+            return Ok(false);
+        }
+        if server::login_codes::verify_login_code(user.id, &otp, &pool).await? {
+            login_guard::record_success(user.id, &ip, AttemptKind::Otp, &pool).await?;
+            crate::auth::login(&auth, user.id, &pool).await?;
             return Ok(true);
         }
     }
+    login_guard::record_failure(user_id, &ip, AttemptKind::Otp, &pool).await?;
     Ok(false)
 }
 
@@ -80,13 +82,10 @@ pub fn LoginOtp(
     let password_input = create_node_ref::<Input>(cx);
     let login_otp_request_action = create_server_action::<LoginOtpRequest>(cx);
     let login_otp_verify_action = create_server_action::<LoginOtpVerifyRequest>(cx);
-    let error = move || {
-        if let Some(Ok(login_otp_response)) = login_otp_verify_action.value().get() {
-            match login_otp_response {
-                true => "",
-                false => "Invalid Code",
-            };
-        }
+    let error = move || match login_otp_verify_action.value().get() {
+        Some(Ok(false)) => "Invalid Code".to_string(),
+        Some(Err(e)) => e.to_string(),
+        _ => "".to_string(),
     };
     create_effect(cx, move |_| {
         if let Some(Ok(login_otp_response)) = login_otp_verify_action.value().get() {
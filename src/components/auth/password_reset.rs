@@ -0,0 +1,159 @@
+use leptos::{ev::SubmitEvent, html::Input, *};
+use leptos_router::*;
+
+#[server(ForgotPasswordRequest, "/api")]
+pub async fn forgot_password_request(cx: Scope, email: String) -> Result<(), ServerFnError> {
+    let pool = crate::pool(cx)?;
+    if let Err(e) = crate::server::password_reset::request_password_reset(email, &pool).await {
+        log!("Unable to send password reset email: {:#?}", e);
+    }
+    Ok(())
+}
+
+#[server(ResetPasswordRequest, "/api")]
+pub async fn reset_password_request(
+    cx: Scope,
+    token: String,
+    password: String,
+    confirm_password: String,
+) -> Result<(), ServerFnError> {
+    let pool = crate::pool(cx)?;
+    crate::server::password_reset::reset_password(&token, password, confirm_password, &pool).await
+}
+
+#[component]
+pub fn ForgotPassword(cx: Scope) -> impl IntoView {
+    let email_input = create_node_ref::<Input>(cx);
+    let forgot_password_action = create_server_action::<ForgotPasswordRequest>(cx);
+    let on_submit = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        let email = email_input
+            .get()
+            .expect("Email element should be present")
+            .value();
+        if !email.is_empty() {
+            forgot_password_action.dispatch(ForgotPasswordRequest { email });
+        }
+    };
+    let disable_control = move || forgot_password_action.pending().get();
+    view! { cx,
+        <div class="container">
+            <h2 class="header">"Forgot Password"</h2>
+            {move || match forgot_password_action.value().get() {
+                Some(_) => {
+                    view! { cx,
+                        <div>
+                            "If an account exists for that email, a link to reset the password is on its way."
+                        </div>
+                        <a href="/login">"Back to login"</a>
+                    }
+                        .into_view(cx)
+                }
+                None => {
+                    view! { cx,
+                        <form on:submit=on_submit>
+                            <div class="flex flex-col text-left">
+                                <label for="forgot_email">"Email"</label>
+                                <input
+                                    id="forgot_email"
+                                    type="email"
+                                    node_ref=email_input
+                                    disabled=disable_control
+                                    required
+                                />
+                                <div class="text-center mt-8">
+                                    <button class="w-40" type="submit" disabled=disable_control>
+                                        "Send reset link"
+                                    </button>
+                                </div>
+                            </div>
+                        </form>
+                    }
+                        .into_view(cx)
+                }
+            }}
+        </div>
+    }
+}
+
+#[component]
+pub fn ResetPassword(cx: Scope) -> impl IntoView {
+    let params = use_params_map(cx);
+    let password_input = create_node_ref::<Input>(cx);
+    let confirm_password_input = create_node_ref::<Input>(cx);
+    let reset_password_action = create_server_action::<ResetPasswordRequest>(cx);
+    let on_submit = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        let token = params.with(|params| params.get("token").cloned().unwrap_or_default());
+        let password = password_input
+            .get()
+            .expect("Password element should be present")
+            .value();
+        let confirm_password = confirm_password_input
+            .get()
+            .expect("Confirm password element should be present")
+            .value();
+        reset_password_action.dispatch(ResetPasswordRequest {
+            token,
+            password,
+            confirm_password,
+        });
+    };
+    let error = move || match reset_password_action.value().get() {
+        Some(Err(e)) => e.to_string(),
+        _ => "".to_string(),
+    };
+    let disable_control = move || reset_password_action.pending().get();
+    view! { cx,
+        <div class="container">
+            <h2 class="header">"Reset Password"</h2>
+            {move || match reset_password_action.value().get() {
+                Some(Ok(_)) => {
+                    view! { cx,
+                        <div>"Your password was changed and all other sessions were signed out."</div>
+                        <a href="/login">"Login"</a>
+                    }
+                        .into_view(cx)
+                }
+                _ => {
+                    view! { cx,
+                        <form on:submit=on_submit>
+                            <div class="flex flex-col text-left">
+                                <div class="flex flex-col mt-2">
+                                    <label for="new_password">"New Password"</label>
+                                    <input
+                                        id="new_password"
+                                        type="password"
+                                        node_ref=password_input
+                                        disabled=disable_control
+                                        required
+                                    />
+                                </div>
+                                <div class="flex flex-col mt-2">
+                                    <label for="confirm_new_password">"Confirm Password"</label>
+                                    <input
+                                        id="confirm_new_password"
+                                        type="password"
+                                        node_ref=confirm_password_input
+                                        disabled=disable_control
+                                        required
+                                    />
+                                    <div class="hint">
+                                        "Minimum 8 characters. Include at least one of each: lowercase, uppercase, number, and special characters !@#$%^&*"
+                                    </div>
+                                    <div class="error">{error}</div>
+                                </div>
+                                <div class="text-center mt-8">
+                                    <button class="w-40" type="submit" disabled=disable_control>
+                                        "Change Password"
+                                    </button>
+                                </div>
+                            </div>
+                        </form>
+                    }
+                        .into_view(cx)
+                }
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,420 @@
+use leptos::{ev::SubmitEvent, html::Input, *};
+use leptos_router::*;
+
+use crate::{
+    components::util::{empty_view::EmptyView, loading::Loading, show_error::ShowError},
+    models::profile::Profile,
+};
+
+#[server(GetProfile, "/api")]
+pub async fn get_profile(cx: Scope) -> Result<Profile, ServerFnError> {
+    let (pool, user) = crate::server::policy::authorize(cx, "GetProfile")?;
+    crate::server::profile::get_profile(&user, &pool).await
+}
+
+#[server(UpdateProfileRequest, "/api")]
+pub async fn update_profile_request(
+    cx: Scope,
+    name: String,
+    phone: Option<String>,
+) -> Result<bool, ServerFnError> {
+    let (pool, user) = crate::server::policy::authorize(cx, "UpdateProfileRequest")?;
+    crate::server::profile::update_profile(&user, name, phone, &pool).await
+}
+
+#[server(ChangeEmailRequest, "/api")]
+pub async fn change_email_request(cx: Scope, email: String) -> Result<bool, ServerFnError> {
+    let (pool, user) = crate::server::policy::authorize(cx, "ChangeEmailRequest")?;
+    crate::server::profile::request_email_change(&user, email, &pool).await
+}
+
+#[server(ConfirmEmailChangeRequest, "/api")]
+pub async fn confirm_email_change_request(cx: Scope, token: String) -> Result<bool, ServerFnError> {
+    let pool = crate::pool(cx)?;
+    crate::server::profile::confirm_email_change(&token, &pool)
+        .await
+        .map(|user| user.is_some())
+}
+
+#[server(SetPasswordRequest, "/api")]
+pub async fn set_password_request(
+    cx: Scope,
+    current_password: String,
+    password: String,
+    confirm_password: String,
+) -> Result<(), ServerFnError> {
+    let (pool, user) = crate::server::policy::authorize(cx, "SetPasswordRequest")?;
+    let auth = crate::auth::auth(cx)?;
+    crate::server::profile::set_password(
+        &user,
+        current_password,
+        password,
+        confirm_password,
+        &pool,
+    )
+    .await?;
+    // Other sessions were ended with the old password, this one carries on
+    crate::auth::login(&auth, user.id, &pool).await
+}
+
+#[server(DeleteAccountRequest, "/api")]
+pub async fn delete_account_request(cx: Scope, email: String) -> Result<(), ServerFnError> {
+    let (pool, user) = crate::server::policy::authorize(cx, "DeleteAccountRequest")?;
+    let auth = crate::auth::auth(cx)?;
+    crate::server::profile::delete_account(&user, email, &pool).await?;
+    auth.logout_user();
+    Ok(())
+}
+
+#[component]
+pub fn ProfileView(cx: Scope) -> impl IntoView {
+    let update_profile_action = create_server_action::<UpdateProfileRequest>(cx);
+    let change_email_action = create_server_action::<ChangeEmailRequest>(cx);
+    let set_password_action = create_server_action::<SetPasswordRequest>(cx);
+    let delete_account_action = create_server_action::<DeleteAccountRequest>(cx);
+    let profile = create_resource(
+        cx,
+        move || {
+            (
+                update_profile_action.version().get(),
+                change_email_action.version().get(),
+                set_password_action.version().get(),
+            )
+        },
+        move |_| get_profile(cx),
+    );
+    view! { cx,
+        {move || match (delete_account_action.value().get(), profile.read(cx)) {
+            (Some(Ok(_)), _) => {
+                view! { cx,
+                    <div class="container">
+                        <h2 class="header">"Account Deleted"</h2>
+                        <div>"Your account has been deleted and you have been signed out."</div>
+                        <a class="button" rel="external" href="/">"Continue"</a>
+                    </div>
+                }
+                    .into_view(cx)
+            }
+            (_, None) => view! { cx, <Loading/> },
+            (_, Some(Err(e))) => view! { cx, <ShowError error=e.to_string()/> },
+            (_, Some(Ok(profile))) => {
+                view! { cx,
+                    <ProfileDetails profile=profile.clone() update_profile_action/>
+                    <ChangeEmail profile=profile.clone() change_email_action/>
+                    <SetPassword has_password=profile.has_password set_password_action/>
+                    {if profile.can_delete {
+                        view! { cx, <DeleteAccount delete_account_action/> }.into_view(cx)
+                    } else {
+                        view! { cx, <EmptyView/> }
+                    }}
+                }
+                    .into_view(cx)
+            }
+        }}
+    }
+}
+
+#[component]
+pub fn ProfileDetails(
+    cx: Scope,
+    profile: Profile,
+    update_profile_action: Action<UpdateProfileRequest, Result<bool, ServerFnError>>,
+) -> impl IntoView {
+    let name_input = create_node_ref::<Input>(cx);
+    let phone_input = create_node_ref::<Input>(cx);
+    let on_submit = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        let name = name_input
+            .get()
+            .expect("Name element should be present")
+            .value();
+        let phone = phone_input
+            .get()
+            .expect("Phone element should be present")
+            .value();
+        update_profile_action.dispatch(UpdateProfileRequest {
+            name,
+            phone: (!phone.is_empty()).then_some(phone),
+        });
+    };
+    let result = move || match update_profile_action.value().get() {
+        Some(Ok(_)) => view! { cx, <div class="hint">"Saved"</div> }.into_view(cx),
+        Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> },
+        None => view! { cx, <EmptyView/> },
+    };
+    let disable_control = move || update_profile_action.pending().get();
+    view! { cx,
+        <div class="container">
+            <h2 class="header">"Profile"</h2>
+            <form on:submit=on_submit>
+                <div class="flex flex-col text-left">
+                    <label for="profile_name">"Name"</label>
+                    <input
+                        id="profile_name"
+                        node_ref=name_input
+                        value=profile.name
+                        disabled=disable_control
+                        required
+                    />
+                    <label for="profile_phone">"Phone"</label>
+                    <input
+                        id="profile_phone"
+                        type="tel"
+                        node_ref=phone_input
+                        value=profile.phone.unwrap_or_default()
+                        disabled=disable_control
+                    />
+                    {result}
+                    <div class="text-center mt-8">
+                        <button class="w-40" type="submit" disabled=disable_control>
+                            "Save"
+                        </button>
+                    </div>
+                </div>
+            </form>
+        </div>
+    }
+}
+
+#[component]
+pub fn ChangeEmail(
+    cx: Scope,
+    profile: Profile,
+    change_email_action: Action<ChangeEmailRequest, Result<bool, ServerFnError>>,
+) -> impl IntoView {
+    let email_input = create_node_ref::<Input>(cx);
+    let on_submit = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        let email = email_input.get().expect("Email element should be present");
+        if !email.value().is_empty() {
+            change_email_action.dispatch(ChangeEmailRequest {
+                email: email.value(),
+            });
+            email.set_value("");
+        }
+    };
+    let error = move || match change_email_action.value().get() {
+        Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> },
+        _ => view! { cx, <EmptyView/> },
+    };
+    let disable_control = move || change_email_action.pending().get();
+    view! { cx,
+        <div class="container">
+            <h2 class="header">"Email"</h2>
+            <div>"Signed in as " <span class="font-bold">{profile.email}</span></div>
+            {match profile.pending_email {
+                Some(pending_email) => {
+                    view! { cx,
+                        <div class="hint">
+                            "Open the link we sent to " {pending_email} " to start using it."
+                        </div>
+                    }
+                        .into_view(cx)
+                }
+                None => view! { cx, <EmptyView/> },
+            }}
+            <form on:submit=on_submit>
+                <div class="flex flex-col text-left">
+                    <label for="profile_email">"New Email"</label>
+                    <input
+                        id="profile_email"
+                        type="email"
+                        node_ref=email_input
+                        disabled=disable_control
+                        required
+                    />
+                    {error}
+                    <div class="text-center mt-8">
+                        <button class="w-40" type="submit" disabled=disable_control>
+                            "Change Email"
+                        </button>
+                    </div>
+                </div>
+            </form>
+        </div>
+    }
+}
+
+#[component]
+pub fn SetPassword(
+    cx: Scope,
+    has_password: bool,
+    set_password_action: Action<SetPasswordRequest, Result<(), ServerFnError>>,
+) -> impl IntoView {
+    let current_password_input = create_node_ref::<Input>(cx);
+    let password_input = create_node_ref::<Input>(cx);
+    let confirm_password_input = create_node_ref::<Input>(cx);
+    let on_submit = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        let current_password = current_password_input
+            .get()
+            .map(|input| input.value())
+            .unwrap_or_default();
+        let password = password_input
+            .get()
+            .expect("Password element should be present")
+            .value();
+        let confirm_password = confirm_password_input
+            .get()
+            .expect("Confirm password element should be present")
+            .value();
+        set_password_action.dispatch(SetPasswordRequest {
+            current_password,
+            password,
+            confirm_password,
+        });
+    };
+    let result = move || match set_password_action.value().get() {
+        Some(Ok(_)) => {
+            view! { cx, <div class="hint">"Password saved, other sessions were signed out"</div> }
+                .into_view(cx)
+        }
+        Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> },
+        None => view! { cx, <EmptyView/> },
+    };
+    let disable_control = move || set_password_action.pending().get();
+    view! { cx,
+        <div class="container">
+            <h2 class="header">{if has_password { "Change Password" } else { "Set Password" }}</h2>
+            {if has_password {
+                view! { cx, <EmptyView/> }
+            } else {
+                view! { cx,
+                    <div class="hint">
+                        "You sign in with emailed codes. Set a password to sign in with it too."
+                    </div>
+                }
+                    .into_view(cx)
+            }}
+            <form on:submit=on_submit>
+                <div class="flex flex-col text-left">
+                    {if has_password {
+                        view! { cx,
+                            <label for="profile_current_password">"Current Password"</label>
+                            <input
+                                id="profile_current_password"
+                                type="password"
+                                node_ref=current_password_input
+                                disabled=disable_control
+                                required
+                            />
+                        }
+                            .into_view(cx)
+                    } else {
+                        view! { cx, <EmptyView/> }
+                    }}
+                    <label for="profile_password">"New Password"</label>
+                    <input
+                        id="profile_password"
+                        type="password"
+                        node_ref=password_input
+                        disabled=disable_control
+                        required
+                    />
+                    <label for="profile_confirm_password">"Confirm Password"</label>
+                    <input
+                        id="profile_confirm_password"
+                        type="password"
+                        node_ref=confirm_password_input
+                        disabled=disable_control
+                        required
+                    />
+                    <div class="hint">
+                        "Minimum 8 characters. Include at least one of each: lowercase, uppercase, number, and special characters !@#$%^&*"
+                    </div>
+                    {result}
+                    <div class="text-center mt-8">
+                        <button class="w-40" type="submit" disabled=disable_control>
+                            "Save Password"
+                        </button>
+                    </div>
+                </div>
+            </form>
+        </div>
+    }
+}
+
+#[component]
+pub fn DeleteAccount(
+    cx: Scope,
+    delete_account_action: Action<DeleteAccountRequest, Result<(), ServerFnError>>,
+) -> impl IntoView {
+    let email_input = create_node_ref::<Input>(cx);
+    let on_submit = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        let email = email_input
+            .get()
+            .expect("Email element should be present")
+            .value();
+        delete_account_action.dispatch(DeleteAccountRequest { email });
+    };
+    let error = move || match delete_account_action.value().get() {
+        Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> },
+        _ => view! { cx, <EmptyView/> },
+    };
+    let disable_control = move || delete_account_action.pending().get();
+    view! { cx,
+        <div class="container">
+            <h2 class="header">"Delete Account"</h2>
+            <div class="hint">
+                "Your name and contact details are removed straight away. Order records are kept without them and your photos are deleted under the usual retention rules."
+            </div>
+            <form on:submit=on_submit>
+                <div class="flex flex-col text-left">
+                    <label for="delete_email">"Type your email to confirm"</label>
+                    <input
+                        id="delete_email"
+                        type="email"
+                        node_ref=email_input
+                        disabled=disable_control
+                        required
+                    />
+                    {error}
+                    <div class="text-center mt-8">
+                        <button class="w-40 red" type="submit" disabled=disable_control>
+                            "Delete Account"
+                        </button>
+                    </div>
+                </div>
+            </form>
+        </div>
+    }
+}
+
+#[component]
+pub fn ConfirmEmailChange(cx: Scope) -> impl IntoView {
+    let params = use_params_map(cx);
+    let token = move || params.with(|params| params.get("token").cloned().unwrap_or_default());
+    let confirm_resource = create_resource(cx, token, move |token| {
+        confirm_email_change_request(cx, token)
+    });
+    view! { cx,
+        <div class="container">
+            <h2 class="header">"Email Change"</h2>
+            <Suspense fallback=move || {
+                view! { cx, <Loading/> }
+            }>
+                {move || match confirm_resource.read(cx) {
+                    None => view! { cx, <Loading/> }.into_view(cx),
+                    Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> },
+                    Some(Ok(true)) => {
+                        view! { cx,
+                            <div>"Your account now uses this email."</div>
+                            <a class="button" rel="external" href="/">"Continue"</a>
+                        }
+                            .into_view(cx)
+                    }
+                    Some(Ok(false)) => {
+                        view! { cx,
+                            <div class="error">
+                                "This link is invalid or has expired. Request a new one from your profile."
+                            </div>
+                            <a class="button" rel="external" href="/">"Back"</a>
+                        }
+                            .into_view(cx)
+                    }
+                }}
+            </Suspense>
+        </div>
+    }
+}
@@ -1,8 +1,7 @@
 use leptos::{ev::SubmitEvent, html::Input, *};
-use leptos_router::use_navigate;
 use serde::{Deserialize, Serialize};
 
-use crate::components::auth::login_otp::LoginOtpRequest;
+use crate::components::auth::verify_email::VerificationSent;
 
 #[derive(Serialize, Deserialize)]
 pub enum SignupResponse {
@@ -18,7 +17,7 @@ pub struct SignupForm {
     pub phone: Option<String>,
     pub password: Option<String>,
 }
-fn validate_password(password: String, confirm_password: String) -> Result<(), Vec<String>> {
+pub fn validate_password(password: String, confirm_password: String) -> Result<(), Vec<String>> {
     let mut error: Vec<String> = Vec::new();
     if password != confirm_password {
         error.push("Passwords don't match.".into());
@@ -58,7 +57,6 @@ fn validate_phone(phone: String) -> Result<Option<String>, Vec<String>> {
 #[server(SignupRequest, "/api")]
 pub async fn signup_request(cx: Scope, form: SignupForm) -> Result<SignupResponse, ServerFnError> {
     use crate::models::user::Role;
-    use totp_rs::*;
 
     let pool = crate::pool(cx)?;
     let response = sqlx::query_scalar!("SELECT COUNT(1) FROM `users` where email = ?", form.email)
@@ -69,33 +67,38 @@ pub async fn signup_request(cx: Scope, form: SignupForm) -> Result<SignupRespons
         return Ok(SignupResponse::EmailAlreadyUsed);
     };
 
-    let Secret::Encoded(otp_secret) = Secret::generate_secret().to_encoded() else {
-                return Err(ServerFnError::ServerError("Unable to generate OTP Secret".into()));
-            };
-    let password_hash = bcrypt::hash(form.password.unwrap_or(otp_secret.clone()), 12).unwrap();
+    // Accounts without a password sign in with emailed codes until they set one
+    let password_hash = form
+        .password
+        .map(|password| bcrypt::hash(password, 12).unwrap());
+
+    sqlx::query(
+        "INSERT INTO users (name, email, phone, password_hash, role) values (?, ?, ?, ?, ?)",
+    )
+    .bind(form.fullname)
+    .bind(form.email.clone())
+    .bind(form.phone)
+    .bind(password_hash)
+    .bind(Role::Anonymous)
+    .execute(&pool)
+    .await
+    .map_err(|e| ServerFnError::ServerError(e.to_string()))?;
 
-    sqlx::query!(
-                "INSERT INTO users (name, email, phone, password_hash,otp_secret, role) values (?, ?, ?, ?,?, ?)",
-                form.fullname,
-                form.email,
-                form.phone,
-                password_hash,
-                otp_secret,
-                Role::Anonymous
-            )
-            .execute(&pool)
-            .await
-            .map(|_| SignupResponse::Success)
-            .map_err(|e| ServerFnError::ServerError(e.to_string()))
+    let user = crate::models::user::User::get_by_username(form.email, &pool).await?;
+    // The account stays Anonymous until the emailed link is opened
+    if let Err(e) = crate::server::verification::send_verification_email(&user, &pool).await {
+        log!("Unable to send verification email: {:#?}", e);
+    }
+    Ok(SignupResponse::Success)
 }
 #[component]
 pub fn signup(
     cx: Scope,
     #[prop(default = true)] ask_password: bool,
-    #[prop(default = false)] otp_on_success: bool,
     #[prop(optional)] completed: Option<Action<(), ()>>,
 ) -> impl IntoView {
     let (errors, set_errors) = create_signal::<Vec<String>>(cx, Vec::new());
+    let (verification_sent, set_verification_sent) = create_signal::<Option<String>>(cx, None);
     let fullname_input = create_node_ref::<Input>(cx);
     let email_input = create_node_ref::<Input>(cx);
     let phone_input = create_node_ref::<Input>(cx);
@@ -105,7 +108,6 @@ pub fn signup(
     let signup_action_fn = move |form: &SignupForm| {
         let form = form.clone();
         async move {
-            let navigate = use_navigate(cx);
             match signup_request(cx, form.clone()).await {
                 Err(e) => {
                     let err_str = e.to_string();
@@ -114,22 +116,9 @@ pub fn signup(
                 Ok(result) => {
                     match result {
                         SignupResponse::Success => {
-                            if otp_on_success {
-                                let SignupForm { email, .. } = form;
-                                let url = format!(
-                                    "/otp?email={}&show_email=false",
-                                    email.replace('+', "%2b")
-                                );
-                                log!("url: {url}");
-                                let login_otp_request_action =
-                                    create_server_action::<LoginOtpRequest>(cx);
-                                login_otp_request_action.dispatch(LoginOtpRequest { email });
-                                _ = navigate(url.as_str(), Default::default());
-                            } else {
-                                _ = navigate("/", Default::default());
-                                if let Some(completed) = completed {
-                                    completed.dispatch(());
-                                }
+                            set_verification_sent.set(Some(form.email));
+                            if let Some(completed) = completed {
+                                completed.dispatch(());
                             }
                         }
                         SignupResponse::EmailAlreadyUsed => {
@@ -192,7 +181,14 @@ pub fn signup(
     let disable_controls = move || signup_action.pending().get();
 
     view! { cx,
-        <div class="container">
+        {move || {
+            verification_sent
+                .get()
+                .map(|email| view! { cx, <VerificationSent email/> })
+        }}
+        <div class="container" style:display=move || {
+            if verification_sent.get().is_some() { "none" } else { "block" }
+        }>
             <form on:submit=on_submit>
                 <div class="flex flex-col text-left">
                     <div class="flex flex-col">
@@ -0,0 +1,110 @@
+use leptos::*;
+use leptos_router::*;
+
+use crate::components::util::loading::Loading;
+
+#[server(VerifyEmailRequest, "/api")]
+pub async fn verify_email_request(cx: Scope, token: String) -> Result<bool, ServerFnError> {
+    let (pool, auth) = crate::server::pool_and_auth(cx)?;
+    match crate::server::verification::verify_email(&token, &pool).await? {
+        Some(user) => {
+            crate::auth::login(&auth, user.id, &pool).await?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+// Always succeeds so it cannot be used to find out which emails are registered
+#[server(ResendVerificationRequest, "/api")]
+pub async fn resend_verification_request(cx: Scope, email: String) -> Result<(), ServerFnError> {
+    use crate::models::user::{User, UserStatus};
+    let pool = crate::pool(cx)?;
+    if let Ok(user) = User::get_by_username(email, &pool).await {
+        if user.status == UserStatus::NotActivatedYet {
+            if let Err(e) =
+                crate::server::verification::send_verification_email(&user, &pool).await
+            {
+                log!("Unable to send verification email: {:#?}", e);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[component]
+pub fn VerificationSent(cx: Scope, email: String) -> impl IntoView {
+    let resend_action = create_server_action::<ResendVerificationRequest>(cx);
+    let resend_email = email.clone();
+    let button_title = move || {
+        if resend_action.pending().get() {
+            "Sending..."
+        } else if resend_action.value().get().is_some() {
+            "Sent, resend again"
+        } else {
+            "Resend email"
+        }
+    };
+    view! { cx,
+        <div class="container">
+            <h2 class="header">"Verify your email"</h2>
+            <div>"We sent a verification link to " <span class="font-bold">{email}</span> "."</div>
+            <div class="hint">"Open the link in the email to activate your account."</div>
+            <div class="text-center mt-8">
+                <button
+                    class="w-40"
+                    disabled=move || resend_action.pending().get()
+                    on:click=move |_| {
+                        resend_action
+                            .dispatch(ResendVerificationRequest {
+                                email: resend_email.clone(),
+                            })
+                    }
+                >
+                    {button_title}
+                </button>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+pub fn VerifyEmail(cx: Scope) -> impl IntoView {
+    let params = use_params_map(cx);
+    let token = move || {
+        params.with(|params| params.get("token").cloned().unwrap_or_default())
+    };
+    let verify_resource = create_resource(cx, token, move |token| verify_email_request(cx, token));
+    view! { cx,
+        <div class="container">
+            <h2 class="header">"Email Verification"</h2>
+            <Suspense fallback=move || {
+                view! { cx, <Loading/> }
+            }>
+                {move || match verify_resource.read(cx) {
+                    None => view! { cx, <Loading/> }.into_view(cx),
+                    Some(Err(e)) => {
+                        view! { cx, <div class="error">"Error encountered: " {e.to_string()}</div> }
+                            .into_view(cx)
+                    }
+                    Some(Ok(true)) => {
+                        view! { cx,
+                            <div>"Your email is verified and your account is active."</div>
+                            <a class="button" rel="external" href="/">"Continue"</a>
+                        }
+                            .into_view(cx)
+                    }
+                    Some(Ok(false)) => {
+                        view! { cx,
+                            <div class="error">
+                                "This link is invalid or has expired. Log in to request a new one."
+                            </div>
+                            <a class="button" rel="external" href="/">"Back"</a>
+                        }
+                            .into_view(cx)
+                    }
+                }}
+            </Suspense>
+        </div>
+    }
+}
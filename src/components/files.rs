@@ -1,2 +1,3 @@
 pub mod file_list;
+pub mod thumbnail;
 pub mod uploader;
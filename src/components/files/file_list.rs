@@ -4,7 +4,7 @@ use web_sys::MouseEvent;
 use crate::{
     components::{
         app::AuthUser,
-        files::uploader::UploaderMode,
+        files::{thumbnail::Thumbnail, uploader::UploaderMode},
         util::{empty_view::EmptyView, loading::Loading},
     },
     models::{
@@ -17,6 +17,12 @@ use crate::{
 
 #[server(GetFiles, "/api")]
 pub async fn get_files(cx: Scope, prefix: String) -> Result<Vec<String>, ServerFnError> {
+    use crate::server::access::{get_viewable_order, parse_storage_path};
+    let (pool, user) = crate::server::policy::authorize(cx, "GetFiles")?;
+    let Some((order_id, _, None)) = parse_storage_path(&prefix) else {
+        return Err(ServerFnError::Args("Invalid prefix".into()));
+    };
+    get_viewable_order(order_id, &user, &pool).await?;
     crate::server::storage::get_files(prefix).await
 }
 
@@ -26,8 +32,17 @@ pub async fn get_order_items(
     order: Order,
     mode: UploaderMode,
 ) -> Result<Vec<OrderItem>, ServerFnError> {
-    let pool = crate::pool(cx)?;
-    order.get_order_items(mode, &pool).await
+    let (pool, user) = crate::server::policy::authorize(cx, "GetOrderItems")?;
+    let order = crate::server::access::get_viewable_order(order.id, &user, &pool).await?;
+    let order_items = order.get_order_items(mode, &pool).await?;
+    if user.role == Role::Customer || user.role == Role::Anonymous {
+        Ok(order_items
+            .into_iter()
+            .map(|order_item| order_item.for_customer(order.status))
+            .collect())
+    } else {
+        Ok(order_items)
+    }
 }
 
 #[server(DeleteOrderItemRequest, "/api")]
@@ -36,8 +51,14 @@ pub async fn delete_order_item_request(
     order: Order,
     order_item: OrderItem,
 ) -> Result<UserOrder, ServerFnError> {
+    let (pool, user) = crate::server::policy::authorize(cx, "DeleteOrderItemRequest")?;
+    let order_id = order.id;
+    let (order, order_item) =
+        crate::server::access::get_changeable_order_item(order_item.id, &user, &pool).await?;
+    if order.id != order_id {
+        return Err(ServerFnError::Args("Invalid Order Id".into()));
+    }
     let mode = order_item.mode;
-    let pool = crate::pool(cx)?;
     let prefix = format!("/{:0>6}/{:?}", order.id, mode).to_lowercase();
     let path = format!("{prefix}/{}", order_item.file_name);
     crate::server::storage::delete_file(path).await?;
@@ -46,29 +67,6 @@ pub async fn delete_order_item_request(
     UserOrder::get_by_order_id(order.id, &pool).await
 }
 
-#[server(RefreshGetUrlsRequest, "/api")]
-pub async fn refresh_get_urls_request(
-    cx: Scope,
-    order_id: u64,
-    mode: UploaderMode,
-) -> Result<bool, ServerFnError> {
-    use super::uploader::get_mime_type;
-    use crate::server::storage::get_prefix;
-    let (pool, _) = crate::server::pool_and_auth(cx)?;
-    let order_items = OrderItem::get_order_items_by_order_id(order_id, mode, &pool).await?;
-    for order_item in order_items {
-        let file_name = order_item.file_name.clone();
-        let Ok(mime_type) = get_mime_type(file_name.clone()) else {
-                        return Err(ServerFnError::ServerError("Invalid file type".to_string()));
-                    };
-        let prefix = get_prefix(order_id, mode);
-        let get_url =
-            crate::server::storage::create_presigned_url(prefix, file_name, mime_type).await?;
-        _ = order_item.update_get_url(get_url, &pool).await;
-    }
-    Ok(true)
-}
-
 #[component]
 pub fn FileList(cx: Scope, order: Order, mode: UploaderMode) -> impl IntoView {
     let auth_user = use_context::<ReadSignal<AuthUser>>(cx).expect("Auth User should be present");
@@ -76,17 +74,11 @@ pub fn FileList(cx: Scope, order: Order, mode: UploaderMode) -> impl IntoView {
         .expect("Set Order Search should be present");
     let (to_delete, set_to_delete) = create_signal::<Option<OrderItem>>(cx, None);
     let delete_order_item_action = create_server_action::<DeleteOrderItemRequest>(cx);
-    let refresh_get_urls_action = create_server_action::<RefreshGetUrlsRequest>(cx);
     let order_id = order.id;
     let order_d = order.clone();
     let get_order_items = create_resource(
         cx,
-        move || {
-            (
-                delete_order_item_action.version().get(),
-                refresh_get_urls_action.version().get(),
-            )
-        },
+        move || delete_order_item_action.version().get(),
         move |_| {
             let order = order.clone();
             async move { get_order_items(cx, order, mode).await }
@@ -103,9 +95,13 @@ pub fn FileList(cx: Scope, order: Order, mode: UploaderMode) -> impl IntoView {
         (mode == UploaderMode::Original && role == Role::Operator)
             || (mode == UploaderMode::Processed && role == Role::Processor)
     };
-    let refresh_urls = move |_: MouseEvent| {
-        refresh_get_urls_action.dispatch(RefreshGetUrlsRequest { order_id, mode });
+    let allow_download_all = move || {
+        auth_user
+            .get()
+            .map(|user| user.role != Role::Customer && user.role != Role::Anonymous)
+            .unwrap_or(false)
     };
+    let download_all_url = format!("/archive/{order_id}/{:?}", mode).to_lowercase();
     let delete_dialog = create_node_ref::<Dialog>(cx);
     create_effect(cx, move |_| {
         let Some(Ok(order)) = delete_order_item_action.value().get() else {
@@ -119,7 +115,18 @@ pub fn FileList(cx: Scope, order: Order, mode: UploaderMode) -> impl IntoView {
             <Suspense fallback=move || {
                 view! { cx, <Loading/> }
             }>
-                <button on:click=refresh_urls>"Refresh Urls"</button>
+                {move || {
+                    if allow_download_all() {
+                        view! { cx,
+                            <a class="button" rel="external" href=download_all_url.clone()>
+                                "Download all"
+                            </a>
+                        }
+                            .into_view(cx)
+                    } else {
+                        view! { cx, <EmptyView/> }
+                    }
+                }}
                 <div class="flex flex-wrap">
                     {move || match get_order_items.read(cx) {
                         None => {
@@ -144,13 +151,15 @@ pub fn FileList(cx: Scope, order: Order, mode: UploaderMode) -> impl IntoView {
                                                     .get()
                                                     .expect("Delete Dialog should be present");
                                                 let get_url = order_item.clone().get_url;
+                                                let checksum = order_item.checksum.clone();
+                                                let thumbnail_item = order_item.clone();
                                                 let delete_click = move |_: MouseEvent| {
                                                     set_to_delete.set(Some(order_item.clone()));
                                                     _ = delete_dialog.show_modal();
                                                 };
                                                 view! { cx,
                                                     <div class="w-48 p-2">
-                                                        <img src=&get_url/>
+                                                        <Thumbnail order_item=thumbnail_item/>
                                                     </div>
                                                     <div class="w-48 p-2 flex flex-col gap-y-5">
                                                         {if allow_delete() {
@@ -162,9 +171,19 @@ pub fn FileList(cx: Scope, order: Order, mode: UploaderMode) -> impl IntoView {
                                                                 .into_view(cx)
                                                         } else {
                                                             view! { cx, <EmptyView/> }
-                                                        }} <a class="button" href=&get_url>
+                                                        }} <a class="button" rel="external" href=&get_url>
                                                             "Download"
                                                         </a>
+                                                        {checksum
+                                                            .map(|checksum| {
+                                                                view! { cx,
+                                                                    <div class="text-xs font-mono" title=checksum.clone()>
+                                                                        "SHA-256 " {checksum.chars().take(12).collect::<String>()}
+                                                                    </div>
+                                                                }
+                                                                    .into_view(cx)
+                                                            })
+                                                            .unwrap_or_else(|| view! { cx, <EmptyView/> })}
                                                     </div>
                                                 }
                                             })
@@ -186,7 +205,7 @@ pub fn FileList(cx: Scope, order: Order, mode: UploaderMode) -> impl IntoView {
                                         view! { cx, <EmptyView/> }
                                     }
                                     Some(order_item) => {
-                                        let get_url = order_item.get_url.clone();
+                                        let thumbnail_item = order_item.clone();
                                         let delete_dialog = delete_dialog
                                             .get()
                                             .expect("Delete dialog should be present");
@@ -201,7 +220,7 @@ pub fn FileList(cx: Scope, order: Order, mode: UploaderMode) -> impl IntoView {
                                         };
                                         view! { cx,
                                             <div class="text-lg">"Confirm delete"</div>
-                                            <img src=get_url/>
+                                            <Thumbnail order_item=thumbnail_item/>
                                             <button class="red" on:click=delete_click>
                                                 "Delete"
                                             </button>
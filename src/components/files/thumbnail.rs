@@ -0,0 +1,34 @@
+use leptos::*;
+
+use crate::models::order_item::OrderItem;
+
+#[component]
+pub fn Thumbnail(cx: Scope, order_item: OrderItem) -> impl IntoView {
+    let OrderItem {
+        file_name,
+        thumbnail_url,
+        preview_url,
+        preview_unavailable,
+        ..
+    } = order_item;
+    match thumbnail_url {
+        Some(thumbnail_url) => {
+            let preview_url = preview_url.unwrap_or(thumbnail_url.clone());
+            view! { cx,
+                <a class="p-0" href=preview_url target="_blank">
+                    <img class="w-full h-48 object-cover rounded" src=thumbnail_url alt=file_name/>
+                </a>
+            }
+            .into_view(cx)
+        }
+        None => view! { cx,
+            <div class="w-full h-48 flex flex-col justify-center rounded bg-slate-100">
+                <div class="italic text-stone-400">
+                    {if preview_unavailable { "No preview available" } else { "Preview not ready" }}
+                </div>
+                <div class="text-xs truncate">{file_name}</div>
+            </div>
+        }
+        .into_view(cx),
+    }
+}
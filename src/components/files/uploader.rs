@@ -1,24 +1,60 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::rc::Rc;
+
 use leptos::*;
 use serde::{Deserialize, Serialize};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{DragEvent, Request, RequestInit, RequestMode};
+use web_sys::{
+    CanvasRenderingContext2d, DragEvent, Event, FilePropertyBag, HtmlCanvasElement,
+    HtmlInputElement, ImageBitmap, ProgressEvent, XmlHttpRequest,
+};
 
-use crate::models::{order::Order, order_item::OrderItem, user_order::UserOrder};
+use crate::models::{
+    file_format::{FileFormat, FILE_FORMATS},
+    order::Order,
+    order_item::OrderItem,
+    user_order::UserOrder,
+};
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "ssr")] {
-        use crate::to_server_fn_error;
+        use crate::server::storage::get_order_item_path;
     }
 }
 
-#[server(GetPreSignedPutUrl, "/api")]
-pub async fn get_pre_signed_put_url(cx: Scope, path: String) -> Result<String, ServerFnError> {
-    crate::server::storage::create_presigned_put_url(path).await
+const MULTIPART_PART_SIZE: f64 = 8.0 * 1024.0 * 1024.0;
+const MULTIPART_CONCURRENCY: usize = 4;
+const UPLOAD_CONCURRENCY: usize = 3;
+const PUT_MAX_ATTEMPTS: u32 = 5;
+const RESIZE_QUALITY: f64 = 0.92;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UploadedPart {
+    pub part_number: u32,
+    pub etag: String,
+}
+
+/// Kept in localStorage while a multipart upload runs so a reload can resume it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MultipartProgress {
+    order_item: OrderItem,
+    upload_id: String,
+    parts: Vec<UploadedPart>,
+}
+
+#[server(GetMaxOriginalDimension, "/api")]
+pub async fn get_max_original_dimension(cx: Scope) -> Result<Option<u32>, ServerFnError> {
+    crate::server::policy::authorize(cx, "GetMaxOriginalDimension")?;
+    Ok(crate::server::uploads::get_max_original_dimension())
 }
 
 #[server(GetUserOrder, "/api")]
 pub async fn get_user_order(cx: Scope, id: u64) -> Result<UserOrder, ServerFnError> {
-    let pool = crate::pool(cx)?;
+    let (pool, user) = crate::server::policy::authorize(cx, "GetUserOrder")?;
+    crate::server::access::get_viewable_order(id, &user, &pool).await?;
     UserOrder::get_by_order_id(id, &pool).await
 }
 
@@ -28,42 +64,103 @@ pub async fn add_order_item_request(
     order: Order,
     mode: UploaderMode,
     file_name: String,
-    mime_type: String,
+    file_size: u64,
 ) -> Result<OrderItem, ServerFnError> {
-    let prefix = format!("/{:0>6}/{:?}", order.id, mode).to_lowercase();
-    let file_split = file_name.split('.');
-    let Some(ext) = file_split.last() else {
-        return Err(ServerFnError::Args("Invalid File Name".to_string()));
-    };
-    let file_name = format!(
-        "{}.{}",
-        uuid::Uuid::new_v4().as_hyphenated().to_string(),
-        ext
-    );
-    let pool = crate::pool(cx)?;
-    let response = get_remaining_uploads(cx, order.clone(), mode)
+    let (pool, user) = crate::server::policy::authorize(cx, "AddOrderItemRequest")?;
+    let order = crate::server::access::get_changeable_order(order.id, mode, &user, &pool).await?;
+    crate::server::uploads::create_order_item(&order, mode, file_name, file_size, &pool).await
+}
+
+#[server(ConfirmOrderItemUpload, "/api")]
+pub async fn confirm_order_item_upload(
+    cx: Scope,
+    order_item: OrderItem,
+) -> Result<bool, ServerFnError> {
+    let (pool, user) = crate::server::policy::authorize(cx, "ConfirmOrderItemUpload")?;
+    crate::server::access::get_changeable_order_item(order_item.id, &user, &pool).await?;
+    crate::server::uploads::confirm_upload(order_item.id, &pool).await
+}
+
+#[server(StartMultipartUpload, "/api")]
+pub async fn start_multipart_upload(
+    cx: Scope,
+    order_item: OrderItem,
+) -> Result<String, ServerFnError> {
+    let (pool, user) = crate::server::policy::authorize(cx, "StartMultipartUpload")?;
+    let (_, order_item) =
+        crate::server::access::get_pending_order_item(order_item.id, &user, &pool).await?;
+    let mime_type = get_mime_type(order_item.file_name.clone())?;
+    crate::server::storage::create_multipart_upload(get_order_item_path(&order_item), mime_type)
         .await
-        .map_err(to_server_fn_error)?;
-    if response < 1 {
-        return Err(ServerFnError::ServerError(
-            "No more uploads allowed".to_string(),
-        ));
-    }
-    let (get_url, put_url) =
-        crate::server::storage::create_presigned_url_pair(prefix, file_name.clone(), mime_type)
-            .await?;
-    order
-        .add_order_item(file_name.clone(), mode, get_url, put_url, &pool)
+}
+
+#[server(GetUploadPartUrls, "/api")]
+pub async fn get_upload_part_urls(
+    cx: Scope,
+    order_item: OrderItem,
+    upload_id: String,
+    part_numbers: Vec<u32>,
+) -> Result<Vec<(u32, String)>, ServerFnError> {
+    let (pool, user) = crate::server::policy::authorize(cx, "GetUploadPartUrls")?;
+    let (_, order_item) =
+        crate::server::access::get_pending_order_item(order_item.id, &user, &pool).await?;
+    crate::server::storage::create_presigned_upload_part_urls(
+        get_order_item_path(&order_item),
+        upload_id,
+        part_numbers,
+    )
+    .await
+}
+
+#[server(CompleteMultipartUpload, "/api")]
+pub async fn complete_multipart_upload(
+    cx: Scope,
+    order_item: OrderItem,
+    upload_id: String,
+    parts: Vec<UploadedPart>,
+) -> Result<bool, ServerFnError> {
+    let (pool, user) = crate::server::policy::authorize(cx, "CompleteMultipartUpload")?;
+    let (_, order_item) =
+        crate::server::access::get_pending_order_item(order_item.id, &user, &pool).await?;
+    crate::server::storage::complete_multipart_upload(
+        get_order_item_path(&order_item),
+        upload_id,
+        parts,
+    )
+    .await
+}
+
+#[server(AbortMultipartUpload, "/api")]
+pub async fn abort_multipart_upload(
+    cx: Scope,
+    order_item: OrderItem,
+    upload_id: String,
+) -> Result<bool, ServerFnError> {
+    let (pool, user) = crate::server::policy::authorize(cx, "AbortMultipartUpload")?;
+    let (_, order_item) =
+        crate::server::access::get_pending_order_item(order_item.id, &user, &pool).await?;
+    crate::server::storage::abort_multipart_upload(get_order_item_path(&order_item), upload_id)
         .await
 }
 
+#[server(CancelOrderItemUpload, "/api")]
+pub async fn cancel_order_item_upload(
+    cx: Scope,
+    order_item: OrderItem,
+) -> Result<bool, ServerFnError> {
+    let (pool, user) = crate::server::policy::authorize(cx, "CancelOrderItemUpload")?;
+    crate::server::access::get_changeable_order_item(order_item.id, &user, &pool).await?;
+    crate::server::uploads::cancel_upload(order_item.id, &pool).await
+}
+
 #[server(GetRemainingUploads, "/api")]
 pub async fn get_remaining_uploads(
     cx: Scope,
     order: Order,
     mode: UploaderMode,
 ) -> Result<u64, ServerFnError> {
-    let pool = crate::pool(cx)?;
+    let (pool, user) = crate::server::policy::authorize(cx, "GetRemainingUploads")?;
+    let order = crate::server::access::get_changeable_order(order.id, mode, &user, &pool).await?;
     order.remaining_order_items(mode, &pool).await
 }
 
@@ -73,7 +170,8 @@ pub async fn update_order_upload_status(
     order: Order,
     mode: UploaderMode,
 ) -> Result<u64, ServerFnError> {
-    let pool = crate::pool(cx)?;
+    let (pool, user) = crate::server::policy::authorize(cx, "UpdateOrderUploadStatus")?;
+    let order = crate::server::access::get_changeable_order(order.id, mode, &user, &pool).await?;
     order.set_uploaded_for_zero_remaining(mode, &pool).await
 }
 
@@ -86,149 +184,574 @@ pub enum FileUploadState {
 }
 
 pub fn get_mime_type(file_name: String) -> Result<String, ServerFnError> {
-    let splits: Vec<&str> = file_name.split('.').collect();
-    let Some(ext) = splits.last() else {
+    if !file_name.contains('.') {
         return Err(ServerFnError::Args("Invalid file name received.".to_string()));
-    };
-    let ext = ext.to_lowercase();
-    let ext = ext.as_str();
-
-    let mime_type = match ext {
-        "jpg" => "image/jpeg",
-        "jpeg" => "image/jpeg",
-        "png" => "image/png",
-        _ => "application/octet-stream",
-    };
+    }
+    let mime_type = FileFormat::from_file_name(&file_name)
+        .map(|format| format.mime_type())
+        .unwrap_or("application/octet-stream");
     Ok(mime_type.to_string())
 }
 
 pub type UploaderMode = crate::models::order_item::Mode;
 
+async fn sleep(millis: i32) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, millis);
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+fn is_online() -> bool {
+    web_sys::window()
+        .map(|window| window.navigator().on_line())
+        .unwrap_or(true)
+}
+
+// The same photo picked again after a reload matches on name, size and date
+fn upload_progress_key(order: &Order, mode: UploaderMode, file: &web_sys::File) -> String {
+    format!(
+        "multipart-upload:{}:{:?}:{}:{}:{}",
+        order.id,
+        mode,
+        file.name(),
+        file.size(),
+        file.last_modified()
+    )
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+fn load_progress(key: &str) -> Option<MultipartProgress> {
+    let value = local_storage()?.get_item(key).ok()??;
+    serde_json::from_str(&value).ok()
+}
+
+fn save_progress(key: &str, progress: &MultipartProgress) {
+    let (Some(storage), Ok(value)) = (local_storage(), serde_json::to_string(progress)) else {
+        return;
+    };
+    let _ = storage.set_item(key, &value);
+}
+
+fn clear_progress(key: &str) {
+    if let Some(storage) = local_storage() {
+        let _ = storage.remove_item(key);
+    }
+}
+
+async fn put_blob(
+    url: &str,
+    blob: &web_sys::Blob,
+    on_loaded: Rc<dyn Fn(f64)>,
+) -> Option<XmlHttpRequest> {
+    let xhr = XmlHttpRequest::new().ok()?;
+    xhr.open("PUT", url).ok()?;
+    let on_progress =
+        Closure::<dyn Fn(ProgressEvent)>::new(move |ev: ProgressEvent| on_loaded(ev.loaded()));
+    xhr.upload()
+        .ok()?
+        .set_onprogress(Some(on_progress.as_ref().unchecked_ref()));
+    let done = js_sys::Promise::new(&mut |resolve, _| {
+        xhr.set_onloadend(Some(&resolve));
+    });
+    xhr.send_with_opt_blob(Some(blob)).ok()?;
+    let _ = JsFuture::from(done).await;
+    drop(on_progress);
+    let status = xhr.status().ok()?;
+    if (200..300).contains(&status) {
+        Some(xhr)
+    } else {
+        error!("Upload to storage failed with status {status}");
+        None
+    }
+}
+
+async fn put_with_retry<F, Fut>(
+    name: &str,
+    get_url: F,
+    blob: &web_sys::Blob,
+    on_loaded: Rc<dyn Fn(f64)>,
+) -> Option<XmlHttpRequest>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Option<String>>,
+{
+    let mut attempts = 0;
+    loop {
+        while !is_online() {
+            sleep(1000).await;
+        }
+        on_loaded(0.0);
+        if let Some(url) = get_url().await {
+            if let Some(xhr) = put_blob(&url, blob, on_loaded.clone()).await {
+                return Some(xhr);
+            }
+        }
+        // Failures while the connection is down are waited out, not counted
+        if is_online() {
+            attempts += 1;
+        }
+        if attempts >= PUT_MAX_ATTEMPTS {
+            error!("Giving up on {name}");
+            return None;
+        }
+        sleep(1000 * 2_i32.pow(attempts)).await;
+    }
+}
+
+async fn upload_part(
+    cx: Scope,
+    order_item: &OrderItem,
+    upload_id: &str,
+    blob: &web_sys::Blob,
+    part_number: u32,
+    on_loaded: Rc<dyn Fn(f64)>,
+) -> Option<UploadedPart> {
+    let get_url = || async move {
+        get_upload_part_urls(
+            cx,
+            order_item.clone(),
+            upload_id.to_string(),
+            vec![part_number],
+        )
+        .await
+        .ok()
+        .and_then(|urls| urls.into_iter().next())
+        .map(|(_, url)| url)
+    };
+    let name = format!("part {part_number} of {}", order_item.file_name);
+    let xhr = put_with_retry(&name, get_url, blob, on_loaded).await?;
+    let etag = xhr.get_response_header("ETag").ok().flatten()?;
+    Some(UploadedPart { part_number, etag })
+}
+
+fn part_range(part_number: u32, size: f64) -> (f64, f64) {
+    let start = (part_number - 1) as f64 * MULTIPART_PART_SIZE;
+    (start, (start + MULTIPART_PART_SIZE).min(size))
+}
+
+async fn upload_multipart(
+    cx: Scope,
+    file: web_sys::File,
+    progress_key: &str,
+    progress: MultipartProgress,
+    on_loaded: Rc<dyn Fn(f64)>,
+) -> bool {
+    let (order_item, upload_id) = (progress.order_item.clone(), progress.upload_id.clone());
+    let size = file.size();
+    let part_count = (size / MULTIPART_PART_SIZE).ceil().max(1.0) as u32;
+    let mut part_loaded = vec![0.0; part_count as usize];
+    for part in &progress.parts {
+        let (start, end) = part_range(part.part_number, size);
+        part_loaded[part.part_number as usize - 1] = end - start;
+    }
+    let queue = (1..=part_count)
+        .filter(|part_number| {
+            !progress
+                .parts
+                .iter()
+                .any(|part| part.part_number == *part_number)
+        })
+        .collect::<VecDeque<u32>>();
+    let queue = Rc::new(RefCell::new(queue));
+    let part_loaded = Rc::new(RefCell::new(part_loaded));
+    let progress = Rc::new(RefCell::new(progress));
+    let failed = Rc::new(Cell::new(false));
+    save_progress(progress_key, &progress.borrow());
+    let workers = (0..MULTIPART_CONCURRENCY).map(|_| {
+        let (file, order_item, upload_id) = (file.clone(), order_item.clone(), upload_id.clone());
+        let (queue, progress, part_loaded, failed) = (
+            queue.clone(),
+            progress.clone(),
+            part_loaded.clone(),
+            failed.clone(),
+        );
+        let on_loaded = on_loaded.clone();
+        async move {
+            while !failed.get() {
+                let Some(part_number) = queue.borrow_mut().pop_front() else {
+                    break;
+                };
+                let (start, end) = part_range(part_number, size);
+                let Ok(blob) = file.slice_with_f64_and_f64(start, end) else {
+                    failed.set(true);
+                    break;
+                };
+                let on_part_loaded = {
+                    let (part_loaded, on_loaded) = (part_loaded.clone(), on_loaded.clone());
+                    Rc::new(move |loaded: f64| {
+                        part_loaded.borrow_mut()[part_number as usize - 1] = loaded;
+                        on_loaded(part_loaded.borrow().iter().sum());
+                    })
+                };
+                match upload_part(
+                    cx,
+                    &order_item,
+                    &upload_id,
+                    &blob,
+                    part_number,
+                    on_part_loaded,
+                )
+                .await
+                {
+                    Some(part) => {
+                        progress.borrow_mut().parts.push(part);
+                        save_progress(progress_key, &progress.borrow());
+                    }
+                    None => failed.set(true),
+                }
+            }
+        }
+    });
+    futures::future::join_all(workers).await;
+
+    let parts = progress.borrow().parts.clone();
+    let completed = !failed.get()
+        && complete_multipart_upload(cx, order_item.clone(), upload_id.clone(), parts)
+            .await
+            .unwrap_or(false);
+    if !completed {
+        // Releases the parts already stored for this upload
+        let _ = abort_multipart_upload(cx, order_item, upload_id).await;
+    }
+    completed
+}
+
+async fn upload_file(
+    cx: Scope,
+    file: web_sys::File,
+    order_item: OrderItem,
+    progress_key: &str,
+    resumed: Option<MultipartProgress>,
+    on_loaded: Rc<dyn Fn(f64)>,
+) -> bool {
+    if file.size() > MULTIPART_PART_SIZE {
+        let progress = match resumed {
+            Some(progress) => progress,
+            None => match start_multipart_upload(cx, order_item.clone()).await {
+                Ok(upload_id) => MultipartProgress {
+                    order_item,
+                    upload_id,
+                    parts: Vec::new(),
+                },
+                Err(_) => return false,
+            },
+        };
+        return upload_multipart(cx, file, progress_key, progress, on_loaded).await;
+    }
+    // A plain slice drops the file's type so no Content-Type header is sent
+    let Ok(blob) = file.slice() else {
+        return false;
+    };
+    let put_url = order_item.put_url.clone();
+    let get_url = || {
+        let put_url = put_url.clone();
+        async move { Some(put_url) }
+    };
+    put_with_retry(&order_item.file_name, get_url, &blob, on_loaded)
+        .await
+        .is_some()
+}
+
+async fn resize_original(file: web_sys::File, max_dimension: u32) -> web_sys::File {
+    // Only formats every browser can decode and re-encode are resized
+    match FileFormat::from_file_name(&file.name()) {
+        Some(format @ (FileFormat::Jpeg | FileFormat::Png)) => {
+            resize_image(&file, format, max_dimension)
+                .await
+                .unwrap_or(file)
+        }
+        _ => file,
+    }
+}
+
+async fn resize_image(
+    file: &web_sys::File,
+    format: FileFormat,
+    max_dimension: u32,
+) -> Option<web_sys::File> {
+    let window = web_sys::window()?;
+    let bitmap = JsFuture::from(window.create_image_bitmap_with_blob(file).ok()?)
+        .await
+        .ok()?
+        .dyn_into::<ImageBitmap>()
+        .ok()?;
+    let (width, height) = (bitmap.width(), bitmap.height());
+    let too_large = file.size() as u64 > format.max_size();
+    if width.max(height) <= max_dimension && !too_large {
+        return None;
+    }
+    let scale = (max_dimension as f64 / width.max(height) as f64).min(1.0);
+    let canvas = window
+        .document()?
+        .create_element("canvas")
+        .ok()?
+        .dyn_into::<HtmlCanvasElement>()
+        .ok()?;
+    canvas.set_width((width as f64 * scale).round() as u32);
+    canvas.set_height((height as f64 * scale).round() as u32);
+    let context = canvas
+        .get_context("2d")
+        .ok()??
+        .dyn_into::<CanvasRenderingContext2d>()
+        .ok()?;
+    context
+        .draw_image_with_image_bitmap_and_dw_and_dh(
+            &bitmap,
+            0.0,
+            0.0,
+            canvas.width() as f64,
+            canvas.height() as f64,
+        )
+        .ok()?;
+    bitmap.close();
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let encoded = canvas.to_blob_with_type_and_encoder_options(
+            &resolve,
+            format.mime_type(),
+            &JsValue::from_f64(RESIZE_QUALITY),
+        );
+        if encoded.is_err() {
+            let _ = resolve.call1(&JsValue::NULL, &JsValue::NULL);
+        }
+    });
+    let blob = JsFuture::from(promise)
+        .await
+        .ok()?
+        .dyn_into::<web_sys::Blob>()
+        .ok()?;
+    log!(
+        "Resized {} from {width}x{height} to {}x{}",
+        file.name(),
+        canvas.width(),
+        canvas.height()
+    );
+    let mut options = FilePropertyBag::new();
+    options.type_(format.mime_type());
+    web_sys::File::new_with_blob_sequence_and_options(
+        &js_sys::Array::of1(&blob),
+        &file.name(),
+        &options,
+    )
+    .ok()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct FileUpload {
+    id: usize,
+    name: String,
+    state: FileUploadState,
+    loaded: f64,
+    size: f64,
+}
+
 #[component]
 pub fn Uploader(
     cx: Scope,
     order: Order,
     mode: UploaderMode,
     order_resource: Resource<Option<UserOrder>, Result<Option<Order>, ServerFnError>>,
+    #[prop(default = UPLOAD_CONCURRENCY)] concurrency: usize,
+    #[prop(default = None)] max_dimension: Option<u32>,
 ) -> impl IntoView {
     let set_order = use_context::<WriteSignal<Option<UserOrder>>>(cx)
         .expect("Set Order Search should be present");
-    let (files_to_upload, set_files_to_upload) =
-        create_signal(cx, Vec::<(String, FileUploadState)>::new());
-    let upload_file = move |file: web_sys::File, url: String| async move {
-        let form_data = web_sys::FormData::new().expect("Form Data should create");
-        form_data
-            .append_with_blob_and_filename(&file.name(), &file, &file.name())
-            .expect("Form Data append should work");
-        form_data
-            .set_with_str("content-type", &file.type_())
-            .expect("Form Data set with str should work");
-        let mut opts = RequestInit::new();
-        opts.method("PUT");
-        opts.mode(RequestMode::Cors);
-        let buffer = JsFuture::from(file.array_buffer())
-            .await
-            .expect("JS Future for buffer should resolve");
-        opts.body(Some(&buffer));
-        let request =
-            Request::new_with_str_and_init(&url, &opts).expect("Request init should work");
-        let window = web_sys::window().expect("Window should work");
-        _ = wasm_bindgen_futures::JsFuture::from(window.fetch_with_request(&request))
-            .await
-            .expect("JS Future await should work");
+    let (files_to_upload, set_files_to_upload) = create_signal(cx, Vec::<FileUpload>::new());
+    let set_state = move |id: usize, state: FileUploadState| {
+        set_files_to_upload.update(|f| {
+            if let Some(elem) = f.iter_mut().find(|elem| elem.id == id) {
+                elem.state = state;
+            }
+        });
     };
-
-    let on_drop = move |ev: DragEvent| {
-        let mode = mode;
-        let order = order.clone();
-        ev.prevent_default();
-        let dt = ev.data_transfer().unwrap();
-        let items = dt.items();
-        let c = items.length();
-        let mut files = Vec::new();
-        for i in 0..c {
-            let item = items.get(i);
-            if let Some(item) = item {
-                if item.kind() == *"file" {
-                    if let Ok(Some(file)) = item.get_as_file() {
-                        set_files_to_upload.update(|f| {
-                            f.push((file.name(), FileUploadState::Added));
-                        });
-                        files.push(file);
+    let set_loaded = move |id: usize, loaded: f64| {
+        set_files_to_upload.update(|f| {
+            if let Some(elem) = f.iter_mut().find(|elem| elem.id == id) {
+                elem.loaded = loaded.min(elem.size);
+            }
+        });
+    };
+    let set_file_size = move |id: usize, size: f64| {
+        set_files_to_upload.update(|f| {
+            if let Some(elem) = f.iter_mut().find(|elem| elem.id == id) {
+                elem.size = size;
+            }
+        });
+    };
+    let upload_one = move |id: usize, file: web_sys::File, order: Order| async move {
+        // Keyed on the picked file, as resizing gives the upload a new date
+        let progress_key = upload_progress_key(&order, mode, &file);
+        let file = match (mode, max_dimension) {
+            (UploaderMode::Original, Some(max_dimension)) => {
+                let file = resize_original(file, max_dimension).await;
+                set_file_size(id, file.size());
+                file
+            }
+            _ => file,
+        };
+        let file_name = file.name();
+        let file_size = file.size() as u64;
+        if let Err(e) = FileFormat::for_upload(&file_name, mode, file_size) {
+            log!("{e}");
+            set_state(id, FileUploadState::Error);
+            return;
+        }
+        set_state(id, FileUploadState::Uploading);
+        let resumed = load_progress(&progress_key);
+        let order_item = match &resumed {
+            Some(progress) => progress.order_item.clone(),
+            None => {
+                match add_order_item_request(cx, order.clone(), mode, file_name, file_size).await {
+                    Ok(order_item) => order_item,
+                    Err(e) => {
+                        error!("{:#?}", e);
+                        set_state(id, FileUploadState::Error);
+                        return;
                     }
                 }
             }
+        };
+        let on_loaded = Rc::new(move |loaded: f64| set_loaded(id, loaded));
+        let uploaded = upload_file(
+            cx,
+            file,
+            order_item.clone(),
+            &progress_key,
+            resumed,
+            on_loaded,
+        )
+        .await
+            && match confirm_order_item_upload(cx, order_item.clone()).await {
+                Ok(confirmed) => confirmed,
+                Err(e) => {
+                    error!("{:#?}", e);
+                    false
+                }
+            };
+        if uploaded {
+            set_state(id, FileUploadState::Done);
+        } else {
+            // Frees the slot taken by the pending item so it can be uploaded again
+            let _ = cancel_order_item_upload(cx, order_item).await;
+            set_state(id, FileUploadState::Error);
         }
-        spawn_local(async move {
-            for file in files.into_iter() {
-                let file_name = file.name();
-                let Ok(mime_type) = get_mime_type(file_name.clone()) else {
-                    log!("Invalid file extension");
-                    return;
+        clear_progress(&progress_key);
+        if let Ok(count) = update_order_upload_status(cx, order.clone(), mode).await {
+            if count > 0 {
+                if let Ok(user_order) = get_user_order(cx, order.id).await {
+                    set_order.set(Some(user_order));
+                    order_resource.refetch();
                 };
+            }
+        };
+    };
+
+    let order = store_value(cx, order);
+    let enqueue_files = move |files: Vec<web_sys::File>| {
+        let first_id = files_to_upload.get_untracked().len();
+        let files = files
+            .into_iter()
+            .enumerate()
+            .map(|(i, file)| {
+                let id = first_id + i;
                 set_files_to_upload.update(|f| {
-                    for elem in f.iter_mut() {
-                        if elem.0 == file_name.clone() {
-                            *elem = (file_name.clone(), FileUploadState::Uploading);
-                        }
-                    }
+                    f.push(FileUpload {
+                        id,
+                        name: file.name(),
+                        state: FileUploadState::Added,
+                        loaded: 0.0,
+                        size: file.size(),
+                    });
                 });
-                match add_order_item_request(cx, order.clone(), mode, file_name.clone(), mime_type)
-                    .await
-                {
-                    Ok(OrderItem { put_url, .. }) => {
-                        upload_file(file, put_url).await;
-                        set_files_to_upload.update(|f| {
-                            for elem in f.iter_mut() {
-                                if elem.0 == file_name.clone() {
-                                    *elem = (file_name.clone(), FileUploadState::Done);
-                                }
-                            }
-                        });
-                    }
-                    Err(e) => {
-                        error!("{:#?}", e);
-                        set_files_to_upload.update(|f| {
-                            for elem in f.iter_mut() {
-                                if elem.0 == file_name.clone() {
-                                    *elem = (file_name.clone(), FileUploadState::Error);
-                                }
-                            }
-                        });
-                    }
-                };
-                if let Ok(count) = update_order_upload_status(cx, order.clone(), mode).await {
-                    if count > 0 {
-                        if let Ok(user_order) = get_user_order(cx, order.clone().id).await {
-                            set_order.set(Some(user_order));
-                            order_resource.refetch();
+                (id, file)
+            })
+            .collect::<VecDeque<_>>();
+        let queue = Rc::new(RefCell::new(files));
+        let workers = (0..concurrency.max(1))
+            .map(|_| {
+                let queue = queue.clone();
+                async move {
+                    loop {
+                        let Some((id, file)) = queue.borrow_mut().pop_front() else {
+                            break;
                         };
+                        upload_one(id, file, order.get_value()).await;
                     }
-                };
-            }
+                }
+            })
+            .collect::<Vec<_>>();
+        spawn_local(async move {
+            futures::future::join_all(workers).await;
         });
     };
+
+    let on_drop = move |ev: DragEvent| {
+        ev.prevent_default();
+        let Some(dt) = ev.data_transfer() else {
+            return;
+        };
+        let items = dt.items();
+        let files = (0..items.length())
+            .filter_map(|i| items.get(i))
+            .filter(|item| item.kind() == *"file")
+            .filter_map(|item| item.get_as_file().ok().flatten())
+            .collect::<Vec<_>>();
+        enqueue_files(files);
+    };
+    let on_files_selected = move |ev: Event| {
+        let input = event_target::<HtmlInputElement>(&ev);
+        let Some(file_list) = input.files() else {
+            return;
+        };
+        let files = (0..file_list.length())
+            .filter_map(|i| file_list.get(i))
+            .collect::<Vec<_>>();
+        // Clearing the input lets the same file be picked again after an error
+        input.set_value("");
+        enqueue_files(files);
+    };
+    let accept = FILE_FORMATS
+        .into_iter()
+        .filter(|format| format.allowed_modes().contains(&mode))
+        .flat_map(|format| format.extensions().iter().map(|ext| format!(".{ext}")))
+        .collect::<Vec<_>>()
+        .join(",");
     let file_list = move || {
         files_to_upload
             .get()
-            .iter()
-            .map(move |(file, state)| {
-                let state = state.to_owned();
+            .into_iter()
+            .map(move |FileUpload { name, state, loaded, size, .. }| {
+                let progress = if size > 0.0 { (loaded / size * 100.0).round() } else { 0.0 };
                 view! { cx,
                 <div>
-                    <span>{file}</span>
+                    <span>{name}</span>
                     {move || match state {
                         FileUploadState::Added => {
                             view! { cx, <span class="italic text-stone-400">" added"</span> }
+                                .into_view(cx)
                         }
                         FileUploadState::Uploading => {
-                            view! { cx, <span class="italic text-green-400">" uploading"</span> }
+                            view! { cx,
+                                <progress class="mx-2" max=size value=loaded></progress>
+                                <span class="italic text-green-400">
+                                    {format!(" uploading {progress}%")}
+                                </span>
+                            }
+                                .into_view(cx)
                         }
                         FileUploadState::Done => {
                             view! { cx, <span class="italic text-green-400">" done"</span> }
+                                .into_view(cx)
                         }
                         FileUploadState::Error => {
                             view! { cx, <span class="italic text-red-400">" error"</span> }
+                                .into_view(cx)
                         }
                     }}
                 </div>
@@ -250,6 +773,28 @@ pub fn Uploader(
                     {file_list}
                 </div>
             </div>
+            <div class="flex flex-row justify-center gap-x-5">
+                <label class="button">
+                    "Choose Files"
+                    <input
+                        class="hidden"
+                        type="file"
+                        multiple
+                        accept=accept
+                        on:change=on_files_selected
+                    />
+                </label>
+                <label class="button">
+                    "Take Photo"
+                    <input
+                        class="hidden"
+                        type="file"
+                        accept="image/*"
+                        capture="environment"
+                        on:change=on_files_selected
+                    />
+                </label>
+            </div>
         </div>
     }
 }
@@ -6,9 +6,12 @@ use crate::{
         app::AuthUser,
         auth::login_otp::LoginOtp,
         auth::logout::Logout,
+        auth::profile::ProfileView,
         auth::signup::Signup,
         error_template::ErrorTemplate,
-        manager::{reports::Reports, settings::Settings, users::Users},
+        manager::{
+            reconciliation::Reconciliation, reports::Reports, settings::Settings, users::Users,
+        },
         orders::orders_view::OrdersView,
         processor::processor_view::ProcessorView,
         search::search_view::SearchView,
@@ -63,6 +66,7 @@ pub enum HomePageViews {
     SearchOrders,
     ProcessOrders,
     Settings,
+    Profile,
 }
 
 #[component]
@@ -133,6 +137,7 @@ pub fn HomePage(cx: Scope) -> impl IntoView {
                                                 view! { cx,
                                                     <Settings/>
                                                     <Reports/>
+                                                    <Reconciliation/>
                                                     <Users/>
                                                 }
                                                     .into_view(cx)
@@ -140,6 +145,9 @@ pub fn HomePage(cx: Scope) -> impl IntoView {
                                             HomePageViews::ProcessOrders => {
                                                 view! { cx, <ProcessorView/> }
                                             }
+                                            HomePageViews::Profile => {
+                                                view! { cx, <ProfileView/> }
+                                            }
                                             HomePageViews::Loading => {
                                                 view! { cx,
                                                     <div class="container">
@@ -193,7 +201,7 @@ pub fn HomePage(cx: Scope) -> impl IntoView {
                                             }
                                             ActiveView::Signup => {
                                                 view! { cx,
-                                                    <Signup otp_on_success=true ask_password=false/>
+                                                    <Signup ask_password=false/>
                                                 }
                                             }
                                         }}
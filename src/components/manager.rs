@@ -1,3 +1,4 @@
+pub mod reconciliation;
 pub mod reports;
 pub mod settings;
 pub mod users;
@@ -0,0 +1,254 @@
+use leptos::{
+    html::{Dialog, Input},
+    *,
+};
+use web_sys::MouseEvent;
+
+use crate::{
+    components::util::{loading::Loading, show_error::ShowError},
+    models::reconciliation::{OrderReconciliation, ReconcileMode},
+};
+
+#[server(ReconcileStorage, "/api")]
+pub async fn reconcile_storage(
+    cx: Scope,
+    order_id: Option<u64>,
+    verify_checksums: bool,
+) -> Result<Vec<OrderReconciliation>, ServerFnError> {
+    let (pool, _) = crate::server::policy::authorize(cx, "ReconcileStorage")?;
+    crate::server::reconcile::reconcile(order_id, ReconcileMode::Report, verify_checksums, &pool)
+        .await
+}
+
+#[server(RepairStorage, "/api")]
+pub async fn repair_storage(
+    cx: Scope,
+    reviewed: Vec<OrderReconciliation>,
+    mode: ReconcileMode,
+    verify_checksums: bool,
+) -> Result<Vec<OrderReconciliation>, ServerFnError> {
+    let (pool, _) = crate::server::policy::authorize(cx, "RepairStorage")?;
+    crate::server::reconcile::repair_reviewed(reviewed, mode, verify_checksums, &pool).await
+}
+
+#[component]
+pub fn Reconciliation(cx: Scope) -> impl IntoView {
+    let reconcile_action = create_server_action::<ReconcileStorage>(cx);
+    let repair_action = create_server_action::<RepairStorage>(cx);
+    let (repair_mode, set_repair_mode) = create_signal(cx, ReconcileMode::Repair);
+    let order_no_input = create_node_ref::<Input>(cx);
+    let verify_checksums_input = create_node_ref::<Input>(cx);
+    let repair_conf = create_node_ref::<Dialog>(cx);
+    let verify_checksums = move || {
+        verify_checksums_input
+            .get()
+            .map(|input| input.checked())
+            .unwrap_or(false)
+    };
+    let check = move |_: MouseEvent| {
+        let order_id = order_no_input
+            .get()
+            .and_then(|input| input.value().parse::<u64>().ok());
+        reconcile_action.dispatch(ReconcileStorage {
+            order_id,
+            verify_checksums: verify_checksums(),
+        });
+    };
+    let reviewed = move || match reconcile_action.value().get() {
+        Some(Ok(reports)) if !reports.is_empty() => Some(reports),
+        _ => None,
+    };
+    let show_conf = move |mode: ReconcileMode| {
+        set_repair_mode.set(mode);
+        let dialog = repair_conf.get().expect("Repair Dialog should be present");
+        _ = dialog.show_modal();
+    };
+    let close_conf = move |_: MouseEvent| {
+        let dialog = repair_conf.get().expect("Repair Dialog should be present");
+        dialog.close();
+    };
+    let confirm_repair = move |_: MouseEvent| {
+        if let Some(reviewed) = reviewed() {
+            repair_action.dispatch(RepairStorage {
+                reviewed,
+                mode: repair_mode.get(),
+                verify_checksums: verify_checksums(),
+            });
+        }
+    };
+    let disable_controls =
+        move || reconcile_action.pending().get() || repair_action.pending().get();
+    create_effect(cx, move |_| {
+        if repair_action.value().get().is_some() {
+            let dialog = repair_conf.get().expect("Repair Dialog should exist");
+            dialog.close();
+        }
+    });
+    view! { cx,
+        <div class="container-lg">
+            <h2 class="header">"Storage Reconciliation"</h2>
+            <div class="flex flex-row justify-between">
+                <label class="p-2" for="reconcile_order_no">
+                    "Order # (blank for all)"
+                </label>
+                <input id="reconcile_order_no" _ref=order_no_input type="number" min="1"/>
+            </div>
+            <div class="flex flex-row justify-between">
+                <label class="p-2" for="reconcile_verify_checksums">
+                    "Verify checksums"
+                </label>
+                <input id="reconcile_verify_checksums" _ref=verify_checksums_input type="checkbox"/>
+            </div>
+            <div class="flex flex-row justify-between">
+                <button on:click=check disabled=disable_controls>"Check"</button>
+                {move || {
+                    reviewed()
+                        .map(|_| {
+                            view! { cx,
+                                <button
+                                    on:click=move |_| show_conf(ReconcileMode::Repair)
+                                    disabled=disable_controls
+                                >
+                                    "Repair"
+                                </button>
+                                <button
+                                    on:click=move |_| show_conf(ReconcileMode::Quarantine)
+                                    disabled=disable_controls
+                                >
+                                    "Quarantine"
+                                </button>
+                            }
+                        })
+                }}
+            </div>
+            <dialog _ref=repair_conf>
+                <h2>
+                    {move || match repair_mode.get() {
+                        ReconcileMode::Quarantine => "Quarantine orphaned objects and repair the ",
+                        _ => "Delete orphaned objects and repair the ",
+                    }}
+                    {move || reviewed().map(|reports| reports.len()).unwrap_or_default()}
+                    " orders in the report?"
+                </h2>
+                <button on:click=confirm_repair disabled=disable_controls>
+                    "Confirm"
+                </button>
+                <button on:click=close_conf class="red" disabled=disable_controls>
+                    "Cancel"
+                </button>
+            </dialog>
+            {move || {
+                if reconcile_action.pending().get() {
+                    return view! { cx, <Loading/> }.into_view(cx);
+                }
+                match reconcile_action.value().get() {
+                    None => ().into_view(cx),
+                    Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> }.into_view(cx),
+                    Some(Ok(reports)) => {
+                        if reports.is_empty() {
+                            view! { cx, <div>"Storage and database are in sync"</div> }.into_view(cx)
+                        } else {
+                            report_table(cx, reports)
+                        }
+                    }
+                }
+            }}
+            {move || {
+                if repair_action.pending().get() {
+                    return view! { cx, <Loading/> }.into_view(cx);
+                }
+                match repair_action.value().get() {
+                    None => ().into_view(cx),
+                    Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> }.into_view(cx),
+                    Some(Ok(reports)) => {
+                        view! { cx,
+                            <h3>"Repaired"</h3>
+                            {report_table(cx, reports)}
+                        }
+                            .into_view(cx)
+                    }
+                }
+            }}
+        </div>
+    }
+}
+
+fn report_table(cx: Scope, reports: Vec<OrderReconciliation>) -> View {
+    view! { cx,
+        <table class="table-auto w-full broder-collapse border border-slate-400">
+            <thead class="bg-slate-50">
+                <tr>
+                    <th class="border border-slate-300">"Order"</th>
+                    <th class="border border-slate-300">"Issue"</th>
+                    <th class="border border-slate-300">"Object"</th>
+                </tr>
+            </thead>
+            <tbody>
+                {reports
+                    .into_iter()
+                    .flat_map(|report| {
+                        let order_id = report.order_id;
+                        report
+                            .orphaned
+                            .into_iter()
+                            .map(|key| ("Orphaned".to_string(), key))
+                            .chain(
+                                report
+                                    .missing
+                                    .into_iter()
+                                    .map(|missing| ("Missing".to_string(), missing.path)),
+                            )
+                            .chain(
+                                report
+                                    .size_mismatches
+                                    .into_iter()
+                                    .map(|mismatch| {
+                                        let issue = format!(
+                                            "Size {} bytes, expected {}",
+                                            mismatch.actual, mismatch.expected
+                                        );
+                                        (issue, mismatch.path)
+                                    }),
+                            )
+                            .chain(
+                                report
+                                    .checksum_mismatches
+                                    .into_iter()
+                                    .map(|mismatch| ("Checksum mismatch".to_string(), mismatch.path)),
+                            )
+                            .chain(
+                                report
+                                    .duplicates
+                                    .into_iter()
+                                    .map(|duplicate| {
+                                        let issue = format!(
+                                            "Duplicate of item {}", duplicate.duplicate_of
+                                        );
+                                        (issue, duplicate.path)
+                                    }),
+                            )
+                            .chain(
+                                report
+                                    .errors
+                                    .into_iter()
+                                    .map(|error| ("Error".to_string(), error)),
+                            )
+                            .map(move |(issue, object)| (order_id, issue, object))
+                            .collect::<Vec<_>>()
+                    })
+                    .map(|(order_id, issue, object)| {
+                        view! { cx,
+                            <tr>
+                                <td class="border border-slate-300">{order_id}</td>
+                                <td class="border border-slate-300">{issue}</td>
+                                <td class="border border-slate-300 text-left">{object}</td>
+                            </tr>
+                        }
+                            .into_view(cx)
+                    })
+                    .collect_view(cx)}
+            </tbody>
+        </table>
+    }
+    .into_view(cx)
+}
@@ -2,7 +2,7 @@ use leptos::*;
 
 use crate::{
     components::util::{loading::Loading, show_error::ShowError},
-    models::report::{OrderCountByProcessor, OrderCountByStatus, PaymentCollection},
+    models::report::{OrderCountByProcessor, OrderCountByStatus, PaymentCollection, StorageByAge},
 };
 
 cfg_if::cfg_if! {
@@ -15,39 +15,26 @@ cfg_if::cfg_if! {
 pub async fn get_order_count_by_status_report(
     cx: Scope,
 ) -> Result<Vec<OrderCountByStatus>, ServerFnError> {
-    let (pool, user) = crate::server::pool_and_current_user(cx)?;
-    if user.role == crate::models::user::Role::Manager {
-        Report::get_order_count_by_status(&pool).await
-    } else {
-        Err(ServerFnError::ServerError(
-            "Only Managers can execute".into(),
-        ))
-    }
+    let (pool, _) = crate::server::policy::authorize(cx, "GetOrderCountByStatusReport")?;
+    Report::get_order_count_by_status(&pool).await
 }
 
 #[server(GetCollectionReport, "/api")]
 pub async fn get_collection_report(cx: Scope) -> Result<Vec<PaymentCollection>, ServerFnError> {
-    let (pool, user) = crate::server::pool_and_current_user(cx)?;
-    if user.role == crate::models::user::Role::Manager {
-        Report::get_collection_by_staff(&pool).await
-    } else {
-        Err(ServerFnError::ServerError(
-            "Only Managers can execute".into(),
-        ))
-    }
+    let (pool, _) = crate::server::policy::authorize(cx, "GetCollectionReport")?;
+    Report::get_collection_by_staff(&pool).await
 }
 
 #[server(GetProcessorReport, "/api")]
 pub async fn get_processor_report(cx: Scope) -> Result<Vec<OrderCountByProcessor>, ServerFnError> {
-    let (pool, user) = crate::server::pool_and_current_user(cx)?;
+    let (pool, _) = crate::server::policy::authorize(cx, "GetProcessorReport")?;
+    Report::get_order_count_by_processor(&pool).await
+}
 
-    if user.role == crate::models::user::Role::Manager {
-        Report::get_order_count_by_processor(&pool).await
-    } else {
-        Err(ServerFnError::ServerError(
-            "Only Managers can execute".into(),
-        ))
-    }
+#[server(GetStorageReport, "/api")]
+pub async fn get_storage_report(cx: Scope) -> Result<Vec<StorageByAge>, ServerFnError> {
+    let (pool, _) = crate::server::policy::authorize(cx, "GetStorageReport")?;
+    Report::get_storage_by_age(&pool).await
 }
 
 #[component]
@@ -56,6 +43,7 @@ pub fn Reports(cx: Scope) -> impl IntoView {
         create_resource(cx, || (), move |_| get_order_count_by_status_report(cx));
     let collection_report = create_resource(cx, || (), move |_| get_collection_report(cx));
     let processor_report = create_resource(cx, || (), move |_| get_processor_report(cx));
+    let storage_report = create_resource(cx, || (), move |_| get_storage_report(cx));
     view! { cx,
         <div class="container-lg">
             <h2 class="header">"Reports"</h2>
@@ -186,6 +174,52 @@ pub fn Reports(cx: Scope) -> impl IntoView {
                     }
                 }
             }}
+            <div class="text-lg">"Storage Report"</div>
+            {move || match storage_report.read(cx) {
+                None => view! { cx, <Loading/> },
+                Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> },
+                Some(Ok(report)) => {
+                    if report.is_empty() {
+                        view! { cx, <div>"No records found!"</div> }.into_view(cx)
+                    } else {
+                        view! { cx,
+                            <table class="table-auto w-full broder-collapse border border-slate-400">
+                                <thead class="bg-slate-50">
+                                    <tr>
+                                        <th class="border border-slate-300">"Mode"</th>
+                                        <th class="border border-slate-300">"Age"</th>
+                                        <th class="border border-slate-300">"Photos"</th>
+                                        <th class="border border-slate-300">"Size"</th>
+                                    </tr>
+                                </thead>
+                                <tbody>
+                                    {report
+                                        .into_iter()
+                                        .map(|report_item| {
+                                            view! { cx,
+                                                <tr>
+                                                    <td class="border border-slate-300">
+                                                        {format!("{:?}", report_item.mode)}
+                                                    </td>
+                                                    <td class="border border-slate-300">
+                                                        {report_item.age_label()}
+                                                    </td>
+                                                    <td class="border border-slate-300">{report_item.count}</td>
+                                                    <td class="border border-slate-300">
+                                                        {report_item.total_size()}
+                                                    </td>
+                                                </tr>
+                                            }
+                                                .into_view(cx)
+                                        })
+                                        .collect_view(cx)}
+                                </tbody>
+                            </table>
+                        }
+                            .into_view(cx)
+                    }
+                }
+            }}
         </div>
     }
 }
@@ -1,9 +1,10 @@
-use leptos::*;
+use leptos::{html::Input, *};
 
 use crate::components::{
     orders::orders_view::get_allow_order_creation_setting,
     util::{loading::Loading, show_error::ShowError},
 };
+use crate::models::setting::RetentionSettings;
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "ssr")] {
@@ -13,10 +14,25 @@ cfg_if::cfg_if! {
 
 #[server(ToggleAllowOrderCreation, "/api")]
 pub async fn toggle_allow_order_creation(cx: Scope, toggle: bool) -> Result<bool, ServerFnError> {
-    let pool = crate::pool(cx)?;
+    let (pool, _) = crate::server::policy::authorize(cx, "ToggleAllowOrderCreation")?;
     Setting::toggle_allow_order_creation(toggle, &pool).await
 }
 
+#[server(GetRetentionSettings, "/api")]
+pub async fn get_retention_settings(cx: Scope) -> Result<RetentionSettings, ServerFnError> {
+    let (pool, _) = crate::server::policy::authorize(cx, "GetRetentionSettings")?;
+    Setting::get_retention(&pool).await
+}
+
+#[server(SetRetentionSettings, "/api")]
+pub async fn set_retention_settings(
+    cx: Scope,
+    retention: RetentionSettings,
+) -> Result<bool, ServerFnError> {
+    let (pool, _) = crate::server::policy::authorize(cx, "SetRetentionSettings")?;
+    Setting::set_retention(retention, &pool).await
+}
+
 #[component]
 pub fn Settings(cx: Scope) -> impl IntoView {
     let toggle_allow_order_creation_action = create_server_action::<ToggleAllowOrderCreation>(cx);
@@ -25,6 +41,28 @@ pub fn Settings(cx: Scope) -> impl IntoView {
         move || toggle_allow_order_creation_action.version().get(),
         move |_| get_allow_order_creation_setting(cx),
     );
+    let set_retention_action = create_server_action::<SetRetentionSettings>(cx);
+    let retention_setting = create_resource(
+        cx,
+        move || set_retention_action.version().get(),
+        move |_| get_retention_settings(cx),
+    );
+    let original_days_input = create_node_ref::<Input>(cx);
+    let processed_days_input = create_node_ref::<Input>(cx);
+    let save_retention = move |_| {
+        let days = |input: NodeRef<Input>| {
+            input
+                .get()
+                .and_then(|input| input.value().parse::<u32>().ok())
+                .unwrap_or_default()
+        };
+        set_retention_action.dispatch(SetRetentionSettings {
+            retention: RetentionSettings {
+                original_days: days(original_days_input),
+                processed_days: days(processed_days_input),
+            },
+        });
+    };
     view! { cx,
         <div class="container">
             <h2 class="header">"Settings"</h2>
@@ -45,6 +83,41 @@ pub fn Settings(cx: Scope) -> impl IntoView {
                     }
                 }}
             </div>
+            <div class="font-bold">"Photo Retention (days after delivery, 0 keeps forever)"</div>
+            {move || match retention_setting.read(cx) {
+                None => view! { cx, <Loading/> },
+                Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> },
+                Some(Ok(retention)) => {
+                    view! { cx,
+                        <div class="flex flex-row justify-between">
+                            <label for="original_retention_days">"Originals"</label>
+                            <input
+                                id="original_retention_days"
+                                type="number"
+                                min="0"
+                                value=retention.original_days
+                                node_ref=original_days_input
+                            />
+                        </div>
+                        <div class="flex flex-row justify-between">
+                            <label for="processed_retention_days">"Processed"</label>
+                            <input
+                                id="processed_retention_days"
+                                type="number"
+                                min="0"
+                                value=retention.processed_days
+                                node_ref=processed_days_input
+                            />
+                        </div>
+                        <button on:click=save_retention>"Save"</button>
+                    }
+                        .into_view(cx)
+                }
+            }}
+            {move || match set_retention_action.value().get() {
+                Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> }.into_view(cx),
+                _ => ().into_view(cx),
+            }}
         </div>
     }
 }
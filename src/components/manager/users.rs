@@ -1,105 +1,712 @@
-use leptos::{html::Input, *};
-use web_sys::MouseEvent;
+use leptos::{
+    ev::SubmitEvent,
+    html::{Input, Select},
+    *,
+};
 
 use crate::{
     components::util::{empty_view::EmptyView, loading::Loading, show_error::ShowError},
-    models::user::{Role, User},
+    models::{
+        audit_log::AuditEntry,
+        invitation::Invitation,
+        login_attempt::AccountLockout,
+        user::{Role, UserStatus},
+        user_admin::{UserDetails, UserPage},
+    },
 };
 
-#[server(GetAllStaff, "/api")]
-pub async fn get_all_staff(cx: Scope) -> Result<Vec<User>, ServerFnError> {
-    let pool = crate::pool(cx)?;
-    User::get_all_staff(&pool).await
+const INVITE_ROLES: [Role; 4] = [
+    Role::Cashier,
+    Role::Operator,
+    Role::Processor,
+    Role::Manager,
+];
+
+const SEARCH_ROLES: [Role; 5] = [
+    Role::Customer,
+    Role::Cashier,
+    Role::Operator,
+    Role::Processor,
+    Role::Manager,
+];
+
+#[server(SearchUsers, "/api")]
+pub async fn search_users(
+    cx: Scope,
+    query: String,
+    role: Option<Role>,
+    page: u64,
+) -> Result<UserPage, ServerFnError> {
+    let (pool, _) = crate::server::policy::authorize(cx, "SearchUsers")?;
+    crate::server::user_admin::search_users(query, role, page, &pool).await
 }
 
-#[server(GetUserByEmail, "/api")]
-pub async fn get_user_by_email(cx: Scope, email: String) -> Result<Option<User>, ServerFnError> {
-    let pool = crate::pool(cx)?;
-    User::get_by_username(email, &pool).await.map(Some)
+#[server(GetUserDetails, "/api")]
+pub async fn get_user_details(cx: Scope, id: u64) -> Result<UserDetails, ServerFnError> {
+    let (pool, _) = crate::server::policy::authorize(cx, "GetUserDetails")?;
+    crate::server::user_admin::get_user_details(id, &pool).await
 }
 
 #[server(ChangeUserRole, "/api")]
 pub async fn change_user_role(cx: Scope, id: u64, role: Role) -> Result<bool, ServerFnError> {
-    let pool = crate::pool(cx)?;
-    User::change_role(id, role, &pool).await
+    let (pool, user) = crate::server::policy::authorize(cx, "ChangeUserRole")?;
+    crate::server::user_admin::change_role(&user, id, role, &pool).await
+}
+
+#[server(SetUserStatus, "/api")]
+pub async fn set_user_status(
+    cx: Scope,
+    id: u64,
+    status: UserStatus,
+) -> Result<bool, ServerFnError> {
+    let (pool, user) = crate::server::policy::authorize(cx, "SetUserStatus")?;
+    crate::server::user_admin::set_status(&user, id, status, &pool).await
+}
+
+#[server(UpdateUserProfile, "/api")]
+pub async fn update_user_profile(
+    cx: Scope,
+    id: u64,
+    name: String,
+    email: String,
+    phone: Option<String>,
+) -> Result<bool, ServerFnError> {
+    let (pool, user) = crate::server::policy::authorize(cx, "UpdateUserProfile")?;
+    crate::server::user_admin::update_profile(&user, id, name, email, phone, &pool).await
+}
+
+#[server(ForceLogout, "/api")]
+pub async fn force_logout(cx: Scope, id: u64) -> Result<u64, ServerFnError> {
+    let (pool, user) = crate::server::policy::authorize(cx, "ForceLogout")?;
+    crate::server::user_admin::force_logout(&user, id, &pool).await
+}
+
+#[server(InviteStaff, "/api")]
+pub async fn invite_staff(cx: Scope, email: String, role: Role) -> Result<bool, ServerFnError> {
+    use crate::{models::audit_log::AuditAction, server::user_admin::record};
+    let (pool, user) = crate::server::policy::authorize(cx, "InviteStaff")?;
+    let details = format!("{} as {:?}", email.trim().to_lowercase(), role);
+    let sent = crate::server::invitations::invite_staff(email, role, &user, &pool).await?;
+    record(&user, None, AuditAction::InviteStaff, details, &pool).await?;
+    Ok(sent)
+}
+
+#[server(GetPendingInvitations, "/api")]
+pub async fn get_pending_invitations(cx: Scope) -> Result<Vec<Invitation>, ServerFnError> {
+    let (pool, _) = crate::server::policy::authorize(cx, "GetPendingInvitations")?;
+    Invitation::get_pending(&pool).await
+}
+
+#[server(RevokeInvitation, "/api")]
+pub async fn revoke_invitation(cx: Scope, id: u64) -> Result<bool, ServerFnError> {
+    use crate::{models::audit_log::AuditAction, server::user_admin::record};
+    let (pool, user) = crate::server::policy::authorize(cx, "RevokeInvitation")?;
+    let revoked = Invitation::revoke(id, &pool).await?;
+    if revoked {
+        let details = format!("invitation {id}");
+        record(&user, None, AuditAction::RevokeInvitation, details, &pool).await?;
+    }
+    Ok(revoked)
+}
+
+#[server(GetLockouts, "/api")]
+pub async fn get_lockouts(cx: Scope) -> Result<Vec<AccountLockout>, ServerFnError> {
+    let (pool, _) = crate::server::policy::authorize(cx, "GetLockouts")?;
+    AccountLockout::get_active(&pool).await
+}
+
+#[server(UnlockAccount, "/api")]
+pub async fn unlock_account(cx: Scope, id: u64) -> Result<bool, ServerFnError> {
+    use crate::{models::audit_log::AuditAction, server::user_admin::record};
+    let (pool, user) = crate::server::policy::authorize(cx, "UnlockAccount")?;
+    let unlocked = crate::server::login_guard::unlock(id, user.id, &pool).await?;
+    if unlocked {
+        let details = format!("lockout {id}");
+        record(&user, None, AuditAction::UnlockAccount, details, &pool).await?;
+    }
+    Ok(unlocked)
 }
 
 #[component]
 pub fn Users(cx: Scope) -> impl IntoView {
-    let (email, set_email) = create_signal::<Option<String>>(cx, None);
+    let (search, set_search) = create_signal::<(String, Option<Role>)>(cx, (String::new(), None));
+    let (page, set_page) = create_signal(cx, 0u64);
+    let (selected, set_selected) = create_signal::<Option<u64>>(cx, None);
+    let query_input = create_node_ref::<Input>(cx);
+    let role_select = create_node_ref::<Select>(cx);
     let change_role_action = create_server_action::<ChangeUserRole>(cx);
-    let email_input = create_node_ref::<Input>(cx);
-    let all_staff = create_resource(
+    let set_status_action = create_server_action::<SetUserStatus>(cx);
+    let update_profile_action = create_server_action::<UpdateUserProfile>(cx);
+    let users = create_resource(
         cx,
-        move || (email.get(), change_role_action.version().get()),
-        move |(email, _)| async move {
-            match email {
-                None => get_all_staff(cx).await.map(Some),
-                Some(email) => get_user_by_email(cx, email)
-                    .await
-                    .map(|user| user.map(|user| vec![user])),
-            }
+        move || {
+            (
+                search.get(),
+                page.get(),
+                change_role_action.version().get(),
+                set_status_action.version().get(),
+                update_profile_action.version().get(),
+            )
         },
+        move |((query, role), page, ..)| search_users(cx, query, role, page),
     );
-    let search_click = move |_: MouseEvent| {
-        let email = email_input.get().expect("Email Input should be present");
-        if email.value().len() > 0 {
-            set_email.set(Some(email.value()));
-        }
-    };
-    let get_all_click = move |_: MouseEvent| {
-        let email = email_input.get().expect("Email Input should be present");
-        email.set_value("");
-        set_email.set(None);
+    let on_submit = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        let query = query_input.get().expect("Query Input should be present");
+        let role = role_select
+            .get()
+            .expect("Role Select should be present")
+            .selected_index();
+        // The first option lists every role
+        let role = usize::try_from(role - 1)
+            .ok()
+            .and_then(|role| SEARCH_ROLES.get(role).cloned());
+        set_page.set(0);
+        set_search.set((query.value(), role));
     };
     view! { cx,
         <div class="container-lg">
             <h2 class="header">"Users"</h2>
-            <div class="flex flex-row justify-between">
-                <input _ref=email_input placeholder="Enter Email..."/>
-                <div>
-                    <button on:click=search_click type="submit">"Search"</button>
-                    <button on:click=get_all_click>"Get All"</button>
+            <form on:submit=on_submit>
+                <div class="flex flex-row justify-between">
+                    <input _ref=query_input placeholder="Name, email or phone..."/>
+                    <select _ref=role_select>
+                        <option>"All roles"</option>
+                        {SEARCH_ROLES
+                            .iter()
+                            .map(|role| view! { cx, <option>{format!("{:?}", role)}</option> })
+                            .collect_view(cx)}
+                    </select>
+                    <button type="submit">"Search"</button>
                 </div>
-            </div>
-            {move || match all_staff.read(cx) {
+            </form>
+            {move || match users.read(cx) {
                 None => view! { cx, <Loading/> },
                 Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> },
-                Some(Ok(None)) => view! { cx, <div>"No records returned"</div> }.into_view(cx),
-                Some(Ok(Some(staff))) => {
+                Some(Ok(user_page)) if user_page.users.is_empty() => {
+                    view! { cx, <div>"No records returned"</div> }.into_view(cx)
+                }
+                Some(Ok(user_page)) => {
+                    let page_count = user_page.page_count();
+                    let current_page = user_page.page;
+                    let is_first_page = current_page == 0;
+                    let is_last_page = current_page + 1 >= page_count;
                     view! { cx,
-                        <table class="table-auto w-full broder-collapse border border-slate-400">
+                        <table class="table-auto w-full broder-collapse border border-slate-400 mt-4">
                             <thead class="bg-slate-50">
                                 <tr>
                                     <th class="border border-slate-300">"Name"</th>
                                     <th class="border border-slate-300">"Email"</th>
+                                    <th class="border border-slate-300">"Phone"</th>
                                     <th class="border border-slate-300">"Role"</th>
+                                    <th class="border border-slate-300">"Status"</th>
+                                    <th class="border border-slate-300"></th>
                                 </tr>
                             </thead>
                             <tbody>
-                                {staff
-                                    .iter()
+                                {user_page
+                                    .users
+                                    .into_iter()
                                     .map(|user| {
-                                        let user = user.clone();
+                                        let id = user.id;
                                         view! { cx,
                                             <tr>
                                                 <td class="border border-slate-300">{user.name}</td>
                                                 <td class="border border-slate-300">{user.email}</td>
+                                                <td class="border border-slate-300">
+                                                    {user.phone.unwrap_or_default()}
+                                                </td>
                                                 <td class="border border-slate-300">
                                                     {format!("{:?}", user.role)}
                                                 </td>
+                                                <td class="border border-slate-300">
+                                                    {format!("{:?}", user.status)}
+                                                </td>
+                                                <td class="border border-slate-300">
+                                                    <button on:click=move |_| set_selected.set(Some(id))>
+                                                        "Manage"
+                                                    </button>
+                                                </td>
+                                            </tr>
+                                        }
+                                    })
+                                    .collect_view(cx)}
+                            </tbody>
+                        </table>
+                        <div class="flex flex-row justify-between mt-2">
+                            <button
+                                disabled=is_first_page
+                                on:click=move |_| set_page.update(|page| *page = page.saturating_sub(1))
+                            >
+                                "Previous"
+                            </button>
+                            <div>
+                                {format!("Page {} of {} ({} users)", current_page + 1, page_count, user_page.total)}
+                            </div>
+                            <button
+                                disabled=is_last_page
+                                on:click=move |_| set_page.update(|page| *page += 1)
+                            >
+                                "Next"
+                            </button>
+                        </div>
+                    }
+                        .into_view(cx)
+                }
+            }}
+        </div>
+        {move || match selected.get() {
+            Some(id) => {
+                view! { cx,
+                    <UserAdmin
+                        id
+                        change_role_action
+                        set_status_action
+                        update_profile_action
+                        set_selected
+                    />
+                }
+                    .into_view(cx)
+            }
+            None => view! { cx, <EmptyView/> },
+        }}
+        <Invitations/>
+        <Lockouts/>
+    }
+}
+
+#[component]
+pub fn UserAdmin(
+    cx: Scope,
+    id: u64,
+    change_role_action: Action<ChangeUserRole, Result<bool, ServerFnError>>,
+    set_status_action: Action<SetUserStatus, Result<bool, ServerFnError>>,
+    update_profile_action: Action<UpdateUserProfile, Result<bool, ServerFnError>>,
+    set_selected: WriteSignal<Option<u64>>,
+) -> impl IntoView {
+    let force_logout_action = create_server_action::<ForceLogout>(cx);
+    let details = create_resource(
+        cx,
+        move || {
+            (
+                change_role_action.version().get(),
+                set_status_action.version().get(),
+                update_profile_action.version().get(),
+                force_logout_action.version().get(),
+            )
+        },
+        move |_| get_user_details(cx, id),
+    );
+    let name_input = create_node_ref::<Input>(cx);
+    let email_input = create_node_ref::<Input>(cx);
+    let phone_input = create_node_ref::<Input>(cx);
+    let on_submit = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        let value = |input: NodeRef<Input>| input.get().expect("Input should be present").value();
+        let phone = value(phone_input);
+        update_profile_action.dispatch(UpdateUserProfile {
+            id,
+            name: value(name_input),
+            email: value(email_input),
+            phone: (!phone.is_empty()).then_some(phone),
+        });
+    };
+    let action_error = move || {
+        [
+            change_role_action.value().get().map(|result| result.map(|_| ())),
+            set_status_action.value().get().map(|result| result.map(|_| ())),
+            update_profile_action.value().get().map(|result| result.map(|_| ())),
+            force_logout_action.value().get().map(|result| result.map(|_| ())),
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(|result| result.err())
+        .map(|e| e.to_string())
+        .unwrap_or_default()
+    };
+    view! { cx,
+        <div class="container-lg">
+            <div class="flex flex-row justify-between">
+                <h2 class="header">"Manage User"</h2>
+                <button on:click=move |_| set_selected.set(None)>"Close"</button>
+            </div>
+            <div class="error">{action_error}</div>
+            {move || match details.read(cx) {
+                None => view! { cx, <Loading/> },
+                Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> },
+                Some(Ok(details)) => {
+                    let user = details.user;
+                    let status = user.status.clone();
+                    view! { cx,
+                        <form on:submit=on_submit>
+                            <div class="flex flex-col text-left">
+                                <label for="admin_name">"Name"</label>
+                                <input id="admin_name" node_ref=name_input value=user.name required/>
+                                <label for="admin_email">"Email"</label>
+                                <input
+                                    id="admin_email"
+                                    type="email"
+                                    node_ref=email_input
+                                    value=user.email
+                                    required
+                                />
+                                <label for="admin_phone">"Phone"</label>
+                                <input
+                                    id="admin_phone"
+                                    node_ref=phone_input
+                                    value=user.phone.unwrap_or_default()
+                                />
+                                <div class="text-center mt-2">
+                                    <button
+                                        type="submit"
+                                        disabled=move || update_profile_action.pending().get()
+                                    >
+                                        "Save"
+                                    </button>
+                                </div>
+                            </div>
+                        </form>
+                        <div class="mt-4">"Role: " {format!("{:?}", user.role)}</div>
+                        <ChangeRoleButtons id role=user.role change_role_action/>
+                        <div class="mt-4">"Status: " {format!("{:?}", status)}</div>
+                        <div class="flex flex-row justify-around">
+                            {if status == UserStatus::Disabled {
+                                view! { cx,
+                                    <button on:click=move |_| {
+                                        set_status_action
+                                            .dispatch(SetUserStatus {
+                                                id,
+                                                status: UserStatus::Active,
+                                            })
+                                    }>"Enable"</button>
+                                }
+                                    .into_view(cx)
+                            } else {
+                                view! { cx,
+                                    <button
+                                        class="red"
+                                        on:click=move |_| {
+                                            set_status_action
+                                                .dispatch(SetUserStatus {
+                                                    id,
+                                                    status: UserStatus::Disabled,
+                                                })
+                                        }
+                                    >
+                                        "Disable"
+                                    </button>
+                                }
+                                    .into_view(cx)
+                            }}
+                            <button on:click=move |_| force_logout_action.dispatch(ForceLogout { id })>
+                                "Force Logout"
+                            </button>
+                        </div>
+                        <h3 class="header mt-4">"Orders"</h3>
+                        {if details.orders.is_empty() {
+                            view! { cx, <div>"No orders"</div> }.into_view(cx)
+                        } else {
+                            view! { cx,
+                                <table class="table-auto w-full broder-collapse border border-slate-400">
+                                    <thead class="bg-slate-50">
+                                        <tr>
+                                            <th class="border border-slate-300">"Order"</th>
+                                            <th class="border border-slate-300">"Photos"</th>
+                                            <th class="border border-slate-300">"Total"</th>
+                                            <th class="border border-slate-300">"Status"</th>
+                                        </tr>
+                                    </thead>
+                                    <tbody>
+                                        {details
+                                            .orders
+                                            .into_iter()
+                                            .map(|order| {
+                                                view! { cx,
+                                                    <tr>
+                                                        <td class="border border-slate-300">{order.id}</td>
+                                                        <td class="border border-slate-300">{order.no_of_photos}</td>
+                                                        <td class="border border-slate-300">{order.order_total}</td>
+                                                        <td class="border border-slate-300">
+                                                            {format!("{:?}", order.status)}
+                                                        </td>
+                                                    </tr>
+                                                }
+                                            })
+                                            .collect_view(cx)}
+                                    </tbody>
+                                </table>
+                            }
+                                .into_view(cx)
+                        }}
+                        <h3 class="header mt-4">"Staff Activity"</h3>
+                        {if details.handled_orders.is_empty() {
+                            view! { cx, <div>"No orders handled"</div> }.into_view(cx)
+                        } else {
+                            view! { cx,
+                                <table class="table-auto w-full broder-collapse border border-slate-400">
+                                    <thead class="bg-slate-50">
+                                        <tr>
+                                            <th class="border border-slate-300">"Order"</th>
+                                            <th class="border border-slate-300">"Handled As"</th>
+                                            <th class="border border-slate-300">"Status"</th>
+                                            <th class="border border-slate-300">"Created"</th>
+                                        </tr>
+                                    </thead>
+                                    <tbody>
+                                        {details
+                                            .handled_orders
+                                            .into_iter()
+                                            .map(|order| {
+                                                let handled_as = [
+                                                    (order.cashier_id, "Cashier"),
+                                                    (order.operator_id, "Operator"),
+                                                    (order.processor_id, "Processor"),
+                                                ]
+                                                    .into_iter()
+                                                    .filter(|(staff_id, _)| *staff_id == Some(id))
+                                                    .map(|(_, role)| role)
+                                                    .collect::<Vec<_>>()
+                                                    .join(", ");
+                                                view! { cx,
+                                                    <tr>
+                                                        <td class="border border-slate-300">{order.id}</td>
+                                                        <td class="border border-slate-300">{handled_as}</td>
+                                                        <td class="border border-slate-300">
+                                                            {format!("{:?}", order.status)}
+                                                        </td>
+                                                        <td class="border border-slate-300">
+                                                            {order.created_at.format("%Y-%m-%d %H:%M").to_string()}
+                                                        </td>
+                                                    </tr>
+                                                }
+                                            })
+                                            .collect_view(cx)}
+                                    </tbody>
+                                </table>
+                            }
+                                .into_view(cx)
+                        }}
+                        <h3 class="header mt-4">"Changes To This Account"</h3>
+                        <AuditTable entries=details.changes/>
+                        <h3 class="header mt-4">"Changes Made By This Account"</h3>
+                        <AuditTable entries=details.actions/>
+                    }
+                        .into_view(cx)
+                }
+            }}
+        </div>
+    }
+}
+
+#[component]
+pub fn AuditTable(cx: Scope, entries: Vec<AuditEntry>) -> impl IntoView {
+    if entries.is_empty() {
+        return view! { cx, <div>"No changes recorded"</div> }.into_view(cx);
+    }
+    view! { cx,
+        <table class="table-auto w-full broder-collapse border border-slate-400">
+            <thead class="bg-slate-50">
+                <tr>
+                    <th class="border border-slate-300">"When"</th>
+                    <th class="border border-slate-300">"By"</th>
+                    <th class="border border-slate-300">"Account"</th>
+                    <th class="border border-slate-300">"Action"</th>
+                    <th class="border border-slate-300">"Details"</th>
+                </tr>
+            </thead>
+            <tbody>
+                {entries
+                    .into_iter()
+                    .map(|entry| {
+                        view! { cx,
+                            <tr>
+                                <td class="border border-slate-300">
+                                    {entry.created_at.format("%Y-%m-%d %H:%M").to_string()}
+                                </td>
+                                <td class="border border-slate-300">
+                                    {entry.actor_name.unwrap_or("-".to_string())}
+                                </td>
+                                <td class="border border-slate-300">
+                                    {entry.target_email.unwrap_or("-".to_string())}
+                                </td>
+                                <td class="border border-slate-300">{format!("{:?}", entry.action)}</td>
+                                <td class="border border-slate-300">{entry.details}</td>
+                            </tr>
+                        }
+                    })
+                    .collect_view(cx)}
+            </tbody>
+        </table>
+    }
+        .into_view(cx)
+}
+
+#[component]
+pub fn Lockouts(cx: Scope) -> impl IntoView {
+    let unlock_action = create_server_action::<UnlockAccount>(cx);
+    let lockouts = create_resource(
+        cx,
+        move || unlock_action.version().get(),
+        move |_| get_lockouts(cx),
+    );
+    view! { cx,
+        <div class="container-lg">
+            <h2 class="header">"Locked Accounts"</h2>
+            <button on:click=move |_| lockouts.refetch()>"Refresh"</button>
+            {move || match lockouts.read(cx) {
+                None => view! { cx, <Loading/> },
+                Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> },
+                Some(Ok(lockouts)) if lockouts.is_empty() => {
+                    view! { cx, <div>"No locked accounts"</div> }.into_view(cx)
+                }
+                Some(Ok(lockouts)) => {
+                    view! { cx,
+                        <table class="table-auto w-full broder-collapse border border-slate-400 mt-4">
+                            <thead class="bg-slate-50">
+                                <tr>
+                                    <th class="border border-slate-300">"Account"</th>
+                                    <th class="border border-slate-300">"Address"</th>
+                                    <th class="border border-slate-300">"Failures"</th>
+                                    <th class="border border-slate-300">"Locked Until"</th>
+                                    <th class="border border-slate-300"></th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {lockouts
+                                    .into_iter()
+                                    .map(|lockout| {
+                                        let id = lockout.id;
+                                        view! { cx,
+                                            <tr>
+                                                <td class="border border-slate-300">
+                                                    {lockout.email.unwrap_or("-".to_string())}
+                                                </td>
+                                                <td class="border border-slate-300">
+                                                    {lockout.ip.unwrap_or_default()}
+                                                </td>
+                                                <td class="border border-slate-300">
+                                                    {lockout.failed_attempts}
+                                                </td>
+                                                <td class="border border-slate-300">
+                                                    {lockout.locked_until.format("%Y-%m-%d %H:%M").to_string()}
+                                                </td>
+                                                <td class="border border-slate-300">
+                                                    <button on:click=move |_| {
+                                                        unlock_action.dispatch(UnlockAccount { id })
+                                                    }>"Unlock"</button>
+                                                </td>
                                             </tr>
+                                        }
+                                    })
+                                    .collect_view(cx)}
+                            </tbody>
+                        </table>
+                    }
+                        .into_view(cx)
+                }
+            }}
+        </div>
+    }
+}
+
+#[component]
+pub fn Invitations(cx: Scope) -> impl IntoView {
+    let invite_action = create_server_action::<InviteStaff>(cx);
+    let revoke_action = create_server_action::<RevokeInvitation>(cx);
+    let email_input = create_node_ref::<Input>(cx);
+    let role_select = create_node_ref::<Select>(cx);
+    let invitations = create_resource(
+        cx,
+        move || (invite_action.version().get(), revoke_action.version().get()),
+        move |_| get_pending_invitations(cx),
+    );
+    let on_submit = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        let email = email_input.get().expect("Email Input should be present");
+        let role = role_select
+            .get()
+            .expect("Role Select should be present")
+            .selected_index();
+        let Some(role) = usize::try_from(role)
+            .ok()
+            .and_then(|role| INVITE_ROLES.get(role).cloned())
+        else {
+            return;
+        };
+        if !email.value().is_empty() {
+            invite_action.dispatch(InviteStaff {
+                email: email.value(),
+                role,
+            });
+            email.set_value("");
+        }
+    };
+    let invite_result = move || match invite_action.value().get() {
+        Some(Ok(_)) => view! { cx, <div class="hint">"Invitation sent"</div> }.into_view(cx),
+        Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> },
+        None => view! { cx, <EmptyView/> },
+    };
+    view! { cx,
+        <div class="container-lg">
+            <h2 class="header">"Invite Staff"</h2>
+            <form on:submit=on_submit>
+                <div class="flex flex-row justify-between">
+                    <input _ref=email_input type="email" placeholder="Enter Email..." required/>
+                    <select _ref=role_select>
+                        {INVITE_ROLES
+                            .iter()
+                            .map(|role| view! { cx, <option>{format!("{:?}", role)}</option> })
+                            .collect_view(cx)}
+                    </select>
+                    <button type="submit" disabled=move || invite_action.pending().get()>
+                        "Invite"
+                    </button>
+                </div>
+            </form>
+            {invite_result}
+            {move || match invitations.read(cx) {
+                None => view! { cx, <Loading/> },
+                Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> },
+                Some(Ok(invitations)) if invitations.is_empty() => {
+                    view! { cx, <div>"No pending invitations"</div> }.into_view(cx)
+                }
+                Some(Ok(invitations)) => {
+                    view! { cx,
+                        <table class="table-auto w-full broder-collapse border border-slate-400 mt-4">
+                            <thead class="bg-slate-50">
+                                <tr>
+                                    <th class="border border-slate-300">"Email"</th>
+                                    <th class="border border-slate-300">"Role"</th>
+                                    <th class="border border-slate-300">"Expires"</th>
+                                    <th class="border border-slate-300"></th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {invitations
+                                    .into_iter()
+                                    .map(|invitation| {
+                                        let id = invitation.id;
+                                        view! { cx,
                                             <tr>
-                                                <td colspan=3 class="border border-slate-300">
-                                                    <ChangeRoleButtons
-                                                        id=user.id
-                                                        role=user.role
-                                                        change_role_action
-                                                    />
+                                                <td class="border border-slate-300">{invitation.email}</td>
+                                                <td class="border border-slate-300">
+                                                    {format!("{:?}", invitation.role)}
+                                                </td>
+                                                <td class="border border-slate-300">
+                                                    {invitation.expires_at.format("%Y-%m-%d %H:%M").to_string()}
+                                                </td>
+                                                <td class="border border-slate-300">
+                                                    <button
+                                                        class="red"
+                                                        on:click=move |_| {
+                                                            revoke_action.dispatch(RevokeInvitation { id })
+                                                        }
+                                                    >
+                                                        "Revoke"
+                                                    </button>
                                                 </td>
                                             </tr>
                                         }
-                                            .into_view(cx)
                                     })
                                     .collect_view(cx)}
                             </tbody>
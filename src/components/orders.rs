@@ -3,4 +3,6 @@ pub mod confirmation;
 pub mod create_order;
 pub mod order_details;
 pub mod order_list;
+pub mod order_photos;
 pub mod orders_view;
+pub mod ticket;
@@ -12,12 +12,7 @@ use crate::{
 
 #[server(MarkPaidRequest, "/api")]
 pub async fn mark_paid_request(cx: Scope, order_id: u64) -> Result<UserOrder, ServerFnError> {
-    let (pool, user) = crate::server::pool_and_current_user(cx)?;
-    if user.role != Role::Cashier && user.role != Role::Manager {
-        return Err(ServerFnError::ServerError(
-            "Only Manager or Cashier can collect payment".to_string(),
-        ));
-    }
+    let (pool, user) = crate::server::policy::authorize(cx, "MarkPaidRequest")?;
     let success =
         crate::models::order::Order::collect_payment_cash(order_id, user.id, &pool).await?;
     if !success {
@@ -15,12 +15,7 @@ pub async fn mark_stripe_paid_request(
     cx: Scope,
     order_id: u64,
 ) -> Result<UserOrder, ServerFnError> {
-    let (pool, user) = crate::server::pool_and_current_user(cx)?;
-    if user.role != Role::Manager {
-        return Err(ServerFnError::ServerError(
-            "Only Manager can mark pending stripe payment to paid".to_string(),
-        ));
-    }
+    let (pool, user) = crate::server::policy::authorize(cx, "MarkStripePaidRequest")?;
     let manager_override = format!("Manager override by {},{}", user.name, user.email);
     let order = crate::models::order::Order::get_by_id(order_id, &pool)
         .await?
@@ -42,12 +37,7 @@ pub async fn clear_stripe_pending_status(
     cx: Scope,
     order_id: u64,
 ) -> Result<UserOrder, ServerFnError> {
-    let (pool, user) = crate::server::pool_and_current_user(cx)?;
-    if user.role != Role::Manager {
-        return Err(ServerFnError::ServerError(
-            "Only Manager can reset pending payment status".to_string(),
-        ));
-    }
+    let (pool, _) = crate::server::policy::authorize(cx, "ClearStripePendingStatus")?;
     let order = crate::models::order::Order::get_by_id(order_id, &pool)
         .await?
         .ok_or(ServerFnError::Args("Invalid Order Id provided".into()))?;
@@ -65,12 +55,7 @@ pub async fn status_change_request(
     from: OrderStatus,
     to: OrderStatus,
 ) -> Result<UserOrder, ServerFnError> {
-    let (pool, user) = crate::server::pool_and_current_user(cx)?;
-    if user.role != Role::Manager {
-        return Err(ServerFnError::ServerError(
-            "Only Manager is allowed to make this request".to_string(),
-        ));
-    }
+    let (pool, _) = crate::server::policy::authorize(cx, "StatusChangeRequest")?;
     crate::models::order::Order::update_status(order_id, from, to, &pool).await?;
     UserOrder::get_by_order_id(order_id, &pool).await
 }
@@ -19,7 +19,7 @@ pub async fn order_status_change_request(
     from: OrderStatus,
     to: OrderStatus,
 ) -> Result<UserOrder, ServerFnError> {
-    let pool = crate::pool(cx)?;
+    let (pool, _) = crate::server::policy::authorize(cx, "OrderStatusChangeRequest")?;
     let success = Order::update_status(order.id, from, to, &pool).await?;
     if !success {
         return Ok(order);
@@ -16,10 +16,15 @@ pub async fn store_stripe_confirmation(
         order_ref,
         payment_ref,
     } = params;
-    let pool = crate::pool(cx)?;
+    let (pool, user) = crate::server::policy::authorize(cx, "StoreStripeConfirmation")?;
     let order = crate::models::order::Order::get_by_order_confirmation(order_ref.clone(), &pool)
         .await?
         .ok_or(ServerFnError::Args("Invalid Order Reference".into()))?;
+    if !crate::server::access::can_view_order(&user, &order) {
+        return Err(ServerFnError::ServerError(
+            crate::server::policy::NOT_AUTHORIZED.to_string(),
+        ));
+    }
     let response = crate::models::order::Order::update_order_confirmation(
         order_ref.clone(),
         payment_ref,
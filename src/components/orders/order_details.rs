@@ -9,6 +9,7 @@ use crate::{
                 customer_actions::CustomerActions, manager_actions::ManagerActions,
                 operator_actions::OperatorActions,
             },
+            order_photos::OrderPhotos,
         },
         util::loading::Loading,
         util::not_authorized::NotAuthorized,
@@ -76,11 +77,15 @@ pub fn OrderDetails(cx: Scope, order: UserOrder) -> impl IntoView {
                     <CashierActions order=order.clone()/>
                     <OperatorActions order=order.clone()/>
                 </Suspense>
+                <a class="button" target="_blank" href=format!("/ticket/{}", order.id)>
+                    "Print Ticket"
+                </a>
                 <button type="button" on:click=move |_| set_order.set(None)>
                     "Back"
                 </button>
             </div>
         </div>
+        <OrderPhotos order=order.clone()/>
     }
     .into_view(cx)
 }
@@ -0,0 +1,78 @@
+use leptos::*;
+
+use crate::{
+    components::{
+        app::AuthUser,
+        files::{file_list::get_order_items, thumbnail::Thumbnail, uploader::UploaderMode},
+        search::search_view::get_order_request,
+        util::{empty_view::EmptyView, loading::Loading, show_error::ShowError},
+    },
+    models::{order::OrderStatus, user_order::UserOrder},
+};
+
+#[component]
+pub fn OrderPhotos(cx: Scope, order: UserOrder) -> impl IntoView {
+    let auth_user = use_context::<ReadSignal<AuthUser>>(cx).expect("AuthUser should be present");
+    let Some(user) = auth_user.get() else {
+        return view! { cx, <EmptyView/> };
+    };
+    if user.id != order.customer_id || order.status < OrderStatus::Uploaded {
+        return view! { cx, <EmptyView/> };
+    }
+    let order_id = order.id;
+    let mode = if order.status == OrderStatus::ReadyForDelivery {
+        UploaderMode::Processed
+    } else {
+        UploaderMode::Original
+    };
+    let order_items = create_resource(
+        cx,
+        || (),
+        move |_| async move {
+            match get_order_request(cx, order_id).await? {
+                None => Ok(Vec::new()),
+                Some(order) => get_order_items(cx, order, mode).await,
+            }
+        },
+    );
+    let download_all = move || {
+        if mode == UploaderMode::Processed {
+            view! { cx,
+                <a class="button" rel="external" href=format!("/archive/{order_id}/processed")>
+                    "Download all"
+                </a>
+            }
+                .into_view(cx)
+        } else {
+            view! { cx, <EmptyView/> }
+        }
+    };
+    view! { cx,
+        <div class="container">
+            <h2 class="header">"Your Photos"</h2>
+            {download_all}
+            {move || match order_items.read(cx) {
+                None => view! { cx, <Loading/> },
+                Some(Err(e)) => view! { cx, <ShowError error=e.to_string()/> },
+                Some(Ok(order_items)) => {
+                    if order_items.is_empty() {
+                        view! { cx, <div>"No photos available yet"</div> }.into_view(cx)
+                    } else {
+                        view! { cx,
+                            <div class="grid grid-cols-2 gap-2">
+                                {order_items
+                                    .into_iter()
+                                    .map(|order_item| {
+                                        view! { cx, <Thumbnail order_item/> }
+                                    })
+                                    .collect_view(cx)}
+                            </div>
+                        }
+                            .into_view(cx)
+                    }
+                }
+            }}
+        </div>
+    }
+    .into_view(cx)
+}
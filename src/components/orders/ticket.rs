@@ -0,0 +1,80 @@
+use leptos::{ev::MouseEvent, *};
+use leptos_router::*;
+
+use crate::{components::util::loading::Loading, models::ticket::OrderTicket};
+
+#[server(GetOrderTicket, "/api")]
+pub async fn get_order_ticket(cx: Scope, order_id: u64) -> Result<OrderTicket, ServerFnError> {
+    let (pool, user) = crate::server::pool_and_current_user(cx)?;
+    crate::server::tickets::get_ticket(order_id, &user, &pool).await
+}
+
+#[component]
+pub fn Ticket(cx: Scope) -> impl IntoView {
+    let params = use_params_map(cx);
+    let order_id = move || {
+        params.with(|params| {
+            params
+                .get("order_id")
+                .and_then(|order_id| order_id.parse::<u64>().ok())
+                .unwrap_or_default()
+        })
+    };
+    let ticket_resource = create_resource(cx, order_id, move |order_id| {
+        get_order_ticket(cx, order_id)
+    });
+    let on_print = move |_: MouseEvent| {
+        _ = window().print();
+    };
+    view! { cx,
+        <Suspense fallback=move || {
+            view! { cx, <Loading/> }
+        }>
+            <div class="container ticket">
+                {move || {
+                    match ticket_resource.read(cx) {
+                        None => view! { cx, <Loading/> }.into_view(cx),
+                        Some(Err(e)) => {
+                            view! { cx, <div class="error">"Error encountered: " {e.to_string()}</div> }
+                                .into_view(cx)
+                        }
+                        Some(Ok(ticket)) => {
+                            view! { cx,
+                                <h2 class="header">"Order #" {ticket.order_id}</h2>
+                                <div class="flex flex-row text-left">
+                                    <div class="w-1/2">"Name"</div>
+                                    <div class="font-bold">{ticket.name.clone()}</div>
+                                </div>
+                                <div class="flex flex-row text-left">
+                                    <div class="w-1/2">"No of Photos"</div>
+                                    <div class="font-bold">{ticket.no_of_photos}</div>
+                                </div>
+                                <div class="flex flex-row text-left">
+                                    <div class="w-1/2">"Order total"</div>
+                                    <div class="font-bold">"$" {ticket.order_total}</div>
+                                </div>
+                                <div class="flex flex-row text-left">
+                                    <div class="w-1/2">"Payment"</div>
+                                    <div class="font-bold">{ticket.payment_status()}</div>
+                                </div>
+                                <div class="qr-code" inner_html=ticket.qr_svg.clone()></div>
+                                <div>{ticket.reference.clone()}</div>
+                                <div class="no-print flex justify-around mt-4">
+                                    <button on:click=on_print>"Print"</button>
+                                    <a
+                                        class="button"
+                                        rel="external"
+                                        href=format!("/ticket/{}/pdf", ticket.order_id)
+                                    >
+                                        "Download PDF"
+                                    </a>
+                                </div>
+                            }
+                                .into_view(cx)
+                        }
+                    }
+                }}
+            </div>
+        </Suspense>
+    }
+}
@@ -29,16 +29,9 @@ pub async fn fetch_order_request(cx: Scope) -> Result<Option<Order>, ServerFnErr
     let (
         pool,
         crate::models::user::User {
-            id: processor_id,
-            role,
-            ..
+            id: processor_id, ..
         },
-    ) = crate::server::pool_and_current_user(cx)?;
-    if role != crate::models::user::Role::Processor {
-        return Err(ServerFnError::ServerError(
-            "Only processors are allowed to make this request".to_string(),
-        ));
-    }
+    ) = crate::server::policy::authorize(cx, "FetchOrderRequest")?;
     Order::fetch_order_for_processor(processor_id, &pool).await
 }
 
@@ -48,26 +41,12 @@ pub async fn mark_ready_for_delivery_request(cx: Scope) -> Result<bool, ServerFn
     let order = fetch_order_request(cx)
         .await?
         .ok_or(ServerFnError::Args("Invalid order".into()))?;
-    let response = order.mark_order_ready_for_delivery(&pool).await?;
-    match response {
-        false => Err(ServerFnError::ServerError(
+    if order.status != OrderStatus::Processed {
+        return Err(ServerFnError::ServerError(
             "Unable to update order status".to_string(),
-        )),
-        true => {
-            let customer = order.get_customer(&pool).await?;
-            let order_items = order
-                .get_order_items(crate::models::order_item::Mode::Processed, &pool)
-                .await?;
-            let to: String = customer.email.clone();
-            let name: String = customer.name.clone();
-            let links: Vec<String> = order_items
-                .into_iter()
-                .map(|order_item| order_item.get_url)
-                .collect();
-            _ = crate::server::mailer::send_processed(to, name, links).await;
-            Ok(true)
-        }
+        ));
     }
+    crate::server::delivery::complete_order(&order, &pool).await
 }
 
 #[component]
@@ -4,7 +4,7 @@ use crate::{
     components::{
         files::{
             file_list::FileList,
-            uploader::{Uploader, UploaderMode},
+            uploader::{get_max_original_dimension, Uploader, UploaderMode},
         },
         util::{empty_view::EmptyView, loading::Loading},
     },
@@ -17,6 +17,7 @@ pub fn OperatorUploader(
     cx: Scope,
     order_resource: Resource<Option<UserOrder>, Result<Option<Order>, ServerFnError>>,
 ) -> impl IntoView {
+    let max_dimension = create_resource(cx, || (), move |_| get_max_original_dimension(cx));
     view! { cx,
         <Suspense fallback=move || {
             view! { cx, <Loading/> }
@@ -34,8 +35,15 @@ pub fn OperatorUploader(
                                     <FileList order=order.clone() mode />
                                     {move || {
                                         if order.clone().status == OrderStatus::Uploading {
-                                            view! { cx,
-                                                <Uploader order=order.clone() mode order_resource/>
+                                            // Waits for the limit so no original skips the resize
+                                            match max_dimension.read(cx) {
+                                                None => view! { cx, <Loading/> },
+                                                Some(max_dimension) => {
+                                                    let max_dimension = max_dimension.ok().flatten();
+                                                    view! { cx,
+                                                        <Uploader order=order.clone() mode order_resource max_dimension/>
+                                                    }
+                                                }
                                             }
                                         } else {
                                             view! { cx, <EmptyView/> }
@@ -1,5 +1,8 @@
 use crate::components::search::search_view::OrderSearchRequest;
-use crate::models::user_order::{OrderSearchForm, UserOrder};
+use crate::models::{
+    order::parse_order_reference,
+    user_order::{OrderSearchForm, UserOrder},
+};
 use leptos::{ev::*, html::*, *};
 
 #[component]
@@ -11,6 +14,18 @@ pub fn OrderSearch(
     let name_input = create_node_ref::<Input>(cx);
     let email_input = create_node_ref::<Input>(cx);
     let phone_input = create_node_ref::<Input>(cx);
+    let set_order = use_context::<WriteSignal<Option<UserOrder>>>(cx)
+        .expect("Set Order Search should be present");
+    // A scanned ticket goes straight to its order instead of the results list
+    let jump_to_order = store_value(cx, false);
+    create_effect(cx, move |_| {
+        if let Some(Ok(orders)) = order_search_action.value().get() {
+            if jump_to_order.get_value() && orders.len() == 1 {
+                jump_to_order.set_value(false);
+                set_order.set(orders.into_iter().next());
+            }
+        }
+    });
     let on_submit = move |ev: SubmitEvent| {
         ev.prevent_default();
         let order_no = order_no_input
@@ -21,13 +36,16 @@ pub fn OrderSearch(
         let email = email_input.get().expect("Email should be present").value();
         let phone = phone_input.get().expect("Phone should be present").value();
         let order_no = if !order_no.is_empty() {
-            order_no.parse().ok()
+            parse_order_reference(&order_no)
         } else {
             None
         };
         let name = if !name.is_empty() { Some(name) } else { None };
         let email = if !email.is_empty() { Some(email) } else { None };
         let phone = if !phone.is_empty() { Some(phone) } else { None };
+        jump_to_order.set_value(
+            order_no.is_some() && name.is_none() && email.is_none() && phone.is_none(),
+        );
         if order_no.is_some() || name.is_some() || email.is_some() || phone.is_some() {
             let form = OrderSearchForm {
                 order_no,
@@ -47,7 +65,14 @@ pub fn OrderSearch(
                         <label class="p-2" for="customer_order_no">
                             "Order #"
                         </label>
-                        <input id="customer_order_no" _ref=order_no_input type="number" min="1"/>
+                        <input
+                            id="customer_order_no"
+                            _ref=order_no_input
+                            type="text"
+                            inputmode="numeric"
+                            placeholder="Number or scan ticket"
+                            autofocus
+                        />
                     </div>
                     <div class="flex justify-between">
                         <label class="p-2" for="customer_name">
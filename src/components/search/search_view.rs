@@ -19,8 +19,8 @@ use crate::{
 
 #[server(GetOrderRequest, "/api")]
 pub async fn get_order_request(cx: Scope, id: u64) -> Result<Option<Order>, ServerFnError> {
-    let pool = crate::pool(cx)?;
-    Order::get_by_id(id, &pool).await
+    let (pool, user) = crate::server::policy::authorize(cx, "GetOrderRequest")?;
+    crate::server::access::get_order_for_user(id, &user, &pool).await
 }
 
 #[server(OrderSearchRequest, "/api")]
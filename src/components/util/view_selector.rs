@@ -21,16 +21,22 @@ pub fn ViewSelector(cx: Scope, user: User) -> impl IntoView {
         (HomePageViews::SearchOrders, "Search Orders"),
         (HomePageViews::MyOrders, "My Orders"),
     ];
+    let profile_view = (HomePageViews::Profile, "Profile");
     let mut manager_views = vec![(HomePageViews::Settings, "Settings")];
     manager_views.extend(common_views.clone());
-    let cashier_views = common_views.clone();
-    let operator_views = common_views;
-    let processor_views = vec![(HomePageViews::ProcessOrders, "Process Orders")];
+    manager_views.push(profile_view);
+    let mut cashier_views = common_views.clone();
+    cashier_views.push(profile_view);
+    let mut operator_views = common_views;
+    operator_views.push(profile_view);
+    let processor_views = vec![(HomePageViews::ProcessOrders, "Process Orders"), profile_view];
+    let customer_views = vec![(HomePageViews::MyOrders, "My Orders"), profile_view];
     match user.role {
         Role::Manager => create_views(manager_views),
         Role::Cashier => create_views(cashier_views),
         Role::Operator => create_views(operator_views),
         Role::Processor => create_views(processor_views),
+        Role::Customer => create_views(customer_views),
         _ => view! {cx, <EmptyView /> },
     }
 }
@@ -23,7 +23,7 @@ cfg_if! {
 
         pub mod auth;
         pub mod server;
-        pub use server::{pool,get_totp_duration,to_server_fn_error};
+        pub use server::{pool,to_server_fn_error};
 
     }
 }
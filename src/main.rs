@@ -4,11 +4,16 @@ cfg_if! { if #[cfg(feature = "ssr")] {
     pub mod auth;
     pub mod components;
     pub mod models;
-    pub use server::{pool,get_totp_duration,to_server_fn_error};
+    pub use server::{pool,to_server_fn_error};
 
     #[tokio::main]
     async fn main() {
-        server::server_main().await
+        let args = std::env::args().skip(1).collect::<Vec<_>>();
+        if server::cli::is_command(&args) {
+            server::cli::run(args).await
+        } else {
+            server::server_main().await
+        }
     }
 }
 
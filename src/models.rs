@@ -1,7 +1,18 @@
+pub mod audit_log;
+pub mod file_format;
+pub mod ingest;
+pub mod invitation;
+pub mod login_attempt;
 pub mod order;
 pub mod order_item;
 pub mod pricing;
+pub mod profile;
+pub mod reconciliation;
 pub mod report;
 pub mod setting;
+pub mod ticket;
 pub mod user;
+pub mod user_admin;
 pub mod user_order;
+pub mod user_session;
+pub mod user_token;
@@ -0,0 +1,91 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use chrono::Local;
+        use leptos::ServerFnError;
+        use sqlx::{FromRow, MySqlPool, Type};
+        use crate::to_server_fn_error;
+    } else {
+        use dummy_macros::*;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize, Type)]
+#[repr(i8)]
+pub enum AuditAction {
+    ChangeRole = 1,
+    DisableUser = 2,
+    EnableUser = 3,
+    UpdateProfile = 4,
+    ForceLogout = 5,
+    InviteStaff = 6,
+    RevokeInvitation = 7,
+    UnlockAccount = 8,
+}
+
+// Entries outlive the users they mention, deleting a user only clears the ids
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AuditEntry {
+    pub id: u64,
+    pub actor_id: Option<u64>,
+    pub actor_name: Option<String>,
+    pub target_user_id: Option<u64>,
+    pub target_email: Option<String>,
+    pub action: AuditAction,
+    pub details: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[cfg(feature = "ssr")]
+impl AuditEntry {
+    pub async fn create(
+        actor_id: u64,
+        target_user_id: Option<u64>,
+        action: AuditAction,
+        details: String,
+        pool: &MySqlPool,
+    ) -> Result<u64, ServerFnError> {
+        sqlx::query("INSERT INTO `audit_log` (`actor_id`, `target_user_id`, `action`, `details`, `created_at`) VALUES (?, ?, ?, ?, ?)")
+            .bind(actor_id)
+            .bind(target_user_id)
+            .bind(action)
+            .bind(details)
+            .bind(Local::now())
+            .execute(pool)
+            .await
+            .map(|result| result.last_insert_id())
+            .map_err(to_server_fn_error)
+    }
+
+    pub async fn get_for_target(
+        user_id: u64,
+        limit: u64,
+        pool: &MySqlPool,
+    ) -> Result<Vec<AuditEntry>, ServerFnError> {
+        sqlx::query_as::<_, AuditEntry>(
+            "SELECT l.*, a.name AS actor_name, t.email AS target_email FROM `audit_log` l LEFT JOIN `users` a ON a.id = l.actor_id LEFT JOIN `users` t ON t.id = l.target_user_id WHERE l.target_user_id = ? ORDER BY l.created_at DESC, l.id DESC LIMIT ?",
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    pub async fn get_by_actor(
+        user_id: u64,
+        limit: u64,
+        pool: &MySqlPool,
+    ) -> Result<Vec<AuditEntry>, ServerFnError> {
+        sqlx::query_as::<_, AuditEntry>(
+            "SELECT l.*, a.name AS actor_name, t.email AS target_email FROM `audit_log` l LEFT JOIN `users` a ON a.id = l.actor_id LEFT JOIN `users` t ON t.id = l.target_user_id WHERE l.actor_id = ? ORDER BY l.created_at DESC, l.id DESC LIMIT ?",
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+}
@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+
+use super::order_item::Mode;
+
+const MB: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FileFormat {
+    Jpeg,
+    Png,
+    Heic,
+    Tiff,
+    Webp,
+    Cr3,
+    Nef,
+    Arw,
+}
+
+pub const FILE_FORMATS: [FileFormat; 8] = [
+    FileFormat::Jpeg,
+    FileFormat::Png,
+    FileFormat::Heic,
+    FileFormat::Tiff,
+    FileFormat::Webp,
+    FileFormat::Cr3,
+    FileFormat::Nef,
+    FileFormat::Arw,
+];
+
+impl FileFormat {
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            FileFormat::Jpeg => &["jpg", "jpeg"],
+            FileFormat::Png => &["png"],
+            FileFormat::Heic => &["heic", "heif"],
+            FileFormat::Tiff => &["tif", "tiff"],
+            FileFormat::Webp => &["webp"],
+            FileFormat::Cr3 => &["cr3"],
+            FileFormat::Nef => &["nef"],
+            FileFormat::Arw => &["arw"],
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            FileFormat::Jpeg => "image/jpeg",
+            FileFormat::Png => "image/png",
+            FileFormat::Heic => "image/heic",
+            FileFormat::Tiff => "image/tiff",
+            FileFormat::Webp => "image/webp",
+            FileFormat::Cr3 => "image/x-canon-cr3",
+            FileFormat::Nef => "image/x-nikon-nef",
+            FileFormat::Arw => "image/x-sony-arw",
+        }
+    }
+
+    pub fn max_size(&self) -> u64 {
+        match self {
+            FileFormat::Jpeg | FileFormat::Heic | FileFormat::Webp => 50 * MB,
+            FileFormat::Png => 100 * MB,
+            FileFormat::Cr3 | FileFormat::Nef | FileFormat::Arw => 150 * MB,
+            FileFormat::Tiff => 250 * MB,
+        }
+    }
+
+    pub fn allowed_modes(&self) -> &'static [Mode] {
+        match self {
+            FileFormat::Jpeg | FileFormat::Png | FileFormat::Tiff => {
+                &[Mode::Original, Mode::Processed]
+            }
+            FileFormat::Heic | FileFormat::Cr3 | FileFormat::Nef | FileFormat::Arw => {
+                &[Mode::Original]
+            }
+            FileFormat::Webp => &[Mode::Processed],
+        }
+    }
+
+    // HEIC and camera RAW need decoders that aren't available in pure Rust
+    pub fn has_preview(&self) -> bool {
+        matches!(
+            self,
+            FileFormat::Jpeg | FileFormat::Png | FileFormat::Tiff | FileFormat::Webp
+        )
+    }
+
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let (_, ext) = file_name.rsplit_once('.')?;
+        let ext = ext.to_lowercase();
+        FILE_FORMATS
+            .into_iter()
+            .find(|format| format.extensions().contains(&ext.as_str()))
+    }
+
+    pub fn for_upload(file_name: &str, mode: Mode, size: u64) -> Result<Self, String> {
+        let format = Self::from_file_name(file_name)
+            .ok_or(format!("{file_name} is not a supported file type"))?;
+        if !format.allowed_modes().contains(&mode) {
+            return Err(format!(
+                "{:?} files are not allowed for {:?} photos",
+                format, mode
+            ));
+        }
+        if size > format.max_size() {
+            return Err(format!(
+                "{file_name} is larger than {} MB",
+                format.max_size() / MB
+            ));
+        }
+        Ok(format)
+    }
+
+    // Camera RAW files are TIFF or ISO BMFF containers, so the brand or
+    // container is the closest content check available without decoding them.
+    pub fn matches(&self, content: &[u8]) -> bool {
+        let is_tiff = content.starts_with(b"II*\0") || content.starts_with(b"MM\0*");
+        let brand = match content.get(4..12) {
+            Some([b'f', b't', b'y', b'p', brand @ ..]) => Some(brand),
+            _ => None,
+        };
+        match self {
+            FileFormat::Jpeg => content.starts_with(&[0xFF, 0xD8, 0xFF]),
+            FileFormat::Png => content.starts_with(b"\x89PNG\r\n\x1a\n"),
+            FileFormat::Heic => matches!(
+                brand,
+                Some(b"heic" | b"heix" | b"hevc" | b"hevx" | b"heim" | b"heis" | b"mif1" | b"msf1")
+            ),
+            FileFormat::Tiff | FileFormat::Nef | FileFormat::Arw => is_tiff,
+            FileFormat::Webp => {
+                content.starts_with(b"RIFF") && content.get(8..12) == Some(b"WEBP".as_slice())
+            }
+            FileFormat::Cr3 => brand == Some(b"crx ".as_slice()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions_are_matched_case_insensitively() {
+        assert_eq!(
+            FileFormat::from_file_name("IMG_0001.JPG"),
+            Some(FileFormat::Jpeg)
+        );
+        assert_eq!(
+            FileFormat::from_file_name("IMG_0001.jpeg"),
+            Some(FileFormat::Jpeg)
+        );
+        assert_eq!(
+            FileFormat::from_file_name("IMG.0001.Tiff"),
+            Some(FileFormat::Tiff)
+        );
+        assert_eq!(FileFormat::from_file_name("IMG_0001.gif"), None);
+        assert_eq!(FileFormat::from_file_name("IMG_0001"), None);
+    }
+
+    #[test]
+    fn originals_accept_camera_formats() {
+        for file_name in [
+            "a.jpg", "a.png", "a.tif", "a.heic", "a.cr3", "a.nef", "a.arw",
+        ] {
+            assert!(
+                FileFormat::for_upload(file_name, Mode::Original, MB).is_ok(),
+                "{file_name} was refused"
+            );
+        }
+        assert!(FileFormat::for_upload("a.webp", Mode::Original, MB).is_err());
+    }
+
+    #[test]
+    fn processed_photos_accept_delivery_formats() {
+        for file_name in ["a.jpg", "a.png", "a.tiff", "a.webp"] {
+            assert!(
+                FileFormat::for_upload(file_name, Mode::Processed, MB).is_ok(),
+                "{file_name} was refused"
+            );
+        }
+        for file_name in ["a.heic", "a.cr3", "a.nef", "a.arw"] {
+            assert!(
+                FileFormat::for_upload(file_name, Mode::Processed, MB).is_err(),
+                "{file_name} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn unsupported_extensions_are_refused() {
+        for file_name in ["a.gif", "a.bmp", "a.pdf", "jpg", ""] {
+            assert!(FileFormat::for_upload(file_name, Mode::Original, MB).is_err());
+        }
+    }
+
+    #[test]
+    fn each_format_has_its_own_size_limit() {
+        assert!(FileFormat::for_upload("a.jpg", Mode::Original, 50 * MB).is_ok());
+        assert!(FileFormat::for_upload("a.jpg", Mode::Original, 50 * MB + 1).is_err());
+        assert!(FileFormat::for_upload("a.png", Mode::Original, 100 * MB).is_ok());
+        assert!(FileFormat::for_upload("a.nef", Mode::Original, 150 * MB).is_ok());
+        assert!(FileFormat::for_upload("a.tif", Mode::Original, 250 * MB).is_ok());
+        assert!(FileFormat::for_upload("a.tif", Mode::Original, 250 * MB + 1).is_err());
+    }
+
+    #[test]
+    fn only_decodable_formats_get_previews() {
+        let with_preview = FILE_FORMATS
+            .into_iter()
+            .filter(FileFormat::has_preview)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            with_preview,
+            [
+                FileFormat::Jpeg,
+                FileFormat::Png,
+                FileFormat::Tiff,
+                FileFormat::Webp
+            ]
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+use super::order::OrderStatus;
+use super::order_item::OrderItem;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestLogin {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestOrder {
+    pub order_id: u64,
+    pub status: OrderStatus,
+    pub no_of_photos: u64,
+    pub remaining: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestFile {
+    pub file_name: String,
+    pub file_size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncOrder {
+    pub order_id: u64,
+    pub status: OrderStatus,
+    pub no_of_photos: u64,
+    pub remaining: u64,
+    pub originals: Vec<OrderItem>,
+}
@@ -0,0 +1,108 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use super::user::Role;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use chrono::Local;
+        use leptos::ServerFnError;
+        use sqlx::{FromRow, MySqlPool};
+        use crate::to_server_fn_error;
+    } else {
+        use dummy_macros::*;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Invitation {
+    pub id: u64,
+    pub email: String,
+    pub role: Role,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub invited_by: u64,
+    pub expires_at: NaiveDateTime,
+    pub accepted_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[cfg(feature = "ssr")]
+impl Invitation {
+    pub async fn create(
+        email: String,
+        role: Role,
+        token_hash: String,
+        invited_by: u64,
+        expires_at: NaiveDateTime,
+        pool: &MySqlPool,
+    ) -> Result<u64, ServerFnError> {
+        sqlx::query("INSERT INTO `invitations` (`email`, `role`, `token_hash`, `invited_by`, `expires_at`, `created_at`) VALUES (?, ?, ?, ?, ?, ?)")
+            .bind(email)
+            .bind(role)
+            .bind(token_hash)
+            .bind(invited_by)
+            .bind(expires_at)
+            .bind(Local::now())
+            .execute(pool)
+            .await
+            .map(|result| result.last_insert_id())
+            .map_err(to_server_fn_error)
+    }
+
+    pub async fn get_pending(pool: &MySqlPool) -> Result<Vec<Invitation>, ServerFnError> {
+        sqlx::query_as::<_, Invitation>(
+            "SELECT * FROM `invitations` WHERE `accepted_at` IS NULL AND `revoked_at` IS NULL AND `expires_at` > ? ORDER BY `created_at` DESC",
+        )
+        .bind(Local::now())
+        .fetch_all(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    pub async fn get_pending_by_token(
+        token_hash: &str,
+        pool: &MySqlPool,
+    ) -> Result<Option<Invitation>, ServerFnError> {
+        sqlx::query_as::<_, Invitation>(
+            "SELECT * FROM `invitations` WHERE `token_hash` = ? AND `accepted_at` IS NULL AND `revoked_at` IS NULL AND `expires_at` > ?",
+        )
+        .bind(token_hash)
+        .bind(Local::now())
+        .fetch_optional(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    // Only one caller can win, so an invitation creates at most one account
+    pub async fn set_accepted(&self, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `invitations` SET `accepted_at` = ? WHERE `id` = ? AND `accepted_at` IS NULL AND `revoked_at` IS NULL")
+            .bind(Local::now())
+            .bind(self.id)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    pub async fn revoke(id: u64, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `invitations` SET `revoked_at` = ? WHERE `id` = ? AND `accepted_at` IS NULL AND `revoked_at` IS NULL")
+            .bind(Local::now())
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    pub async fn revoke_pending_for_email(email: &str, pool: &MySqlPool) -> Result<u64, ServerFnError> {
+        sqlx::query("UPDATE `invitations` SET `revoked_at` = ? WHERE `email` = ? AND `accepted_at` IS NULL AND `revoked_at` IS NULL")
+            .bind(Local::now())
+            .bind(email)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected())
+    }
+}
@@ -0,0 +1,219 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use chrono::Local;
+        use leptos::ServerFnError;
+        use sqlx::{FromRow, MySqlPool, Type};
+        use crate::to_server_fn_error;
+    } else {
+        use dummy_macros::*;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize, Type)]
+#[repr(i8)]
+pub enum AttemptKind {
+    Password = 1,
+    Otp = 2,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LoginAttempt {
+    pub id: u64,
+    pub user_id: Option<u64>,
+    pub ip: String,
+    pub kind: AttemptKind,
+    pub succeeded: bool,
+    pub created_at: NaiveDateTime,
+}
+
+// Account lockouts keep the address they came from, address lockouts have no user_id
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AccountLockout {
+    pub id: u64,
+    pub user_id: Option<u64>,
+    pub email: Option<String>,
+    pub ip: Option<String>,
+    pub failed_attempts: u32,
+    pub locked_until: NaiveDateTime,
+    pub unlocked_by: Option<u64>,
+    pub unlocked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[cfg(feature = "ssr")]
+impl LoginAttempt {
+    pub async fn create(
+        user_id: Option<u64>,
+        ip: &str,
+        kind: AttemptKind,
+        succeeded: bool,
+        pool: &MySqlPool,
+    ) -> Result<u64, ServerFnError> {
+        sqlx::query("INSERT INTO `login_attempts` (`user_id`, `ip`, `kind`, `succeeded`, `created_at`) VALUES (?, ?, ?, ?, ?)")
+            .bind(user_id)
+            .bind(ip)
+            .bind(kind)
+            .bind(succeeded)
+            .bind(Local::now())
+            .execute(pool)
+            .await
+            .map(|result| result.last_insert_id())
+            .map_err(to_server_fn_error)
+    }
+
+    pub async fn count_user_failures(
+        user_id: u64,
+        since: NaiveDateTime,
+        pool: &MySqlPool,
+    ) -> Result<i64, ServerFnError> {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM `login_attempts` WHERE `user_id` = ? AND `succeeded` = FALSE AND `created_at` > ?",
+        )
+        .bind(user_id)
+        .bind(since)
+        .fetch_one(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    pub async fn count_ip_failures(
+        ip: &str,
+        since: NaiveDateTime,
+        pool: &MySqlPool,
+    ) -> Result<i64, ServerFnError> {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM `login_attempts` WHERE `ip` = ? AND `succeeded` = FALSE AND `created_at` > ?",
+        )
+        .bind(ip)
+        .bind(since)
+        .fetch_one(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    pub async fn last_success_at(
+        user_id: u64,
+        pool: &MySqlPool,
+    ) -> Result<Option<NaiveDateTime>, ServerFnError> {
+        sqlx::query_scalar::<_, Option<NaiveDateTime>>(
+            "SELECT MAX(`created_at`) FROM `login_attempts` WHERE `user_id` = ? AND `succeeded` = TRUE",
+        )
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    pub async fn delete_before(
+        before: NaiveDateTime,
+        pool: &MySqlPool,
+    ) -> Result<u64, ServerFnError> {
+        sqlx::query("DELETE FROM `login_attempts` WHERE `created_at` < ?")
+            .bind(before)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected())
+            .map_err(to_server_fn_error)
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl AccountLockout {
+    pub async fn create(
+        user_id: Option<u64>,
+        ip: Option<&str>,
+        failed_attempts: u32,
+        locked_until: NaiveDateTime,
+        pool: &MySqlPool,
+    ) -> Result<u64, ServerFnError> {
+        sqlx::query("INSERT INTO `account_lockouts` (`user_id`, `ip`, `failed_attempts`, `locked_until`, `created_at`) VALUES (?, ?, ?, ?, ?)")
+            .bind(user_id)
+            .bind(ip)
+            .bind(failed_attempts)
+            .bind(locked_until)
+            .bind(Local::now())
+            .execute(pool)
+            .await
+            .map(|result| result.last_insert_id())
+            .map_err(to_server_fn_error)
+    }
+
+    pub async fn is_user_locked(user_id: u64, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM `account_lockouts` WHERE `user_id` = ? AND `unlocked_at` IS NULL AND `locked_until` > ?",
+        )
+        .bind(user_id)
+        .bind(Local::now())
+        .fetch_one(pool)
+        .await
+        .map(|count| count > 0)
+        .map_err(to_server_fn_error)
+    }
+
+    pub async fn is_ip_locked(ip: &str, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM `account_lockouts` WHERE `user_id` IS NULL AND `ip` = ? AND `unlocked_at` IS NULL AND `locked_until` > ?",
+        )
+        .bind(ip)
+        .bind(Local::now())
+        .fetch_one(pool)
+        .await
+        .map(|count| count > 0)
+        .map_err(to_server_fn_error)
+    }
+
+    // Failures before the latest lockout (or unlock) no longer count towards the next one
+    pub async fn last_user_lockout_at(
+        user_id: u64,
+        pool: &MySqlPool,
+    ) -> Result<Option<NaiveDateTime>, ServerFnError> {
+        sqlx::query_scalar::<_, Option<NaiveDateTime>>(
+            "SELECT MAX(COALESCE(`unlocked_at`, `created_at`)) FROM `account_lockouts` WHERE `user_id` = ?",
+        )
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    pub async fn last_ip_lockout_at(
+        ip: &str,
+        pool: &MySqlPool,
+    ) -> Result<Option<NaiveDateTime>, ServerFnError> {
+        sqlx::query_scalar::<_, Option<NaiveDateTime>>(
+            "SELECT MAX(COALESCE(`unlocked_at`, `created_at`)) FROM `account_lockouts` WHERE `user_id` IS NULL AND `ip` = ?",
+        )
+        .bind(ip)
+        .fetch_one(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    pub async fn get_active(pool: &MySqlPool) -> Result<Vec<AccountLockout>, ServerFnError> {
+        sqlx::query_as::<_, AccountLockout>(
+            "SELECT `account_lockouts`.*, `users`.`email` FROM `account_lockouts` LEFT JOIN `users` ON `users`.`id` = `account_lockouts`.`user_id` WHERE `unlocked_at` IS NULL AND `locked_until` > ? ORDER BY `account_lockouts`.`created_at` DESC",
+        )
+        .bind(Local::now())
+        .fetch_all(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    pub async fn unlock(
+        id: u64,
+        unlocked_by: u64,
+        pool: &MySqlPool,
+    ) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `account_lockouts` SET `unlocked_by` = ?, `unlocked_at` = ? WHERE `id` = ? AND `unlocked_at` IS NULL")
+            .bind(unlocked_by)
+            .bind(Local::now())
+            .bind(id)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected() > 0)
+            .map_err(to_server_fn_error)
+    }
+}
@@ -69,6 +69,23 @@ impl Default for PaymentMode {
     }
 }
 
+// What an order ticket's QR code encodes
+pub fn get_order_reference(order_id: u64) -> String {
+    format!("ORDER-{:0>6}", order_id)
+}
+
+// Accepts a plain order number or anything ending in one, such as the
+// contents of an order ticket's QR code
+pub fn parse_order_reference(reference: &str) -> Option<u64> {
+    let reference = reference.trim();
+    let digits = reference
+        .chars()
+        .rev()
+        .take_while(|c| c.is_ascii_digit())
+        .count();
+    reference[reference.len() - digits..].parse().ok()
+}
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "ssr")] {
         use sqlx::MySqlPool;
@@ -92,6 +109,22 @@ impl Order {
         .await
         .map_err(to_server_fn_error)
     }
+    pub async fn get_handled_by(
+        staff_id: u64,
+        limit: u64,
+        pool: &MySqlPool,
+    ) -> Result<Vec<Order>, ServerFnError> {
+        sqlx::query_as::<_, Order>(
+            "SELECT * FROM `orders` WHERE `cashier_id` = ? OR `operator_id` = ? OR `processor_id` = ? ORDER BY `id` DESC LIMIT ?",
+        )
+        .bind(staff_id)
+        .bind(staff_id)
+        .bind(staff_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
     pub async fn get_customer(&self, pool: &MySqlPool) -> Result<User, ServerFnError> {
         User::get_by_id(self.customer_id, pool).await
     }
@@ -365,10 +398,7 @@ impl Order {
         mode: Mode,
         pool: &MySqlPool,
     ) -> Result<Vec<OrderItem>, ServerFnError> {
-        sqlx::query_as!(OrderItem,"SELECT id,order_id,mode as `mode: _`, file_name, get_url,put_url,uploaded as `uploaded: _`,uploaded_at,created_at FROM `order_items` WHERE `order_id` = ? and `mode` = ?",self.id,mode)
-            .fetch_all(pool)
-            .await
-            .map_err(to_server_fn_error)
+        OrderItem::get_order_items_by_order_id(self.id, mode, pool).await
     }
     pub async fn remaining_order_items(
         &self,
@@ -593,6 +623,21 @@ impl Order {
         }
     }
 
+    pub async fn get_claimed_by_processor(
+        processor_id: u64,
+        pool: &MySqlPool,
+    ) -> Result<Vec<Order>, ServerFnError> {
+        sqlx::query_as::<_, Order>(
+            "SELECT * FROM `orders` WHERE `processor_id` = ? AND `status` IN (?, ?) ORDER BY `id`",
+        )
+        .bind(processor_id)
+        .bind(OrderStatus::InProcess)
+        .bind(OrderStatus::Processed)
+        .fetch_all(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
     pub async fn mark_order_ready_for_delivery(
         &self,
         pool: &MySqlPool,
@@ -608,4 +653,115 @@ impl Order {
         .map_err(to_server_fn_error)
         .map(|result| result.rows_affected() > 0)
     }
+
+    pub async fn set_delivered(&self, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `orders` SET `delivered_at` = ? WHERE `id` = ?")
+            .bind(Local::now())
+            .bind(self.id)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    // Each mode has its own retention period, so each gets its own warning
+    pub fn retention_warning_column(mode: Mode) -> &'static str {
+        match mode {
+            Mode::Original => "original_warning_sent_at",
+            Mode::Processed => "processed_warning_sent_at",
+        }
+    }
+
+    pub async fn get_due_for_retention_warning(
+        mode: Mode,
+        delivered_before: NaiveDateTime,
+        pool: &MySqlPool,
+    ) -> Result<Vec<Order>, ServerFnError> {
+        sqlx::query_as::<_, Order>(&format!(
+            "SELECT o.* FROM `orders` o WHERE o.status = ? AND o.delivered_at IS NOT NULL AND o.delivered_at <= ? AND o.`{}` IS NULL AND EXISTS (SELECT 1 FROM `order_items` oi WHERE oi.order_id = o.id AND oi.mode = ? AND oi.purged_at IS NULL)",
+            Self::retention_warning_column(mode)
+        ))
+        .bind(OrderStatus::ReadyForDelivery)
+        .bind(delivered_before)
+        .bind(mode)
+        .fetch_all(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    // Paid orders that have not been delivered yet still need the customer
+    pub async fn count_in_progress_for_customer(
+        customer_id: u64,
+        pool: &MySqlPool,
+    ) -> Result<i64, ServerFnError> {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM `orders` WHERE `customer_id` = ? AND `status` >= ? AND `status` < ?",
+        )
+        .bind(customer_id)
+        .bind(OrderStatus::Paid)
+        .bind(OrderStatus::ReadyForDelivery)
+        .fetch_one(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    // Starts the retention clock on every order that has not been delivered and
+    // skips the warning emails, the retention job then purges the photos as usual
+    pub async fn schedule_purge_for_customer(
+        customer_id: u64,
+        pool: &MySqlPool,
+    ) -> Result<u64, ServerFnError> {
+        let now = Local::now();
+        sqlx::query("UPDATE `orders` SET `delivered_at` = COALESCE(`delivered_at`, ?), `original_warning_sent_at` = COALESCE(`original_warning_sent_at`, ?), `processed_warning_sent_at` = COALESCE(`processed_warning_sent_at`, ?) WHERE `customer_id` = ?")
+            .bind(now)
+            .bind(now)
+            .bind(now)
+            .bind(customer_id)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected())
+    }
+
+    // Stripe references embed the customer's email, they are rewritten with the
+    // order number only so confirmations keep resolving to an order
+    pub async fn anonymize_order_refs_for_customer(
+        customer_id: u64,
+        pool: &MySqlPool,
+    ) -> Result<u64, ServerFnError> {
+        use base64::{engine::general_purpose, Engine as _};
+        let ids = sqlx::query_scalar::<_, u64>(
+            "SELECT `id` FROM `orders` WHERE `customer_id` = ? AND `order_ref` IS NOT NULL",
+        )
+        .bind(customer_id)
+        .fetch_all(pool)
+        .await
+        .map_err(to_server_fn_error)?;
+        for id in ids.iter() {
+            sqlx::query("UPDATE `orders` SET `order_ref` = ? WHERE `id` = ?")
+                .bind(general_purpose::URL_SAFE_NO_PAD.encode(format!("Order #:{id}")))
+                .bind(id)
+                .execute(pool)
+                .await
+                .map_err(to_server_fn_error)?;
+        }
+        Ok(ids.len() as u64)
+    }
+
+    pub async fn set_retention_warning_sent(
+        &self,
+        mode: Mode,
+        pool: &MySqlPool,
+    ) -> Result<bool, ServerFnError> {
+        sqlx::query(&format!(
+            "UPDATE `orders` SET `{}` = ? WHERE `id` = ?",
+            Self::retention_warning_column(mode)
+        ))
+        .bind(Local::now())
+        .bind(self.id)
+        .execute(pool)
+        .await
+        .map_err(to_server_fn_error)
+        .map(|result| result.rows_affected() > 0)
+    }
 }
@@ -1,6 +1,8 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
+use super::order::OrderStatus;
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "ssr")] {
         use leptos::ServerFnError;
@@ -8,6 +10,7 @@ cfg_if::cfg_if! {
         use sqlx::{FromRow,Type};
         use chrono::Local;
         use crate::server::to_server_fn_error;
+        use super::order::Order;
     } else {
         use dummy_macros::*;
     }
@@ -20,6 +23,31 @@ pub enum Mode {
     Processed = 2,
 }
 
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize, Copy)]
+pub enum Derivative {
+    Thumbnail,
+    Preview,
+    Watermarked,
+}
+
+impl Derivative {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Derivative::Thumbnail => "thumbnail",
+            Derivative::Preview => "preview",
+            Derivative::Watermarked => "watermarked",
+        }
+    }
+
+    pub fn max_dimension(&self) -> u32 {
+        match self {
+            Derivative::Thumbnail => 320,
+            Derivative::Preview => 1600,
+            Derivative::Watermarked => 1024,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct OrderItem {
     pub id: u64,
@@ -31,8 +59,40 @@ pub struct OrderItem {
     pub uploaded: bool,
     pub uploaded_at: Option<NaiveDateTime>,
     pub created_at: NaiveDateTime,
+    pub thumbnail_url: Option<String>,
+    pub preview_url: Option<String>,
+    pub watermarked_url: Option<String>,
+    // Set when the original can't be decoded, so no previews will ever appear
+    pub preview_unavailable: bool,
+    pub delivery_file_name: Option<String>,
+    pub file_size: Option<u64>,
+    pub purged_at: Option<NaiveDateTime>,
+    pub checksum: Option<String>,
 }
 
+impl OrderItem {
+    pub fn for_customer(self, status: OrderStatus) -> Self {
+        if self.mode == Mode::Processed && status == OrderStatus::ReadyForDelivery {
+            return Self {
+                put_url: String::new(),
+                ..self
+            };
+        }
+        let watermarked_url = self.watermarked_url.clone();
+        Self {
+            get_url: watermarked_url.clone().unwrap_or_default(),
+            put_url: String::new(),
+            thumbnail_url: watermarked_url.clone(),
+            preview_url: watermarked_url.clone(),
+            watermarked_url,
+            ..self
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+const ER_DUP_ENTRY: u16 = 1062;
+
 #[cfg(feature = "ssr")]
 impl OrderItem {
     pub async fn get_order_items_by_order_id(
@@ -40,16 +100,20 @@ impl OrderItem {
         mode: Mode,
         pool: &MySqlPool,
     ) -> Result<Vec<OrderItem>, ServerFnError> {
-        sqlx::query_as!(OrderItem,"SELECT id,order_id,mode as `mode: _`, file_name, get_url,put_url,uploaded as `uploaded: _`,uploaded_at,created_at FROM `order_items` WHERE `order_id` = ? and `mode` = ?",order_id,mode)
-            .fetch_all(pool)
-            .await
-            .map_err(to_server_fn_error)
+        sqlx::query_as::<_, OrderItem>(
+            "SELECT * FROM `order_items` WHERE `order_id` = ? and `mode` = ?",
+        )
+        .bind(order_id)
+        .bind(mode)
+        .fetch_all(pool)
+        .await
+        .map_err(to_server_fn_error)
     }
     pub async fn set_uploaded(&self, pool: &MySqlPool) -> Result<bool, ServerFnError> {
         sqlx::query!(
             "UPDATE `order_items` SET `uploaded` = true, `uploaded_at` = ? WHERE `id` = ?",
-            self.id,
-            Local::now()
+            Local::now(),
+            self.id
         )
         .execute(pool)
         .await
@@ -72,8 +136,140 @@ impl OrderItem {
         .map(|result| result.rows_affected() > 0)
     }
 
+    pub async fn update_derivative_urls(
+        &self,
+        thumbnail_url: Option<String>,
+        preview_url: Option<String>,
+        watermarked_url: Option<String>,
+        pool: &MySqlPool,
+    ) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `order_items` SET `thumbnail_url` = ?, `preview_url` = ?, `watermarked_url` = ? WHERE `id` = ?")
+            .bind(thumbnail_url)
+            .bind(preview_url)
+            .bind(watermarked_url)
+            .bind(self.id)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    pub async fn set_preview_unavailable(&self, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `order_items` SET `preview_unavailable` = TRUE WHERE `id` = ?")
+            .bind(self.id)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    pub async fn set_delivery_file(
+        &self,
+        delivery_file_name: String,
+        get_url: String,
+        pool: &MySqlPool,
+    ) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `order_items` SET `delivery_file_name` = ?, `get_url` = ? WHERE `id` = ?")
+            .bind(delivery_file_name)
+            .bind(get_url)
+            .bind(self.id)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    pub async fn set_file_size(&self, file_size: u64, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `order_items` SET `file_size` = ? WHERE `id` = ?")
+            .bind(file_size)
+            .bind(self.id)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    pub async fn set_purged(&self, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `order_items` SET `purged_at` = ?, `thumbnail_url` = NULL, `preview_url` = NULL, `watermarked_url` = NULL WHERE `id` = ?")
+            .bind(Local::now())
+            .bind(self.id)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    // Items are only purged once the customer has been warned for this mode
+    pub async fn get_due_for_purge(
+        mode: Mode,
+        delivered_before: NaiveDateTime,
+        warned_before: NaiveDateTime,
+        pool: &MySqlPool,
+    ) -> Result<Vec<OrderItem>, ServerFnError> {
+        sqlx::query_as::<_, OrderItem>(&format!(
+            "SELECT oi.* FROM `order_items` oi INNER JOIN `orders` o ON o.id = oi.order_id WHERE oi.mode = ? AND oi.purged_at IS NULL AND o.delivered_at IS NOT NULL AND o.delivered_at <= ? AND o.`{0}` IS NOT NULL AND o.`{0}` <= ?",
+            Order::retention_warning_column(mode)
+        ))
+        .bind(mode)
+        .bind(delivered_before)
+        .bind(warned_before)
+        .fetch_all(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    pub async fn get_missing_file_size(pool: &MySqlPool) -> Result<Vec<OrderItem>, ServerFnError> {
+        sqlx::query_as::<_, OrderItem>(
+            "SELECT * FROM `order_items` WHERE `uploaded` = true AND `purged_at` IS NULL AND `file_size` IS NULL",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    // The unique (order_id, mode, checksum) index keeps two copies of a photo
+    // out of an order, Ok(false) means another item already has this checksum
+    pub async fn set_checksum(&self, checksum: String, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+        let result = sqlx::query("UPDATE `order_items` SET `checksum` = ? WHERE `id` = ?")
+            .bind(checksum)
+            .bind(self.id)
+            .execute(pool)
+            .await;
+        match result {
+            Ok(_) => Ok(true),
+            Err(sqlx::Error::Database(e))
+                if e.try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>()
+                    .map_or(false, |e| e.number() == ER_DUP_ENTRY) =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(to_server_fn_error(e)),
+        }
+    }
+
+    pub async fn find_duplicate(&self, checksum: &str, pool: &MySqlPool) -> Result<Option<OrderItem>, ServerFnError> {
+        sqlx::query_as::<_, OrderItem>(
+            "SELECT * FROM `order_items` WHERE `order_id` = ? AND `mode` = ? AND `checksum` = ? AND `id` <> ? LIMIT 1",
+        )
+        .bind(self.order_id)
+        .bind(self.mode)
+        .bind(checksum)
+        .bind(self.id)
+        .fetch_optional(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    pub async fn get_order_ids(pool: &MySqlPool) -> Result<Vec<u64>, ServerFnError> {
+        sqlx::query_scalar::<_, u64>("SELECT DISTINCT `order_id` FROM `order_items` ORDER BY `order_id`")
+            .fetch_all(pool)
+            .await
+            .map_err(to_server_fn_error)
+    }
+
     pub async fn get_by_id(id: u64, pool: &MySqlPool) -> Result<OrderItem, ServerFnError> {
-        sqlx::query_as!(OrderItem, "SELECT id,order_id,mode as `mode: _`, file_name, get_url,put_url,uploaded as `uploaded: _`,uploaded_at,created_at FROM `order_items` WHERE `id` = ?", id)
+        sqlx::query_as::<_, OrderItem>("SELECT * FROM `order_items` WHERE `id` = ?")
+            .bind(id)
             .fetch_one(pool)
             .await
             .map_err(to_server_fn_error)
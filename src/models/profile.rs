@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+// What a signed in user sees and edits about their own account
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Profile {
+    pub name: String,
+    pub email: String,
+    pub phone: Option<String>,
+    pub has_password: bool,
+    pub pending_email: Option<String>,
+    pub can_delete: bool,
+}
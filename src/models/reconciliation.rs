@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ReconcileMode {
+    // Only report the drift between storage and the database
+    Report,
+    // Delete orphaned objects and bring the database in line with storage
+    Repair,
+    // Same as Repair, but orphaned objects are moved under the quarantine prefix
+    Quarantine,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MissingObject {
+    pub item_id: u64,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SizeMismatch {
+    pub item_id: u64,
+    pub path: String,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChecksumMismatch {
+    pub item_id: u64,
+    pub path: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateItem {
+    pub item_id: u64,
+    pub duplicate_of: u64,
+    pub path: String,
+}
+
+// Sent back by the manager when repairing, empty lists are left out of the form data
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrderReconciliation {
+    pub order_id: u64,
+    pub orphaned: Vec<String>,
+    pub missing: Vec<MissingObject>,
+    pub size_mismatches: Vec<SizeMismatch>,
+    pub checksum_mismatches: Vec<ChecksumMismatch>,
+    pub duplicates: Vec<DuplicateItem>,
+    pub errors: Vec<String>,
+}
+
+impl OrderReconciliation {
+    pub fn is_clean(&self) -> bool {
+        self.orphaned.is_empty()
+            && self.missing.is_empty()
+            && self.size_mismatches.is_empty()
+            && self.checksum_mismatches.is_empty()
+            && self.duplicates.is_empty()
+            && self.errors.is_empty()
+    }
+}
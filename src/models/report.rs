@@ -1,4 +1,5 @@
 use super::order::OrderStatus;
+use super::order_item::Mode;
 use serde::{Deserialize, Serialize};
 
 cfg_if::cfg_if! {
@@ -34,6 +35,31 @@ pub struct OrderCountByProcessor {
     pub photos_count: Option<i64>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct StorageByAge {
+    pub mode: Mode,
+    pub age_bucket: i64,
+    pub count: i64,
+    pub total_bytes: Option<i64>,
+}
+
+impl StorageByAge {
+    pub fn age_label(&self) -> &'static str {
+        match self.age_bucket {
+            0 => "0-30 days",
+            1 => "31-90 days",
+            2 => "91-180 days",
+            3 => "181-365 days",
+            _ => "Over a year",
+        }
+    }
+
+    pub fn total_size(&self) -> String {
+        let bytes = self.total_bytes.unwrap_or_default() as f64;
+        format!("{:.1} GB", bytes / (1024.0 * 1024.0 * 1024.0))
+    }
+}
+
 pub struct Report {}
 
 #[cfg(feature = "ssr")]
@@ -80,4 +106,26 @@ impl Report {
         .await
         .map_err(to_server_fn_error)
     }
+
+    pub async fn get_storage_by_age(pool: &MySqlPool) -> Result<Vec<StorageByAge>, ServerFnError> {
+        sqlx::query_as::<_, StorageByAge>(
+            r#"select mode, age_bucket, count(1) as `count`,
+            cast(sum(file_size) as signed) as total_bytes
+            from (select mode, file_size,
+                cast(case
+                    when datediff(now(), ifnull(uploaded_at, created_at)) <= 30 then 0
+                    when datediff(now(), ifnull(uploaded_at, created_at)) <= 90 then 1
+                    when datediff(now(), ifnull(uploaded_at, created_at)) <= 180 then 2
+                    when datediff(now(), ifnull(uploaded_at, created_at)) <= 365 then 3
+                    else 4
+                end as signed) as age_bucket
+                from order_items
+                where uploaded = true and purged_at is null) as x
+            group by mode, age_bucket
+            order by mode, age_bucket"#,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
 }
@@ -2,6 +2,8 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
+use super::order_item::Mode;
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "ssr")] {
         use sqlx::MySqlPool;
@@ -22,6 +24,8 @@ pub struct Setting {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd)]
 pub enum SettingKey {
     AllowOrderCreation,
+    OriginalRetentionDays,
+    ProcessedRetentionDays,
 }
 
 impl Display for SettingKey {
@@ -30,6 +34,22 @@ impl Display for SettingKey {
     }
 }
 
+// Number of days after delivery that photos are kept, 0 keeps them forever
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct RetentionSettings {
+    pub original_days: u32,
+    pub processed_days: u32,
+}
+
+impl RetentionSettings {
+    pub fn days_for(&self, mode: Mode) -> u32 {
+        match mode {
+            Mode::Original => self.original_days,
+            Mode::Processed => self.processed_days,
+        }
+    }
+}
+
 impl Setting {
     pub fn is_true(&self) -> bool {
         self.value == String::from("1")
@@ -70,4 +90,53 @@ impl Setting {
             },
         })
     }
+
+    async fn get_value(key: SettingKey, pool: &MySqlPool) -> Result<Option<String>, ServerFnError> {
+        sqlx::query_as::<_, Setting>("SELECT `name`, `value` FROM `settings` WHERE `name` = ?")
+            .bind(key.to_string())
+            .fetch_optional(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|setting| setting.map(|setting| setting.value))
+    }
+
+    async fn set_value(key: SettingKey, value: String, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+        sqlx::query("REPLACE INTO `settings` (`name`,`value`) VALUES (?,?)")
+            .bind(key.to_string())
+            .bind(value)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    pub async fn get_retention(pool: &MySqlPool) -> Result<RetentionSettings, ServerFnError> {
+        let parse = |value: Option<String>| {
+            value
+                .and_then(|value| value.parse::<u32>().ok())
+                .unwrap_or_default()
+        };
+        Ok(RetentionSettings {
+            original_days: parse(Setting::get_value(SettingKey::OriginalRetentionDays, pool).await?),
+            processed_days: parse(Setting::get_value(SettingKey::ProcessedRetentionDays, pool).await?),
+        })
+    }
+
+    pub async fn set_retention(
+        retention: RetentionSettings,
+        pool: &MySqlPool,
+    ) -> Result<bool, ServerFnError> {
+        Setting::set_value(
+            SettingKey::OriginalRetentionDays,
+            retention.original_days.to_string(),
+            pool,
+        )
+        .await?;
+        Setting::set_value(
+            SettingKey::ProcessedRetentionDays,
+            retention.processed_days.to_string(),
+            pool,
+        )
+        .await
+    }
 }
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+use super::order::{OrderStatus, PaymentMode};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OrderTicket {
+    pub order_id: u64,
+    pub reference: String,
+    pub name: String,
+    pub no_of_photos: u64,
+    pub order_total: u64,
+    pub mode_of_payment: PaymentMode,
+    pub status: OrderStatus,
+    pub qr_svg: String,
+}
+
+impl OrderTicket {
+    pub fn payment_status(&self) -> String {
+        match self.status {
+            OrderStatus::Created => "Not paid".to_string(),
+            OrderStatus::PaymentPending => format!("Pending ({:?})", self.mode_of_payment),
+            OrderStatus::PaymentError => "Payment failed".to_string(),
+            _ => format!("Paid ({:?})", self.mode_of_payment),
+        }
+    }
+}
@@ -115,6 +115,132 @@ impl User {
         .map_err(to_server_fn_error)
     }
 
+    pub async fn set_password(
+        id: u64,
+        password_hash: String,
+        pool: &MySqlPool,
+    ) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `users` SET `password_hash` = ? WHERE `id` = ?")
+            .bind(password_hash)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    pub async fn set_status(
+        id: u64,
+        status: UserStatus,
+        pool: &MySqlPool,
+    ) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `users` SET `status` = ? WHERE `id` = ?")
+            .bind(status)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    pub async fn activate(id: u64, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `users` SET `role` = ?, `status` = ? WHERE `id` = ? AND `role` = ? AND `status` = ?")
+            .bind(Role::Customer)
+            .bind(UserStatus::Active)
+            .bind(id)
+            .bind(Role::Anonymous)
+            .bind(UserStatus::NotActivatedYet)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    // Accounts with orders are kept so order history is never lost
+    pub async fn delete_unverified(
+        created_before: chrono::NaiveDateTime,
+        pool: &MySqlPool,
+    ) -> Result<u64, ServerFnError> {
+        sqlx::query("DELETE FROM `users` WHERE `role` = ? AND `status` = ? AND `created_at` < ? AND NOT EXISTS (SELECT 1 FROM `orders` o WHERE o.customer_id = `users`.id)")
+            .bind(Role::Anonymous)
+            .bind(UserStatus::NotActivatedYet)
+            .bind(created_before)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected())
+    }
+
+    // An empty query matches every account, anonymous visitors are never listed
+    pub async fn search(
+        query: &str,
+        role: Option<Role>,
+        offset: u64,
+        limit: u64,
+        pool: &MySqlPool,
+    ) -> Result<(Vec<Self>, u64), ServerFnError> {
+        let mut filter = String::from(" WHERE `role` <> ? AND (`name` LIKE ? OR `email` LIKE ? OR `phone` LIKE ?)");
+        if role.is_some() {
+            filter.push_str(" AND `role` = ?");
+        }
+        let pattern = format!("%{}%", query.trim());
+        let count_query = format!("SELECT COUNT(*) FROM `users`{filter}");
+        let mut count = sqlx::query_scalar::<_, i64>(&count_query)
+            .bind(Role::Anonymous)
+            .bind(&pattern)
+            .bind(&pattern)
+            .bind(&pattern);
+        let users_query = format!("SELECT * FROM `users`{filter} ORDER BY `name`, `id` LIMIT ? OFFSET ?");
+        let mut users = sqlx::query_as::<_, User>(&users_query)
+            .bind(Role::Anonymous)
+            .bind(&pattern)
+            .bind(&pattern)
+            .bind(&pattern);
+        if let Some(role) = role {
+            count = count.bind(role.clone());
+            users = users.bind(role);
+        }
+        let total = count.fetch_one(pool).await.map_err(to_server_fn_error)?;
+        let users = users
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(pool)
+            .await
+            .map_err(to_server_fn_error)?;
+        Ok((users, total as u64))
+    }
+
+    pub async fn update_profile(
+        id: u64,
+        name: String,
+        email: String,
+        phone: Option<String>,
+        pool: &MySqlPool,
+    ) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `users` SET `name` = ?, `email` = ?, `phone` = ? WHERE `id` = ?")
+            .bind(name)
+            .bind(email)
+            .bind(phone)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    // Keeps the row so order history stays intact, but nothing in it points to the person
+    pub async fn anonymize(id: u64, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `users` SET `name` = ?, `email` = ?, `phone` = NULL, `password_hash` = NULL, `otp_secret` = NULL, `status` = ? WHERE `id` = ?")
+            .bind("Deleted User")
+            .bind(format!("deleted-{id}@invalid"))
+            .bind(UserStatus::Disabled)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|result| result.rows_affected() > 0)
+    }
+
     pub async fn orders(&self, pool: &MySqlPool) -> Result<Vec<UserOrder>, ServerFnError> {
         Order::get_orders_for_customer(self.id, pool).await
     }
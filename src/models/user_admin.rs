@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use super::{audit_log::AuditEntry, order::Order, user::User, user_order::UserOrder};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct UserPage {
+    pub users: Vec<User>,
+    pub total: u64,
+    pub page: u64,
+    pub page_size: u64,
+}
+
+impl UserPage {
+    pub fn page_count(&self) -> u64 {
+        let page_size = self.page_size.max(1);
+        ((self.total + page_size - 1) / page_size).max(1)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserDetails {
+    pub user: User,
+    pub orders: Vec<UserOrder>,
+    pub handled_orders: Vec<Order>,
+    pub changes: Vec<AuditEntry>,
+    pub actions: Vec<AuditEntry>,
+}
@@ -0,0 +1,55 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use chrono::Local;
+        use leptos::ServerFnError;
+        use sqlx::{FromRow, MySqlPool};
+        use crate::to_server_fn_error;
+    } else {
+        use dummy_macros::*;
+    }
+}
+
+// Each login gets a row, so every session of a user can be revoked at once
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserSession {
+    pub id: u64,
+    pub user_id: u64,
+    pub created_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+#[cfg(feature = "ssr")]
+impl UserSession {
+    pub async fn create(user_id: u64, pool: &MySqlPool) -> Result<u64, ServerFnError> {
+        sqlx::query("INSERT INTO `user_sessions` (`user_id`, `created_at`) VALUES (?, ?)")
+            .bind(user_id)
+            .bind(Local::now())
+            .execute(pool)
+            .await
+            .map(|result| result.last_insert_id())
+            .map_err(to_server_fn_error)
+    }
+
+    pub async fn get_user_id(id: u64, pool: &MySqlPool) -> Result<Option<u64>, ServerFnError> {
+        sqlx::query_scalar::<_, u64>(
+            "SELECT `user_id` FROM `user_sessions` WHERE `id` = ? AND `revoked_at` IS NULL",
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    pub async fn revoke_all_for_user(user_id: u64, pool: &MySqlPool) -> Result<u64, ServerFnError> {
+        sqlx::query("UPDATE `user_sessions` SET `revoked_at` = ? WHERE `user_id` = ? AND `revoked_at` IS NULL")
+            .bind(Local::now())
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected())
+            .map_err(to_server_fn_error)
+    }
+}
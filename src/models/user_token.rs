@@ -0,0 +1,189 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use chrono::Local;
+        use leptos::ServerFnError;
+        use sqlx::{FromRow, MySqlPool, Type};
+        use crate::to_server_fn_error;
+    } else {
+        use dummy_macros::*;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize, Type)]
+#[repr(i8)]
+pub enum TokenPurpose {
+    EmailVerification = 1,
+    PasswordReset = 2,
+    LoginCode = 3,
+    EmailChange = 4,
+}
+
+// Only the SHA-256 of a token is stored, the token itself is sent by email
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserToken {
+    pub id: u64,
+    pub user_id: u64,
+    pub purpose: TokenPurpose,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
+    pub attempts: u32,
+    // The address an EmailChange token moves the account to
+    pub new_email: Option<String>,
+    pub used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[cfg(feature = "ssr")]
+impl UserToken {
+    pub async fn create(
+        user_id: u64,
+        purpose: TokenPurpose,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+        pool: &MySqlPool,
+    ) -> Result<u64, ServerFnError> {
+        sqlx::query("INSERT INTO `user_tokens` (`user_id`, `purpose`, `token_hash`, `expires_at`, `created_at`) VALUES (?, ?, ?, ?, ?)")
+            .bind(user_id)
+            .bind(purpose)
+            .bind(token_hash)
+            .bind(expires_at)
+            .bind(Local::now())
+            .execute(pool)
+            .await
+            .map(|result| result.last_insert_id())
+            .map_err(to_server_fn_error)
+    }
+
+    // Marks the token used and returns its user, so a token only ever works once
+    pub async fn consume(
+        purpose: TokenPurpose,
+        token_hash: &str,
+        pool: &MySqlPool,
+    ) -> Result<Option<u64>, ServerFnError> {
+        let now = Local::now().naive_local();
+        let Some(token) = sqlx::query_as::<_, UserToken>(
+            "SELECT * FROM `user_tokens` WHERE `purpose` = ? AND `token_hash` = ? AND `used_at` IS NULL AND `expires_at` > ?",
+        )
+        .bind(purpose)
+        .bind(token_hash)
+        .bind(now)
+        .fetch_optional(pool)
+        .await
+        .map_err(to_server_fn_error)? else {
+            return Ok(None);
+        };
+        let result = sqlx::query("UPDATE `user_tokens` SET `used_at` = ? WHERE `id` = ? AND `used_at` IS NULL")
+            .bind(now)
+            .bind(token.id)
+            .execute(pool)
+            .await
+            .map_err(to_server_fn_error)?;
+        Ok((result.rows_affected() > 0).then_some(token.user_id))
+    }
+
+    pub async fn get_active(
+        user_id: u64,
+        purpose: TokenPurpose,
+        pool: &MySqlPool,
+    ) -> Result<Option<UserToken>, ServerFnError> {
+        sqlx::query_as::<_, UserToken>(
+            "SELECT * FROM `user_tokens` WHERE `user_id` = ? AND `purpose` = ? AND `used_at` IS NULL AND `expires_at` > ? ORDER BY `id` DESC LIMIT 1",
+        )
+        .bind(user_id)
+        .bind(purpose)
+        .bind(Local::now())
+        .fetch_optional(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    pub async fn get_valid(
+        purpose: TokenPurpose,
+        token_hash: &str,
+        pool: &MySqlPool,
+    ) -> Result<Option<UserToken>, ServerFnError> {
+        sqlx::query_as::<_, UserToken>(
+            "SELECT * FROM `user_tokens` WHERE `purpose` = ? AND `token_hash` = ? AND `used_at` IS NULL AND `expires_at` > ?",
+        )
+        .bind(purpose)
+        .bind(token_hash)
+        .bind(Local::now())
+        .fetch_optional(pool)
+        .await
+        .map_err(to_server_fn_error)
+    }
+
+    pub async fn set_new_email(
+        token_hash: &str,
+        new_email: &str,
+        pool: &MySqlPool,
+    ) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `user_tokens` SET `new_email` = ? WHERE `token_hash` = ?")
+            .bind(new_email)
+            .bind(token_hash)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected() > 0)
+            .map_err(to_server_fn_error)
+    }
+
+    // Counts a guess against the token, returns false once it has no guesses left
+    pub async fn add_attempt(
+        id: u64,
+        max_attempts: u32,
+        pool: &MySqlPool,
+    ) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `user_tokens` SET `attempts` = `attempts` + 1 WHERE `id` = ? AND `used_at` IS NULL AND `attempts` < ?")
+            .bind(id)
+            .bind(max_attempts)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected() > 0)
+            .map_err(to_server_fn_error)
+    }
+
+    pub async fn set_used(id: u64, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+        sqlx::query("UPDATE `user_tokens` SET `used_at` = ? WHERE `id` = ? AND `used_at` IS NULL")
+            .bind(Local::now())
+            .bind(id)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected() > 0)
+            .map_err(to_server_fn_error)
+    }
+
+    pub async fn delete_for_user(
+        user_id: u64,
+        purpose: TokenPurpose,
+        pool: &MySqlPool,
+    ) -> Result<u64, ServerFnError> {
+        sqlx::query("DELETE FROM `user_tokens` WHERE `user_id` = ? AND `purpose` = ?")
+            .bind(user_id)
+            .bind(purpose)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected())
+            .map_err(to_server_fn_error)
+    }
+
+    pub async fn delete_all_for_user(user_id: u64, pool: &MySqlPool) -> Result<u64, ServerFnError> {
+        sqlx::query("DELETE FROM `user_tokens` WHERE `user_id` = ?")
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected())
+            .map_err(to_server_fn_error)
+    }
+
+    pub async fn delete_expired(pool: &MySqlPool) -> Result<u64, ServerFnError> {
+        sqlx::query("DELETE FROM `user_tokens` WHERE `expires_at` <= ?")
+            .bind(Local::now())
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected())
+            .map_err(to_server_fn_error)
+    }
+}
@@ -1,20 +1,62 @@
+pub mod access;
 pub mod app_state;
+pub mod archive;
+pub mod cli;
+pub mod client;
+pub mod delivery;
+pub mod downloads;
 pub mod fileserv;
 pub mod handlers;
+pub mod imaging;
+pub mod ingest;
+pub mod invitations;
+pub mod login_codes;
+pub mod login_guard;
 pub mod mailer;
+pub mod password_reset;
+pub mod policy;
+pub mod profile;
+pub mod reconcile;
+pub mod retention;
 pub mod storage;
 pub mod stripe;
+pub mod sync;
+pub mod tickets;
+pub mod tokens;
+pub mod uploads;
+pub mod user_admin;
+pub mod validation;
+pub mod verification;
+pub mod watermark;
 
 use crate::{
     auth::AuthSession,
     models::user::User,
     server::{
         app_state::AppState,
+        archive::order_archive_handler,
+        downloads::download_handler,
         fileserv::file_and_error_handler,
         handlers::{leptos_routes_handler, server_fn_handler},
+        ingest::{
+            add_item_handler, bind_order_handler, cancel_item_handler, confirm_item_handler,
+            login_handler, mark_uploaded_handler,
+        },
+        retention::spawn_retention_job,
+        storage::{
+            get_backend_kind,
+            local::{get_object_handler, put_object_handler},
+            StorageBackendKind,
+        },
+        sync,
+        tickets::ticket_pdf_handler,
+        verification::spawn_cleanup_job,
     },
 };
-use axum::{routing::get, Router};
+use axum::{
+    routing::{get, post},
+    Router,
+};
 use axum_session::{SessionConfig, SessionLayer, SessionMySqlPool, SessionStore};
 use axum_session_auth::AuthConfig;
 use leptos::*;
@@ -23,6 +65,14 @@ use crate::{auth::AuthSessionLayer, components::app::*};
 use leptos_axum::{generate_route_list, LeptosRoutes};
 use sqlx::mysql::MySqlPoolOptions;
 
+pub async fn connect_pool() -> MySqlPool {
+    let dburl = dotenvy::var("DATABASE_URL").expect("DATABASE_URL env variable should be present");
+    MySqlPoolOptions::new()
+        .connect(&dburl)
+        .await
+        .expect("Could not connect to MySQL")
+}
+
 pub async fn server_main() {
     use dotenvy;
     simple_logger::init_with_level(log::Level::Debug).expect("couldn't initialize logging");
@@ -36,28 +86,31 @@ pub async fn server_main() {
         "PHOTO_PRICING_ID_3",
         "PHOTO_UNIT_PRICE",
         "PHOTO_ZERO_PRICE",
-        "TOTP_DURATION",
-        "S3_BUCKET_NAME",
-        "S3_REGION",
-        "S3_ENDPOINT",
-        "S3_ACCESS_KEY",
-        "S3_SECRET_KEY",
+        "DOWNLOAD_TOKEN_SECRET",
         "SMTP_FROM_EMAIL",
         "SMTP_USERNAME",
         "SMTP_PASSWORD",
         "SMTP_RELAY",
     ];
 
-    env_vars.iter().for_each(|key| {
+    let storage_backend = get_backend_kind();
+    let storage_env_vars: &[&str] = match storage_backend {
+        StorageBackendKind::S3 => &[
+            "S3_BUCKET_NAME",
+            "S3_REGION",
+            "S3_ENDPOINT",
+            "S3_ACCESS_KEY",
+            "S3_SECRET_KEY",
+        ],
+        StorageBackendKind::Local => &["STORAGE_LOCAL_SECRET"],
+    };
+
+    env_vars.iter().chain(storage_env_vars).for_each(|key| {
         let error = format!("{} env variable should be present", key);
         dotenvy::var(key).expect(&error);
     });
 
-    let dburl = dotenvy::var("DATABASE_URL").expect("DATABASE_URL env variable should be present");
-    let pool = MySqlPoolOptions::new()
-        .connect(&dburl)
-        .await
-        .expect("Could not connect to MySQL");
+    let pool = connect_pool().await;
     let session_config = SessionConfig::default().with_table_name("axum_sessions");
     let auth_config = AuthConfig::<u64>::default().with_anonymous_user_id(Some(0));
     let session_store =
@@ -68,6 +121,8 @@ pub async fn server_main() {
         .run(&pool)
         .await
         .expect("Could not run SQLX migrations");
+    spawn_retention_job(pool.clone());
+    spawn_cleanup_job(pool.clone());
     // Setting get_configuration(None) means we'll be using cargo-leptos's env values
     // For deployment these variables are:
     // <https://github.com/leptos-rs/start-axum#executing-a-server-on-a-remote-machine-without-the-toolchain>
@@ -83,11 +138,33 @@ pub async fn server_main() {
         pool: pool.clone(),
     };
     // build our application with a route
-    let app = Router::new()
+    let mut app = Router::new()
         .route(
             "/api/*fn_name",
             get(server_fn_handler).post(server_fn_handler),
         )
+        .route("/archive/:order_id/:mode", get(order_archive_handler))
+        .route("/download/:item_id", get(download_handler))
+        .route("/ticket/:order_id/pdf", get(ticket_pdf_handler))
+        .route("/ingest/login", post(login_handler))
+        .route("/ingest/orders/:order_id", post(bind_order_handler))
+        .route("/ingest/orders/:order_id/items", post(add_item_handler))
+        .route("/ingest/orders/:order_id/uploaded", post(mark_uploaded_handler))
+        .route("/ingest/items/:item_id/confirm", post(confirm_item_handler))
+        .route("/ingest/items/:item_id/cancel", post(cancel_item_handler))
+        .route("/sync/orders", get(sync::orders_handler))
+        .route("/sync/orders/claim", post(sync::claim_handler))
+        .route("/sync/orders/:order_id/items", post(sync::add_item_handler))
+        .route("/sync/orders/:order_id/complete", post(sync::complete_handler))
+        .route("/sync/items/:item_id/confirm", post(sync::confirm_item_handler))
+        .route("/sync/items/:item_id/cancel", post(sync::cancel_item_handler));
+    if storage_backend == StorageBackendKind::Local {
+        app = app.route(
+            "/storage/*path",
+            get(get_object_handler).put(put_object_handler),
+        );
+    }
+    let app = app
         .leptos_routes_with_handler(routes, get(leptos_routes_handler))
         .fallback(file_and_error_handler)
         .layer(AuthSessionLayer::new(Some(pool.clone())).with_config(auth_config))
@@ -100,7 +177,7 @@ pub async fn server_main() {
     // `axum::Server` is a re-export of `hyper::Server`
     log!("listening on http://{}", &addr);
     axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<std::net::SocketAddr>())
         .await
         .unwrap();
 }
@@ -128,10 +205,3 @@ pub fn pool_and_current_user(cx: Scope) -> Result<(MySqlPool, User), ServerFnErr
             .map(|user| (pool, user))
     })
 }
-
-pub fn get_totp_duration() -> u64 {
-    let dur = dotenvy::var("TOTP_DURATION").unwrap_or("3600".into());
-    let dur = dur.parse().expect("TOTP_DURATION should be set");
-    leptos::log!("TOTP_DURATION: {}s", dur);
-    dur
-}
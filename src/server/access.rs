@@ -0,0 +1,280 @@
+use leptos::{log, ServerFnError};
+use sqlx::MySqlPool;
+
+use crate::models::{
+    order::Order,
+    order_item::{Mode, OrderItem},
+    user::{Role, User},
+};
+use crate::server::policy::NOT_AUTHORIZED;
+
+// Counter staff look up any order, processors only the ones they claimed and
+// customers only their own
+pub fn can_view_order(user: &User, order: &Order) -> bool {
+    match user.role {
+        Role::Cashier | Role::Operator | Role::Manager => true,
+        Role::Processor => order.processor_id == Some(user.id),
+        Role::Customer => order.customer_id == user.id,
+        Role::Anonymous => false,
+    }
+}
+
+pub fn can_change_items(user: &User, order: &Order, mode: Mode) -> bool {
+    match (&user.role, mode) {
+        (Role::Operator, Mode::Original) => true,
+        (Role::Processor, Mode::Processed) => order.processor_id == Some(user.id),
+        _ => false,
+    }
+}
+
+// Accepts `/<order>/<mode>` with an optional trailing file name, the layout used by get_prefix
+pub fn parse_storage_path(path: &str) -> Option<(u64, Mode, Option<&str>)> {
+    let mut parts = path.strip_prefix('/')?.splitn(3, '/');
+    let order_id = parts
+        .next()
+        .filter(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))?;
+    let mode = match parts.next()? {
+        "original" => Mode::Original,
+        "processed" => Mode::Processed,
+        _ => return None,
+    };
+    let file_name = match parts.next() {
+        None | Some("") => None,
+        Some(name) if name.contains('/') || name == "." || name == ".." => return None,
+        Some(name) => Some(name),
+    };
+    Some((order_id.parse().ok()?, mode, file_name))
+}
+
+fn denied(user: &User, order_id: u64) -> ServerFnError {
+    log!(
+        "Denied order {order_id} to user {} ({:?})",
+        user.id,
+        user.role
+    );
+    ServerFnError::ServerError(NOT_AUTHORIZED.to_string())
+}
+
+// Split from the loaders below so the decisions are testable without a database
+fn check_viewable(user: &User, order: Option<Order>) -> Result<Option<Order>, ServerFnError> {
+    match order {
+        Some(order) if !can_view_order(user, &order) => Err(denied(user, order.id)),
+        order => Ok(order),
+    }
+}
+
+fn check_changeable(user: &User, order: Option<Order>, mode: Mode) -> Result<Order, ServerFnError> {
+    let order = order.ok_or(ServerFnError::Args("Invalid Order Id".into()))?;
+    if !can_change_items(user, &order, mode) {
+        return Err(denied(user, order.id));
+    }
+    Ok(order)
+}
+
+pub async fn get_order_for_user(
+    order_id: u64,
+    user: &User,
+    pool: &MySqlPool,
+) -> Result<Option<Order>, ServerFnError> {
+    check_viewable(user, Order::get_by_id(order_id, pool).await?)
+}
+
+pub async fn get_viewable_order(
+    order_id: u64,
+    user: &User,
+    pool: &MySqlPool,
+) -> Result<Order, ServerFnError> {
+    get_order_for_user(order_id, user, pool)
+        .await?
+        .ok_or(ServerFnError::Args("Invalid Order Id".into()))
+}
+
+pub async fn get_changeable_order(
+    order_id: u64,
+    mode: Mode,
+    user: &User,
+    pool: &MySqlPool,
+) -> Result<Order, ServerFnError> {
+    check_changeable(user, Order::get_by_id(order_id, pool).await?, mode)
+}
+
+// Reloads the item, so nothing the client sent about it is trusted
+pub async fn get_changeable_order_item(
+    order_item_id: u64,
+    user: &User,
+    pool: &MySqlPool,
+) -> Result<(Order, OrderItem), ServerFnError> {
+    let order_item = OrderItem::get_by_id(order_item_id, pool).await?;
+    let order = get_changeable_order(order_item.order_id, order_item.mode, user, pool).await?;
+    Ok((order, order_item))
+}
+
+// Multipart uploads write straight to the item's path, so a confirmed photo is
+// never handed out for another upload
+pub async fn get_pending_order_item(
+    order_item_id: u64,
+    user: &User,
+    pool: &MySqlPool,
+) -> Result<(Order, OrderItem), ServerFnError> {
+    let (order, order_item) = get_changeable_order_item(order_item_id, user, pool).await?;
+    if order_item.uploaded {
+        return Err(ServerFnError::Args(
+            "Upload was already confirmed".to_string(),
+        ));
+    }
+    Ok((order, order_item))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+    use crate::models::order::{OrderStatus, PaymentMode};
+
+    fn user(id: u64, role: Role) -> User {
+        User {
+            id,
+            role,
+            ..Default::default()
+        }
+    }
+
+    fn order(customer_id: u64, processor_id: Option<u64>) -> Order {
+        Order {
+            id: 42,
+            customer_id,
+            cashier_id: None,
+            operator_id: None,
+            processor_id,
+            no_of_photos: 1,
+            order_total: 5,
+            mode_of_payment: PaymentMode::Cash,
+            order_ref: None,
+            payment_ref: None,
+            status: OrderStatus::Paid,
+            created_at: NaiveDateTime::default(),
+            payment_at: None,
+        }
+    }
+
+    #[test]
+    fn customers_only_see_their_own_orders() {
+        let order = order(1, None);
+        assert!(can_view_order(&user(1, Role::Customer), &order));
+        assert!(!can_view_order(&user(2, Role::Customer), &order));
+    }
+
+    #[test]
+    fn anonymous_users_see_no_orders() {
+        assert!(!can_view_order(&user(1, Role::Anonymous), &order(1, None)));
+    }
+
+    #[test]
+    fn processors_only_see_claimed_orders() {
+        let claimed = order(1, Some(7));
+        assert!(can_view_order(&user(7, Role::Processor), &claimed));
+        assert!(!can_view_order(&user(8, Role::Processor), &claimed));
+        assert!(!can_view_order(&user(7, Role::Processor), &order(1, None)));
+    }
+
+    #[test]
+    fn counter_staff_see_all_orders() {
+        for role in [Role::Cashier, Role::Operator, Role::Manager] {
+            assert!(can_view_order(&user(3, role), &order(1, None)));
+        }
+    }
+
+    #[test]
+    fn only_uploaders_change_items() {
+        let order = order(1, Some(7));
+        let operator = user(3, Role::Operator);
+        let processor = user(7, Role::Processor);
+        assert!(can_change_items(&operator, &order, Mode::Original));
+        assert!(!can_change_items(&operator, &order, Mode::Processed));
+        assert!(can_change_items(&processor, &order, Mode::Processed));
+        assert!(!can_change_items(&processor, &order, Mode::Original));
+        assert!(!can_change_items(
+            &user(8, Role::Processor),
+            &order,
+            Mode::Processed
+        ));
+        assert!(!can_change_items(
+            &user(1, Role::Customer),
+            &order,
+            Mode::Original
+        ));
+        assert!(!can_change_items(
+            &user(5, Role::Manager),
+            &order,
+            Mode::Original
+        ));
+    }
+
+    fn is_denied(result: Result<impl std::fmt::Debug, ServerFnError>) -> bool {
+        matches!(result, Err(ServerFnError::ServerError(message)) if message == NOT_AUTHORIZED)
+    }
+
+    #[test]
+    fn loading_an_order_checks_the_viewer() {
+        let customer = user(1, Role::Customer);
+        assert_eq!(
+            check_viewable(&customer, Some(order(1, None)))
+                .unwrap()
+                .map(|order| order.id),
+            Some(42)
+        );
+        assert!(is_denied(check_viewable(
+            &user(2, Role::Customer),
+            Some(order(1, None))
+        )));
+        assert!(is_denied(check_viewable(
+            &user(8, Role::Processor),
+            Some(order(1, Some(7)))
+        )));
+        assert!(check_viewable(&customer, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn loading_an_order_for_changes_checks_the_uploader() {
+        let processor = user(7, Role::Processor);
+        assert!(check_changeable(&processor, Some(order(1, Some(7))), Mode::Processed).is_ok());
+        assert!(is_denied(check_changeable(
+            &processor,
+            Some(order(1, Some(8))),
+            Mode::Processed
+        )));
+        assert!(is_denied(check_changeable(
+            &user(1, Role::Customer),
+            Some(order(1, None)),
+            Mode::Original
+        )));
+        assert!(matches!(
+            check_changeable(&processor, None, Mode::Processed),
+            Err(ServerFnError::Args(_))
+        ));
+    }
+
+    #[test]
+    fn storage_paths_are_scoped_to_one_order() {
+        assert_eq!(
+            parse_storage_path("/000042/original"),
+            Some((42, Mode::Original, None))
+        );
+        assert_eq!(
+            parse_storage_path("/000042/processed/"),
+            Some((42, Mode::Processed, None))
+        );
+        assert_eq!(
+            parse_storage_path("/000042/original/IMG_1.jpg"),
+            Some((42, Mode::Original, Some("IMG_1.jpg")))
+        );
+        assert_eq!(parse_storage_path(""), None);
+        assert_eq!(parse_storage_path("/"), None);
+        assert_eq!(parse_storage_path("/000042"), None);
+        assert_eq!(parse_storage_path("/000042/delivery/processed"), None);
+        assert_eq!(parse_storage_path("/000042/original/../000043"), None);
+        assert_eq!(parse_storage_path("/000042/original/.."), None);
+        assert_eq!(parse_storage_path("000042/original"), None);
+    }
+}
@@ -0,0 +1,517 @@
+use axum::{
+    body::{Bytes, StreamBody},
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{Datelike, Local, NaiveDateTime, Timelike};
+use leptos::{log, ServerFnError};
+use sqlx::MySqlPool;
+use tokio::sync::mpsc;
+
+use crate::auth::AuthSession;
+use crate::models::{
+    order::{Order, OrderStatus},
+    order_item::Mode,
+    user::{Role, User},
+};
+use crate::server::access::can_view_order;
+use crate::server::app_state::AppState;
+use crate::server::storage::{
+    get_delivery_prefix, get_object_range, get_order_item_path, head_object,
+};
+
+const CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+const ZIP64_LIMIT: u64 = u32::MAX as u64;
+const FLAGS: u16 = 0x0008 | 0x0800;
+const VERSION: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+
+struct ArchiveEntry {
+    path: String,
+    name: String,
+    size: u64,
+}
+
+struct CentralEntry {
+    name: String,
+    crc: u32,
+    size: u64,
+    offset: u64,
+}
+
+fn zip32(value: u64) -> u32 {
+    if value >= ZIP64_LIMIT {
+        u32::MAX
+    } else {
+        value as u32
+    }
+}
+
+// Writes a ZIP of stored (uncompressed) entries front to back. Sizes and CRCs
+// follow each entry in a data descriptor, so nothing needs to be buffered.
+pub struct ZipStream {
+    offset: u64,
+    time: u16,
+    date: u16,
+    entries: Vec<CentralEntry>,
+}
+
+impl ZipStream {
+    pub fn new(modified: NaiveDateTime) -> Self {
+        let time = (modified.hour() << 11 | modified.minute() << 5 | modified.second() / 2) as u16;
+        let date = ((modified.year().max(1980) - 1980) as u32) << 9
+            | modified.month() << 5
+            | modified.day();
+        Self {
+            offset: 0,
+            time,
+            date: date as u16,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn local_header(&mut self, name: &str, size: u64) -> Vec<u8> {
+        let zip64 = size >= ZIP64_LIMIT;
+        let mut header = Vec::new();
+        header.extend(0x04034b50u32.to_le_bytes());
+        header.extend(if zip64 { VERSION_ZIP64 } else { VERSION }.to_le_bytes());
+        header.extend(FLAGS.to_le_bytes());
+        header.extend(0u16.to_le_bytes());
+        header.extend(self.time.to_le_bytes());
+        header.extend(self.date.to_le_bytes());
+        header.extend(0u32.to_le_bytes());
+        let size_field = if zip64 { u32::MAX } else { 0 };
+        header.extend(size_field.to_le_bytes());
+        header.extend(size_field.to_le_bytes());
+        header.extend((name.len() as u16).to_le_bytes());
+        header.extend((if zip64 { 20u16 } else { 0 }).to_le_bytes());
+        header.extend(name.as_bytes());
+        if zip64 {
+            header.extend(0x0001u16.to_le_bytes());
+            header.extend(16u16.to_le_bytes());
+            header.extend(0u64.to_le_bytes());
+            header.extend(0u64.to_le_bytes());
+        }
+        self.entries.push(CentralEntry {
+            name: name.to_string(),
+            crc: 0,
+            size,
+            offset: self.offset,
+        });
+        self.offset += header.len() as u64;
+        header
+    }
+
+    pub fn data_descriptor(&mut self, crc: u32, size: u64) -> Vec<u8> {
+        let entry = self
+            .entries
+            .last_mut()
+            .expect("local header should be written first");
+        entry.crc = crc;
+        let zip64 = entry.size >= ZIP64_LIMIT;
+        entry.size = size;
+        let mut descriptor = Vec::new();
+        descriptor.extend(0x08074b50u32.to_le_bytes());
+        descriptor.extend(crc.to_le_bytes());
+        if zip64 {
+            descriptor.extend(size.to_le_bytes());
+            descriptor.extend(size.to_le_bytes());
+        } else {
+            descriptor.extend((size as u32).to_le_bytes());
+            descriptor.extend((size as u32).to_le_bytes());
+        }
+        self.offset += size + descriptor.len() as u64;
+        descriptor
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        let mut directory = Vec::new();
+        for entry in self.entries.iter() {
+            let mut extra = Vec::new();
+            if entry.size >= ZIP64_LIMIT {
+                extra.extend(entry.size.to_le_bytes());
+                extra.extend(entry.size.to_le_bytes());
+            }
+            if entry.offset >= ZIP64_LIMIT {
+                extra.extend(entry.offset.to_le_bytes());
+            }
+            let zip64 = !extra.is_empty();
+            let version = if zip64 { VERSION_ZIP64 } else { VERSION };
+            let size = zip32(entry.size);
+            directory.extend(0x02014b50u32.to_le_bytes());
+            directory.extend(version.to_le_bytes());
+            directory.extend(version.to_le_bytes());
+            directory.extend(FLAGS.to_le_bytes());
+            directory.extend(0u16.to_le_bytes());
+            directory.extend(self.time.to_le_bytes());
+            directory.extend(self.date.to_le_bytes());
+            directory.extend(entry.crc.to_le_bytes());
+            directory.extend(size.to_le_bytes());
+            directory.extend(size.to_le_bytes());
+            directory.extend((entry.name.len() as u16).to_le_bytes());
+            directory.extend((if zip64 { extra.len() as u16 + 4 } else { 0 }).to_le_bytes());
+            directory.extend(0u16.to_le_bytes());
+            directory.extend(0u16.to_le_bytes());
+            directory.extend(0u16.to_le_bytes());
+            directory.extend(0u32.to_le_bytes());
+            directory.extend(zip32(entry.offset).to_le_bytes());
+            directory.extend(entry.name.as_bytes());
+            if zip64 {
+                directory.extend(0x0001u16.to_le_bytes());
+                directory.extend((extra.len() as u16).to_le_bytes());
+                directory.extend(extra);
+            }
+        }
+
+        let count = self.entries.len() as u64;
+        let directory_size = directory.len() as u64;
+        let directory_offset = self.offset;
+        if count >= u16::MAX as u64
+            || directory_size >= ZIP64_LIMIT
+            || directory_offset >= ZIP64_LIMIT
+        {
+            let record_offset = directory_offset + directory_size;
+            directory.extend(0x06064b50u32.to_le_bytes());
+            directory.extend(44u64.to_le_bytes());
+            directory.extend(VERSION_ZIP64.to_le_bytes());
+            directory.extend(VERSION_ZIP64.to_le_bytes());
+            directory.extend(0u32.to_le_bytes());
+            directory.extend(0u32.to_le_bytes());
+            directory.extend(count.to_le_bytes());
+            directory.extend(count.to_le_bytes());
+            directory.extend(directory_size.to_le_bytes());
+            directory.extend(directory_offset.to_le_bytes());
+            directory.extend(0x07064b50u32.to_le_bytes());
+            directory.extend(0u32.to_le_bytes());
+            directory.extend(record_offset.to_le_bytes());
+            directory.extend(1u32.to_le_bytes());
+        }
+        directory.extend(0x06054b50u32.to_le_bytes());
+        directory.extend(0u16.to_le_bytes());
+        directory.extend(0u16.to_le_bytes());
+        directory.extend((count.min(u16::MAX as u64) as u16).to_le_bytes());
+        directory.extend((count.min(u16::MAX as u64) as u16).to_le_bytes());
+        directory.extend(zip32(directory_size).to_le_bytes());
+        directory.extend(zip32(directory_offset).to_le_bytes());
+        directory.extend(0u16.to_le_bytes());
+        directory
+    }
+}
+
+// Customers only get their processed photos, once the order is delivered
+pub fn can_download_archive(user: &User, order: &Order, mode: Mode) -> bool {
+    can_view_order(user, order)
+        && (user.role != Role::Customer
+            || (mode == Mode::Processed && order.status == OrderStatus::ReadyForDelivery))
+}
+
+async fn get_archive_entries(
+    order: &Order,
+    mode: Mode,
+    role: Role,
+    pool: &MySqlPool,
+) -> Result<Vec<ArchiveEntry>, ServerFnError> {
+    let mut entries = Vec::new();
+    for order_item in order.get_order_items(mode, pool).await? {
+        if !order_item.uploaded || order_item.purged_at.is_some() {
+            continue;
+        }
+        let (path, name) = match (&role, order_item.delivery_file_name.clone()) {
+            (_, Some(delivery_file_name)) => (
+                format!(
+                    "{}/{delivery_file_name}",
+                    get_delivery_prefix(order.id, mode)
+                ),
+                delivery_file_name,
+            ),
+            // Customers only ever receive the cleaned delivery copies
+            (Role::Customer, None) => continue,
+            (_, None) => (
+                get_order_item_path(&order_item),
+                order_item.file_name.clone(),
+            ),
+        };
+        let Some(object) = head_object(path.clone()).await? else {
+            log!("Skipping missing object {path} in archive");
+            continue;
+        };
+        entries.push(ArchiveEntry {
+            path,
+            name,
+            size: object.size,
+        });
+    }
+    Ok(entries)
+}
+
+async fn stream_archive(
+    entries: Vec<ArchiveEntry>,
+    sender: mpsc::Sender<Result<Bytes, std::io::Error>>,
+) -> Result<(), ServerFnError> {
+    let mut zip = ZipStream::new(Local::now().naive_local());
+    for entry in entries {
+        let header = zip.local_header(&entry.name, entry.size);
+        send(&sender, header).await?;
+        let mut hasher = crc32fast::Hasher::new();
+        let mut written = 0;
+        while written < entry.size {
+            let end = (written + CHUNK_SIZE).min(entry.size) - 1;
+            let chunk = get_object_range(entry.path.clone(), written, end).await?;
+            if chunk.is_empty() {
+                return Err(ServerFnError::ServerError(format!(
+                    "{} ended early",
+                    entry.path
+                )));
+            }
+            hasher.update(&chunk);
+            written += chunk.len() as u64;
+            send(&sender, chunk).await?;
+        }
+        let descriptor = zip.data_descriptor(hasher.finalize(), written);
+        send(&sender, descriptor).await?;
+    }
+    send(&sender, zip.finish()).await
+}
+
+async fn send(
+    sender: &mpsc::Sender<Result<Bytes, std::io::Error>>,
+    content: Vec<u8>,
+) -> Result<(), ServerFnError> {
+    sender
+        .send(Ok(Bytes::from(content)))
+        .await
+        .map_err(|_| ServerFnError::ServerError("Archive download was cancelled".to_string()))
+}
+
+pub async fn order_archive_handler(
+    State(AppState { pool, .. }): State<AppState>,
+    auth_session: AuthSession,
+    Path((order_id, mode)): Path<(u64, String)>,
+) -> impl IntoResponse {
+    let mode = match mode.as_str() {
+        "original" => Mode::Original,
+        "processed" => Mode::Processed,
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let Some(user) = auth_session.current_user else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let order = match Order::get_by_id(order_id, &pool).await {
+        Ok(Some(order)) => order,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            log!("Unable to load order {order_id}: {:#?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    if !can_download_archive(&user, &order, mode) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let entries = match get_archive_entries(&order, mode, user.role, &pool).await {
+        Ok(entries) if entries.is_empty() => return StatusCode::NOT_FOUND.into_response(),
+        Ok(entries) => entries,
+        Err(e) => {
+            log!("Unable to prepare archive for order {order_id}: {:#?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let (sender, receiver) = mpsc::channel(2);
+    tokio::spawn(async move {
+        if let Err(e) = stream_archive(entries, sender.clone()).await {
+            log!("Archive for order {order_id} failed: {:#?}", e);
+            _ = sender
+                .send(Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    e.to_string(),
+                )))
+                .await;
+        }
+    });
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|item| (item, receiver))
+    });
+    let file_name = format!("order-{:0>6}-{:?}.zip", order_id, mode).to_lowercase();
+    (
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{file_name}\""),
+            ),
+        ],
+        StreamBody::new(stream),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+    use super::*;
+
+    fn order(status: OrderStatus, processor_id: Option<u64>) -> Order {
+        Order {
+            id: 42,
+            customer_id: 1,
+            cashier_id: None,
+            operator_id: None,
+            processor_id,
+            no_of_photos: 1,
+            order_total: 5,
+            mode_of_payment: crate::models::order::PaymentMode::Cash,
+            order_ref: None,
+            payment_ref: None,
+            status,
+            created_at: NaiveDateTime::default(),
+            payment_at: None,
+        }
+    }
+
+    fn user(id: u64, role: Role) -> User {
+        User {
+            id,
+            role,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn archives_follow_order_access() {
+        let delivered = order(OrderStatus::ReadyForDelivery, Some(7));
+        let customer = user(1, Role::Customer);
+        assert!(can_download_archive(&customer, &delivered, Mode::Processed));
+        assert!(!can_download_archive(&customer, &delivered, Mode::Original));
+        assert!(!can_download_archive(
+            &customer,
+            &order(OrderStatus::InProcess, Some(7)),
+            Mode::Processed
+        ));
+        assert!(!can_download_archive(
+            &user(2, Role::Customer),
+            &delivered,
+            Mode::Processed
+        ));
+        assert!(can_download_archive(
+            &user(7, Role::Processor),
+            &delivered,
+            Mode::Original
+        ));
+        assert!(!can_download_archive(
+            &user(8, Role::Processor),
+            &delivered,
+            Mode::Original
+        ));
+        assert!(can_download_archive(
+            &user(3, Role::Cashier),
+            &delivered,
+            Mode::Original
+        ));
+        assert!(!can_download_archive(
+            &user(0, Role::Anonymous),
+            &delivered,
+            Mode::Processed
+        ));
+    }
+
+    fn modified() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2023-08-14 10:30:20", "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn write_entry(zip: &mut ZipStream, out: &mut impl Write, name: &str, content: &[u8]) {
+        out.write_all(&zip.local_header(name, content.len() as u64))
+            .unwrap();
+        out.write_all(content).unwrap();
+        out.write_all(&zip.data_descriptor(crc32fast::hash(content), content.len() as u64))
+            .unwrap();
+    }
+
+    #[test]
+    fn archives_read_back() {
+        let files: [(&str, &[u8]); 3] = [
+            ("IMG_0001.jpg", b"first photo"),
+            ("IMG_0002.jpg", &[0xff; 70_000]),
+            ("empty.jpg", b""),
+        ];
+        let mut zip = ZipStream::new(modified());
+        let mut out = Vec::new();
+        for (name, content) in files {
+            write_entry(&mut zip, &mut out, name, content);
+        }
+        out.extend(zip.finish());
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(out)).unwrap();
+        assert_eq!(archive.len(), files.len());
+        for (index, (name, content)) in files.into_iter().enumerate() {
+            let mut file = archive.by_index(index).unwrap();
+            assert_eq!(file.name(), name);
+            assert_eq!(file.size(), content.len() as u64);
+            assert_eq!(file.compression(), zip::CompressionMethod::Stored);
+            let modified = file.last_modified();
+            assert_eq!(
+                (modified.year(), modified.month(), modified.day()),
+                (2023, 8, 14)
+            );
+            assert_eq!(
+                (modified.hour(), modified.minute(), modified.second()),
+                (10, 30, 20)
+            );
+            let mut read = Vec::new();
+            // Fails on a CRC mismatch
+            file.read_to_end(&mut read).unwrap();
+            assert_eq!(read, content);
+        }
+    }
+
+    // The large entry is left as a hole in a sparse file, so it takes no disk space
+    #[test]
+    fn large_entries_use_zip64() {
+        let size = ZIP64_LIMIT + 10;
+        let path = std::env::temp_dir().join(format!("archive-test-{}.zip", std::process::id()));
+        let mut out = File::options()
+            .create(true)
+            .truncate(true)
+            .read(true)
+            .write(true)
+            .open(&path)
+            .unwrap();
+        let mut zip = ZipStream::new(modified());
+        write_entry(&mut zip, &mut out, "before.jpg", b"before");
+        out.write_all(&zip.local_header("large.tif", size)).unwrap();
+        out.seek(SeekFrom::Current(size as i64)).unwrap();
+        let mut hasher = crc32fast::Hasher::new();
+        let zeros = vec![0; CHUNK_SIZE as usize];
+        let mut hashed = 0;
+        while hashed < size {
+            let chunk = (size - hashed).min(CHUNK_SIZE) as usize;
+            hasher.update(&zeros[..chunk]);
+            hashed += chunk as u64;
+        }
+        let crc = hasher.finalize();
+        out.write_all(&zip.data_descriptor(crc, size)).unwrap();
+        write_entry(&mut zip, &mut out, "after.jpg", b"after");
+        out.write_all(&zip.finish()).unwrap();
+        out.flush().unwrap();
+        out.seek(SeekFrom::Start(0)).unwrap();
+
+        let result = std::panic::catch_unwind(move || {
+            let mut archive = zip::ZipArchive::new(out).unwrap();
+            assert_eq!(archive.len(), 3);
+            let large = archive.by_name("large.tif").unwrap();
+            assert_eq!(large.size(), size);
+            assert_eq!(large.compressed_size(), size);
+            assert_eq!(large.crc32(), crc);
+            drop(large);
+            let mut after = archive.by_name("after.jpg").unwrap();
+            assert!(after.data_start() > ZIP64_LIMIT);
+            let mut read = Vec::new();
+            after.read_to_end(&mut read).unwrap();
+            assert_eq!(read, b"after");
+        });
+        std::fs::remove_file(&path).unwrap();
+        result.unwrap();
+    }
+}
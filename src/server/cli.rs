@@ -0,0 +1,78 @@
+use crate::models::reconciliation::ReconcileMode;
+use crate::server::{connect_pool, reconcile::reconcile};
+
+const USAGE: &str =
+    "Usage: portrait-booth reconcile [--order <id>] [--repair | --quarantine] [--verify-checksums]";
+
+pub fn is_command(args: &[String]) -> bool {
+    matches!(args.first().map(String::as_str), Some("reconcile"))
+}
+
+pub async fn run(args: Vec<String>) {
+    let result = match args.first().map(String::as_str) {
+        Some("reconcile") => reconcile_command(&args[1..]).await,
+        _ => Err(USAGE.to_string()),
+    };
+    if let Err(e) = result {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+async fn reconcile_command(args: &[String]) -> Result<(), String> {
+    let mut order_id = None;
+    let mut mode = ReconcileMode::Report;
+    let mut verify_checksums = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--order" => {
+                let id = args.next().and_then(|id| id.parse::<u64>().ok());
+                order_id = Some(id.ok_or(USAGE.to_string())?);
+            }
+            "--repair" => mode = ReconcileMode::Repair,
+            "--quarantine" => mode = ReconcileMode::Quarantine,
+            "--verify-checksums" => verify_checksums = true,
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    let pool = connect_pool().await;
+    let reports = reconcile(order_id, mode, verify_checksums, &pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    if reports.is_empty() {
+        println!("Storage and database are in sync");
+    }
+    for report in reports {
+        println!("Order {:0>6}", report.order_id);
+        for key in report.orphaned {
+            println!("  orphaned: {key}");
+        }
+        for missing in report.missing {
+            println!("  missing: {} (item {})", missing.path, missing.item_id);
+        }
+        for mismatch in report.size_mismatches {
+            println!(
+                "  size mismatch: {} (item {}) expected {} bytes, found {} bytes",
+                mismatch.path, mismatch.item_id, mismatch.expected, mismatch.actual
+            );
+        }
+        for mismatch in report.checksum_mismatches {
+            println!(
+                "  checksum mismatch: {} (item {}) expected {}, found {}",
+                mismatch.path, mismatch.item_id, mismatch.expected, mismatch.actual
+            );
+        }
+        for duplicate in report.duplicates {
+            println!(
+                "  duplicate: {} (item {}) of item {}",
+                duplicate.path, duplicate.item_id, duplicate.duplicate_of
+            );
+        }
+        for error in report.errors {
+            println!("  error: {error}");
+        }
+    }
+    Ok(())
+}
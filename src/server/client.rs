@@ -0,0 +1,216 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use reqwest::{
+    header::{HeaderMap, HeaderValue, COOKIE, SET_COOKIE},
+    Client, Response,
+};
+use serde::de::DeserializeOwned;
+
+use crate::models::{
+    file_format::FileFormat,
+    ingest::{IngestFile, IngestLogin},
+    order_item::{Mode, OrderItem},
+};
+
+const PUT_MAX_ATTEMPTS: u32 = 3;
+
+// HTTP client shared by the hot-folder and processor-sync binaries
+pub struct IngestClient {
+    client: Client,
+    base_url: String,
+}
+
+async fn parse_response<T: DeserializeOwned>(response: Response) -> Result<T, String> {
+    let status = response.status();
+    if !status.is_success() {
+        let message = response.text().await.unwrap_or_default();
+        return Err(format!("{status}: {message}"));
+    }
+    response.json::<T>().await.map_err(|e| e.to_string())
+}
+
+fn is_supported(path: &Path, mode: Mode) -> bool {
+    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    !file_name.starts_with('.')
+        && FileFormat::from_file_name(file_name)
+            .map(|format| format.allowed_modes().contains(&mode))
+            .unwrap_or(false)
+}
+
+fn list_files(dir: &Path, mode: Mode) -> HashMap<PathBuf, u64> {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| Some((entry.path(), entry.metadata().ok()?)))
+                .filter(|(path, metadata)| metadata.is_file() && is_supported(path, mode))
+                .map(|(path, metadata)| (path, metadata.len()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub struct FolderWatcher {
+    dir: PathBuf,
+    mode: Mode,
+    seen: HashSet<PathBuf>,
+    pending: HashMap<PathBuf, u64>,
+}
+
+impl FolderWatcher {
+    pub fn new(dir: PathBuf, mode: Mode) -> Self {
+        Self {
+            dir,
+            mode,
+            seen: HashSet::new(),
+            pending: HashMap::new(),
+        }
+    }
+
+    // Leaves files that were in the folder before watching started alone
+    pub fn skip_existing(mut self) -> Self {
+        self.seen = list_files(&self.dir, self.mode).into_keys().collect();
+        self
+    }
+
+    // Returns files whose size has not changed since the previous poll,
+    // so nothing is picked up while the camera or editor is still writing it
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let mut ready = Vec::new();
+        for (path, size) in list_files(&self.dir, self.mode) {
+            if self.seen.contains(&path) {
+                continue;
+            }
+            if self.pending.insert(path.clone(), size) != Some(size) || size == 0 {
+                continue;
+            }
+            self.pending.remove(&path);
+            self.seen.insert(path.clone());
+            ready.push(path);
+        }
+        ready.sort();
+        ready
+    }
+}
+
+impl IngestClient {
+    pub async fn login(base_url: &str, username: String, password: String) -> Result<Self, String> {
+        let base_url = base_url.trim_end_matches('/').to_string();
+        let response = Client::new()
+            .post(format!("{base_url}/ingest/login"))
+            .json(&IngestLogin { username, password })
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let cookies = response
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|cookie| cookie.to_str().ok())
+            .filter_map(|cookie| cookie.split(';').next())
+            .collect::<Vec<_>>()
+            .join("; ");
+        parse_response::<serde_json::Value>(response).await?;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            COOKIE,
+            HeaderValue::from_str(&cookies).map_err(|e| e.to_string())?,
+        );
+        let client = Client::builder()
+            .default_headers(headers)
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self { client, base_url })
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path: String) -> Result<T, String> {
+        let request = self.client.get(format!("{}{path}", self.base_url));
+        parse_response(request.send().await.map_err(|e| e.to_string())?).await
+    }
+
+    pub async fn post<T: DeserializeOwned>(
+        &self,
+        path: String,
+        body: Option<&IngestFile>,
+    ) -> Result<T, String> {
+        let request = self.client.post(format!("{}{path}", self.base_url));
+        let request = match body {
+            Some(body) => request.json(body),
+            None => request,
+        };
+        parse_response(request.send().await.map_err(|e| e.to_string())?).await
+    }
+
+    async fn put(&self, url: &str, content: Vec<u8>) -> Result<(), String> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let result = self.client.put(url).body(content.clone()).send().await;
+            match result {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) if attempts >= PUT_MAX_ATTEMPTS => {
+                    return Err(format!("Upload failed with {}", response.status()))
+                }
+                Err(e) if attempts >= PUT_MAX_ATTEMPTS => return Err(e.to_string()),
+                _ => tokio::time::sleep(Duration::from_secs(2_u64.pow(attempts))).await,
+            }
+        }
+    }
+
+    // Writes to a temporary file first so a failed download never looks complete
+    pub async fn download(&self, order_item: &OrderItem, dest: &Path) -> Result<(), String> {
+        let response = self
+            .client
+            .get(format!("{}{}", self.base_url, order_item.get_url))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("Download failed with {}", response.status()));
+        }
+        let content = response.bytes().await.map_err(|e| e.to_string())?;
+        let partial = dest.with_extension("part");
+        tokio::fs::write(&partial, &content)
+            .await
+            .map_err(|e| e.to_string())?;
+        tokio::fs::rename(&partial, dest)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    // `prefix` is the API the order was opened through, `/ingest` or `/sync`
+    pub async fn upload(&self, prefix: &str, order_id: u64, path: &Path) -> Result<(), String> {
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or("Invalid file name")?
+            .to_string();
+        let content = tokio::fs::read(path).await.map_err(|e| e.to_string())?;
+        let file = IngestFile {
+            file_name,
+            file_size: content.len() as u64,
+        };
+        let order_item: OrderItem = self
+            .post(format!("{prefix}/orders/{order_id}/items"), Some(&file))
+            .await?;
+        let uploaded = match self.put(&order_item.put_url, content).await {
+            Ok(_) => {
+                self.post::<bool>(format!("{prefix}/items/{}/confirm", order_item.id), None)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = uploaded {
+            // Frees the slot so the photo can be sent again
+            let _ = self
+                .post::<bool>(format!("{prefix}/items/{}/cancel", order_item.id), None)
+                .await;
+            return Err(e);
+        }
+        Ok(())
+    }
+}
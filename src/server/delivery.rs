@@ -0,0 +1,430 @@
+use std::io::Cursor;
+
+use exif::{experimental::Writer, Field, In, Reader, Tag};
+use image::{codecs::jpeg::JpegEncoder, DynamicImage, ImageFormat, ImageOutputFormat};
+use img_parts::{jpeg::Jpeg, Bytes, DynImage, ImageEXIF, ImageICC};
+use leptos::{log, ServerFnError};
+use sqlx::MySqlPool;
+
+use crate::models::order::Order;
+use crate::models::order_item::{Mode, OrderItem};
+use crate::server::downloads::{get_download_path, get_signed_download_url};
+use crate::server::mailer::send_processed;
+use crate::server::storage::{get_delivery_prefix, get_object, get_prefix, put_object};
+use crate::to_server_fn_error;
+
+const DELIVERY_JPEG_QUALITY: u8 = 95;
+const DEFAULT_EXIF_ALLOWED_TAGS: &str = "Artist,Copyright,DateTimeOriginal,ExposureTime,FNumber,PhotographicSensitivity,FocalLength,ColorSpace";
+const XMP_PREFIX: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const APP1: u8 = 0xE1;
+const APP13: u8 = 0xED;
+
+pub fn get_exif_allowed_tags() -> Vec<String> {
+    dotenvy::var("EXIF_ALLOWED_TAGS")
+        .unwrap_or(DEFAULT_EXIF_ALLOWED_TAGS.into())
+        .split(',')
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+pub async fn prepare_order_for_delivery(
+    order: &Order,
+    pool: &MySqlPool,
+) -> Result<bool, ServerFnError> {
+    let allowed_tags = get_exif_allowed_tags();
+    // Every photo is attempted so a single bad file does not hide the others
+    let mut failed = Vec::new();
+    for order_item in order.get_order_items(Mode::Processed, pool).await? {
+        let file_name = order_item.file_name.clone();
+        if let Err(e) = prepare_for_delivery(order_item, allowed_tags.clone(), pool).await {
+            log!("Unable to prepare {file_name} for delivery: {:#?}", e);
+            failed.push(file_name);
+        }
+    }
+    if !failed.is_empty() {
+        return Err(ServerFnError::ServerError(format!(
+            "Unable to prepare {} for delivery",
+            failed.join(", ")
+        )));
+    }
+    Ok(true)
+}
+
+// Prepares the delivery copies and emails the customer their links
+pub async fn complete_order(order: &Order, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+    prepare_order_for_delivery(order, pool).await?;
+    if !order.mark_order_ready_for_delivery(pool).await? {
+        return Err(ServerFnError::ServerError(
+            "Unable to update order status".to_string(),
+        ));
+    }
+    order.set_delivered(pool).await?;
+    let customer = order.get_customer(pool).await?;
+    let links = order
+        .get_order_items(Mode::Processed, pool)
+        .await?
+        .into_iter()
+        .map(|order_item| get_signed_download_url(order_item.id))
+        .collect::<Result<Vec<String>, ServerFnError>>()?;
+    _ = send_processed(customer.email, customer.name, links).await;
+    Ok(true)
+}
+
+pub async fn prepare_for_delivery(
+    order_item: OrderItem,
+    allowed_tags: Vec<String>,
+    pool: &MySqlPool,
+) -> Result<bool, ServerFnError> {
+    let path = format!(
+        "{}/{}",
+        get_prefix(order_item.order_id, order_item.mode),
+        order_item.file_name
+    );
+    let content = get_object(path).await?;
+    let file_name = order_item.file_name.clone();
+    let (cleaned, delivery_file_name) =
+        tokio::task::spawn_blocking(move || clean_image(content, &file_name, &allowed_tags))
+            .await
+            .map_err(to_server_fn_error)??;
+    let mime_type = crate::components::files::uploader::get_mime_type(delivery_file_name.clone())?;
+    let prefix = get_delivery_prefix(order_item.order_id, order_item.mode);
+    put_object(
+        format!("{prefix}/{delivery_file_name}"),
+        cleaned,
+        mime_type,
+    )
+    .await?;
+    log!("Prepared {} for delivery", order_item.file_name);
+    order_item
+        .set_delivery_file(delivery_file_name, get_download_path(order_item.id), pool)
+        .await
+}
+
+pub fn clean_image(
+    content: Vec<u8>,
+    file_name: &str,
+    allowed_tags: &[String],
+) -> Result<(Vec<u8>, String), ServerFnError> {
+    let format = image::guess_format(&content).map_err(to_server_fn_error)?;
+    let exif = Reader::new()
+        .read_from_container(&mut Cursor::new(&content))
+        .ok();
+    let orientation = exif
+        .as_ref()
+        .and_then(|exif| exif.get_field(Tag::Orientation, In::PRIMARY))
+        .and_then(|field| field.value.get_uint(0))
+        .unwrap_or(1);
+    let allowed_fields = exif
+        .as_ref()
+        .map(|exif| {
+            exif.fields()
+                .filter(|field| field.ifd_num == In::PRIMARY && field.tag != Tag::Orientation)
+                .filter(|field| allowed_tags.contains(&field.tag.to_string()))
+                .cloned()
+                .collect::<Vec<Field>>()
+        })
+        .unwrap_or_default();
+    let stem = file_name
+        .rsplit_once('.')
+        .map(|(stem, _)| stem)
+        .unwrap_or(file_name);
+
+    match format {
+        ImageFormat::Jpeg => {
+            let content = if orientation == 1 {
+                content
+            } else {
+                let image = image::load_from_memory_with_format(&content, format)
+                    .map_err(to_server_fn_error)?;
+                let mut buffer = Cursor::new(Vec::new());
+                JpegEncoder::new_with_quality(&mut buffer, DELIVERY_JPEG_QUALITY)
+                    .encode_image(&apply_orientation(image, orientation))
+                    .map_err(to_server_fn_error)?;
+                copy_icc_profile(&content, buffer.into_inner())?
+            };
+            let mut jpeg = Jpeg::from_bytes(Bytes::from(content)).map_err(to_server_fn_error)?;
+            jpeg.segments_mut().retain(|segment| {
+                !(segment.marker() == APP13
+                    || (segment.marker() == APP1 && segment.contents().starts_with(XMP_PREFIX)))
+            });
+            jpeg.set_exif(encode_exif(&allowed_fields)?.map(Bytes::from));
+            let mut cleaned = Vec::new();
+            jpeg.encoder()
+                .write_to(&mut cleaned)
+                .map_err(to_server_fn_error)?;
+            Ok((cleaned, format!("{stem}.jpg")))
+        }
+        // PNG and TIFF keep their format, anything else (WebP, GIF) is delivered
+        // as a JPEG so every customer can open it. TIFF has no metadata writer
+        // here, so TIFF deliveries go out without any EXIF at all.
+        format => {
+            let image = image::load_from_memory_with_format(&content, format)
+                .map_err(to_server_fn_error)?;
+            let image = apply_orientation(image, orientation);
+            let (output_format, ext) = match format {
+                ImageFormat::Png => (ImageOutputFormat::Png, "png"),
+                ImageFormat::Tiff => (ImageOutputFormat::Tiff, "tiff"),
+                _ => (ImageOutputFormat::Jpeg(DELIVERY_JPEG_QUALITY), "jpg"),
+            };
+            let image = match output_format {
+                ImageOutputFormat::Jpeg(_) => DynamicImage::ImageRgb8(image.to_rgb8()),
+                _ => image,
+            };
+            let mut buffer = Cursor::new(Vec::new());
+            image
+                .write_to(&mut buffer, output_format)
+                .map_err(to_server_fn_error)?;
+            let cleaned = copy_icc_profile(&content, buffer.into_inner())?;
+            let cleaned = set_allowed_exif(cleaned, &allowed_fields)?;
+            Ok((cleaned, format!("{stem}.{ext}")))
+        }
+    }
+}
+
+// The image crate drops the colour profile when encoding, it is carried over
+// from the original wherever img-parts can read and write it
+fn copy_icc_profile(original: &[u8], encoded: Vec<u8>) -> Result<Vec<u8>, ServerFnError> {
+    let profile = DynImage::from_bytes(Bytes::copy_from_slice(original))
+        .ok()
+        .flatten()
+        .and_then(|image| image.icc_profile());
+    let Some(profile) = profile else {
+        return Ok(encoded);
+    };
+    let encoded = Bytes::from(encoded);
+    let Some(mut image) = DynImage::from_bytes(encoded.clone()).map_err(to_server_fn_error)? else {
+        return Ok(encoded.to_vec());
+    };
+    image.set_icc_profile(Some(profile));
+    let mut buffer = Vec::new();
+    image
+        .encoder()
+        .write_to(&mut buffer)
+        .map_err(to_server_fn_error)?;
+    Ok(buffer)
+}
+
+// Writes the allowed tags wherever img-parts can carry EXIF, the image crate
+// drops all metadata when encoding
+fn set_allowed_exif(encoded: Vec<u8>, fields: &[Field]) -> Result<Vec<u8>, ServerFnError> {
+    let Some(exif) = encode_exif(fields)? else {
+        return Ok(encoded);
+    };
+    let encoded = Bytes::from(encoded);
+    let Some(mut image) = DynImage::from_bytes(encoded.clone()).map_err(to_server_fn_error)? else {
+        return Ok(encoded.to_vec());
+    };
+    image.set_exif(Some(Bytes::from(exif)));
+    let mut buffer = Vec::new();
+    image
+        .encoder()
+        .write_to(&mut buffer)
+        .map_err(to_server_fn_error)?;
+    Ok(buffer)
+}
+
+fn apply_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+fn encode_exif(fields: &[Field]) -> Result<Option<Vec<u8>>, ServerFnError> {
+    if fields.is_empty() {
+        return Ok(None);
+    }
+    let mut writer = Writer::new();
+    fields.iter().for_each(|field| writer.push_field(field));
+    let mut buffer = Cursor::new(Vec::new());
+    writer
+        .write(&mut buffer, false)
+        .map_err(to_server_fn_error)?;
+    Ok(Some(buffer.into_inner()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::{Rational, Value};
+    use img_parts::{jpeg::JpegSegment, png::Png};
+
+    const ICC_PROFILE: &[u8] = b"test icc profile";
+    // A 1x1 lossless WebP
+    const WEBP: &[u8] = &[
+        0x52, 0x49, 0x46, 0x46, 0x1a, 0x00, 0x00, 0x00, 0x57, 0x45, 0x42, 0x50, 0x56, 0x50, 0x38,
+        0x4c, 0x0d, 0x00, 0x00, 0x00, 0x2f, 0x00, 0x00, 0x00, 0x10, 0x07, 0x10, 0x11, 0x11, 0x88,
+        0x88, 0xfe, 0x07, 0x00,
+    ];
+
+    fn allowed_tags() -> Vec<String> {
+        vec!["Artist".to_string(), "Copyright".to_string()]
+    }
+
+    fn ascii(tag: Tag, value: &str) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![value.as_bytes().to_vec()]),
+        }
+    }
+
+    // Camera metadata as it comes off a phone: EXIF with GPS, XMP and IPTC
+    fn tagged_fields() -> Vec<Field> {
+        let degrees = |value| Rational {
+            num: value,
+            denom: 1,
+        };
+        vec![
+            ascii(Tag::Artist, "Booth Photographer"),
+            ascii(Tag::Make, "Phone Maker"),
+            ascii(Tag::GPSLatitudeRef, "N"),
+            Field {
+                tag: Tag::GPSLatitude,
+                ifd_num: In::PRIMARY,
+                value: Value::Rational(vec![degrees(51), degrees(30), degrees(0)]),
+            },
+        ]
+    }
+
+    fn read_exif(content: &[u8]) -> Option<exif::Exif> {
+        Reader::new()
+            .read_from_container(&mut Cursor::new(content))
+            .ok()
+    }
+
+    fn tag_names(content: &[u8]) -> Vec<String> {
+        read_exif(content)
+            .map(|exif| exif.fields().map(|field| field.tag.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    fn tagged_jpeg() -> Vec<u8> {
+        let mut buffer = Cursor::new(Vec::new());
+        JpegEncoder::new(&mut buffer)
+            .encode_image(&DynamicImage::new_rgb8(4, 2))
+            .unwrap();
+        let mut jpeg = Jpeg::from_bytes(Bytes::from(buffer.into_inner())).unwrap();
+        jpeg.set_exif(encode_exif(&tagged_fields()).unwrap().map(Bytes::from));
+        let mut xmp = XMP_PREFIX.to_vec();
+        xmp.extend_from_slice(b"<x:xmpmeta>location</x:xmpmeta>");
+        let iptc = b"Photoshop 3.0\08BIM".to_vec();
+        jpeg.segments_mut()
+            .insert(1, JpegSegment::new_with_contents(APP1, Bytes::from(xmp)));
+        jpeg.segments_mut()
+            .insert(1, JpegSegment::new_with_contents(APP13, Bytes::from(iptc)));
+        let mut content = Vec::new();
+        jpeg.encoder().write_to(&mut content).unwrap();
+        content
+    }
+
+    #[test]
+    fn jpegs_keep_only_allowed_tags() {
+        let content = tagged_jpeg();
+        assert!(tag_names(&content).contains(&"GPSLatitude".to_string()));
+        let (cleaned, file_name) = clean_image(content, "photo.jpg", &allowed_tags()).unwrap();
+        assert_eq!(file_name, "photo.jpg");
+        assert_eq!(tag_names(&cleaned), vec!["Artist".to_string()]);
+        let jpeg = Jpeg::from_bytes(Bytes::from(cleaned)).unwrap();
+        assert!(jpeg
+            .segments()
+            .iter()
+            .all(|segment| segment.marker() != APP13));
+        assert!(!jpeg
+            .segments()
+            .iter()
+            .any(|segment| segment.contents().starts_with(XMP_PREFIX)));
+    }
+
+    #[test]
+    fn pngs_keep_only_allowed_tags() {
+        let mut buffer = Cursor::new(Vec::new());
+        DynamicImage::new_rgb8(4, 2)
+            .write_to(&mut buffer, ImageOutputFormat::Png)
+            .unwrap();
+        let mut png = Png::from_bytes(Bytes::from(buffer.into_inner())).unwrap();
+        png.set_exif(encode_exif(&tagged_fields()).unwrap().map(Bytes::from));
+        let mut content = Vec::new();
+        png.encoder().write_to(&mut content).unwrap();
+
+        let (cleaned, file_name) = clean_image(content, "photo.png", &allowed_tags()).unwrap();
+        assert_eq!(file_name, "photo.png");
+        assert_eq!(tag_names(&cleaned), vec!["Artist".to_string()]);
+    }
+
+    #[test]
+    fn tiffs_are_delivered_without_metadata() {
+        let mut buffer = Cursor::new(Vec::new());
+        DynamicImage::new_rgb8(4, 2)
+            .write_to(&mut buffer, ImageOutputFormat::Tiff)
+            .unwrap();
+        let (cleaned, file_name) =
+            clean_image(buffer.into_inner(), "photo.tif", &allowed_tags()).unwrap();
+        assert_eq!(file_name, "photo.tiff");
+        assert_eq!(image::guess_format(&cleaned).unwrap(), ImageFormat::Tiff);
+        let names = tag_names(&cleaned);
+        assert!(!names.contains(&"Artist".to_string()));
+        assert!(!names.contains(&"GPSLatitude".to_string()));
+    }
+
+    #[test]
+    fn webps_are_delivered_as_jpegs() {
+        let (cleaned, file_name) =
+            clean_image(WEBP.to_vec(), "photo.webp", &allowed_tags()).unwrap();
+        assert_eq!(file_name, "photo.jpg");
+        assert_eq!(image::guess_format(&cleaned).unwrap(), ImageFormat::Jpeg);
+    }
+
+    fn rotated_jpeg() -> Vec<u8> {
+        let mut buffer = Cursor::new(Vec::new());
+        JpegEncoder::new(&mut buffer)
+            .encode_image(&DynamicImage::new_rgb8(4, 2))
+            .unwrap();
+        let mut jpeg = Jpeg::from_bytes(Bytes::from(buffer.into_inner())).unwrap();
+        let orientation = Field {
+            tag: Tag::Orientation,
+            ifd_num: In::PRIMARY,
+            value: Value::Short(vec![6]),
+        };
+        jpeg.set_exif(encode_exif(&[orientation]).unwrap().map(Bytes::from));
+        jpeg.set_icc_profile(Some(Bytes::from_static(ICC_PROFILE)));
+        let mut content = Vec::new();
+        jpeg.encoder().write_to(&mut content).unwrap();
+        content
+    }
+
+    #[test]
+    fn rotated_jpegs_keep_their_icc_profile() {
+        let (cleaned, file_name) = clean_image(rotated_jpeg(), "photo.jpeg", &[]).unwrap();
+        assert_eq!(file_name, "photo.jpg");
+        let image = image::load_from_memory(&cleaned).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 4));
+        let jpeg = Jpeg::from_bytes(Bytes::from(cleaned)).unwrap();
+        assert_eq!(jpeg.exif(), None);
+        assert_eq!(jpeg.icc_profile(), Some(Bytes::from_static(ICC_PROFILE)));
+    }
+
+    #[test]
+    fn reencoded_pngs_keep_their_icc_profile() {
+        let mut buffer = Cursor::new(Vec::new());
+        DynamicImage::new_rgb8(4, 2)
+            .write_to(&mut buffer, ImageOutputFormat::Png)
+            .unwrap();
+        let mut png = Png::from_bytes(Bytes::from(buffer.into_inner())).unwrap();
+        png.set_icc_profile(Some(Bytes::from_static(ICC_PROFILE)));
+        let mut content = Vec::new();
+        png.encoder().write_to(&mut content).unwrap();
+
+        let (cleaned, file_name) = clean_image(content, "photo.png", &[]).unwrap();
+        assert_eq!(file_name, "photo.png");
+        let png = Png::from_bytes(Bytes::from(cleaned)).unwrap();
+        assert_eq!(png.icc_profile(), Some(Bytes::from_static(ICC_PROFILE)));
+    }
+}
@@ -0,0 +1,319 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+};
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use leptos::{log, ServerFnError};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::auth::AuthSession;
+use crate::components::files::uploader::get_mime_type;
+use crate::models::{
+    order::{Order, OrderStatus},
+    order_item::{Derivative, Mode, OrderItem},
+    user::{Role, User},
+};
+use crate::server::access::can_view_order;
+use crate::server::app_state::AppState;
+use crate::server::imaging::{get_derivative_file_name, DERIVATIVES};
+use crate::server::storage::{
+    create_download_url, get_delivery_prefix, get_derivative_prefix, get_prefix,
+};
+use crate::to_server_fn_error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// How long the links emailed on delivery keep working
+const DOWNLOAD_LINK_DAYS: i64 = 30;
+
+#[derive(Debug, Deserialize)]
+pub struct DownloadQuery {
+    token: Option<String>,
+    expires: Option<i64>,
+    variant: Option<String>,
+}
+
+pub fn get_download_path(item_id: u64) -> String {
+    format!("/download/{item_id}")
+}
+
+pub fn get_derivative_download_path(item_id: u64, derivative: Derivative) -> String {
+    format!(
+        "{}?variant={}",
+        get_download_path(item_id),
+        derivative.name()
+    )
+}
+
+fn download_token_secret() -> Result<String, ServerFnError> {
+    dotenvy::var("DOWNLOAD_TOKEN_SECRET").map_err(to_server_fn_error)
+}
+
+// The expiry is signed along with the path so a link can't be extended
+fn download_token(secret: &str, item_id: u64, expires: i64) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key size");
+    mac.update(format!("{}\n{expires}", get_download_path(item_id)).as_bytes());
+    mac
+}
+
+pub fn get_signed_download_url(item_id: u64) -> Result<String, ServerFnError> {
+    let app_url = dotenvy::var("APP_URL").map_err(to_server_fn_error)?;
+    let expires = (Utc::now() + Duration::days(DOWNLOAD_LINK_DAYS)).timestamp();
+    let token = download_token(&download_token_secret()?, item_id, expires)
+        .finalize()
+        .into_bytes();
+    Ok(format!(
+        "{}{}?expires={expires}&token={}",
+        app_url.trim_end_matches('/'),
+        get_download_path(item_id),
+        hex::encode(token)
+    ))
+}
+
+fn verify_download_token(secret: &str, item_id: u64, expires: i64, token: &str, now: i64) -> bool {
+    if expires < now {
+        return false;
+    }
+    let Ok(token) = hex::decode(token) else {
+        return false;
+    };
+    download_token(secret, item_id, expires)
+        .verify_slice(&token)
+        .is_ok()
+}
+
+fn get_download_location(order_item: &OrderItem, customer: bool) -> Option<(String, String)> {
+    match order_item.delivery_file_name.clone() {
+        Some(delivery_file_name) => Some((
+            get_delivery_prefix(order_item.order_id, order_item.mode),
+            delivery_file_name,
+        )),
+        // Customers only ever receive the cleaned delivery copies
+        None if customer => None,
+        None => Some((
+            get_prefix(order_item.order_id, order_item.mode),
+            order_item.file_name.clone(),
+        )),
+    }
+}
+
+fn get_derivative_location(
+    order_item: &OrderItem,
+    derivative: Derivative,
+) -> Option<(String, String)> {
+    let generated = match derivative {
+        Derivative::Thumbnail => order_item.thumbnail_url.is_some(),
+        Derivative::Preview => order_item.preview_url.is_some(),
+        Derivative::Watermarked => order_item.watermarked_url.is_some(),
+    };
+    generated.then(|| {
+        (
+            get_derivative_prefix(order_item.order_id, order_item.mode, derivative),
+            get_derivative_file_name(&order_item.file_name),
+        )
+    })
+}
+
+// Signed in users go through the same order check as the rest of the app, the
+// emailed token lets customers in without a session. Ok(true) means the caller
+// only gets what a customer may see.
+fn check_download(user: Option<&User>, order: &Order, has_token: bool) -> Result<bool, StatusCode> {
+    match user.filter(|user| user.role != Role::Anonymous) {
+        Some(user) if can_view_order(user, order) => Ok(user.role == Role::Customer),
+        _ if has_token => Ok(true),
+        Some(_) => Err(StatusCode::FORBIDDEN),
+        None => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+pub async fn download_handler(
+    State(AppState { pool, .. }): State<AppState>,
+    auth_session: AuthSession,
+    Path(item_id): Path<u64>,
+    Query(query): Query<DownloadQuery>,
+) -> Response {
+    let Ok(order_item) = OrderItem::get_by_id(item_id, &pool).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let order = match Order::get_by_id(order_item.order_id, &pool).await {
+        Ok(Some(order)) => order,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            log!("Unable to load order {}: {:#?}", order_item.order_id, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let has_token = match (query.token, query.expires, download_token_secret()) {
+        (Some(token), Some(expires), Ok(secret)) => {
+            verify_download_token(&secret, item_id, expires, &token, Utc::now().timestamp())
+        }
+        _ => false,
+    };
+    let customer_view = match check_download(auth_session.current_user.as_ref(), &order, has_token)
+    {
+        Ok(customer_view) => customer_view,
+        Err(status) => return status.into_response(),
+    };
+    // Only those allowed to download learn that the photo has been removed
+    if order_item.purged_at.is_some() {
+        return StatusCode::GONE.into_response();
+    }
+    let variant = match query.variant {
+        Some(variant) => match DERIVATIVES
+            .into_iter()
+            .find(|derivative| derivative.name() == variant)
+        {
+            Some(derivative) => Some(derivative),
+            None => return StatusCode::BAD_REQUEST.into_response(),
+        },
+        None => None,
+    };
+    // Until delivery customers only get to see the watermarked previews
+    if customer_view
+        && variant != Some(Derivative::Watermarked)
+        && (order_item.mode != Mode::Processed || order.status != OrderStatus::ReadyForDelivery)
+    {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let location = match variant {
+        Some(derivative) => get_derivative_location(&order_item, derivative),
+        None => get_download_location(&order_item, customer_view),
+    };
+    let Some((prefix, file_name)) = location else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let url = match get_mime_type(file_name.clone()) {
+        Ok(mime_type) => create_download_url(prefix, file_name, mime_type).await,
+        Err(e) => Err(e),
+    };
+    match url {
+        Ok(url) => Redirect::temporary(&url).into_response(),
+        Err(e) => {
+            log!("Unable to create download url for {item_id}: {:#?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::order::PaymentMode;
+    use chrono::NaiveDateTime;
+
+    fn user(id: u64, role: Role) -> User {
+        User {
+            id,
+            role,
+            ..Default::default()
+        }
+    }
+
+    fn order(customer_id: u64, processor_id: Option<u64>) -> Order {
+        Order {
+            id: 42,
+            customer_id,
+            cashier_id: None,
+            operator_id: None,
+            processor_id,
+            no_of_photos: 1,
+            order_total: 5,
+            mode_of_payment: PaymentMode::Cash,
+            order_ref: None,
+            payment_ref: None,
+            status: OrderStatus::ReadyForDelivery,
+            created_at: NaiveDateTime::default(),
+            payment_at: None,
+        }
+    }
+
+    #[test]
+    fn processors_only_download_claimed_orders() {
+        let processor = user(7, Role::Processor);
+        assert_eq!(
+            check_download(Some(&processor), &order(1, None), false),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            check_download(Some(&processor), &order(1, Some(8)), false),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            check_download(Some(&processor), &order(1, Some(7)), false),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn customers_download_their_own_orders_or_with_a_token() {
+        let order = order(1, None);
+        assert_eq!(
+            check_download(Some(&user(1, Role::Customer)), &order, false),
+            Ok(true)
+        );
+        assert_eq!(
+            check_download(Some(&user(2, Role::Customer)), &order, false),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            check_download(None, &order, false),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            check_download(Some(&user(0, Role::Anonymous)), &order, false),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(check_download(None, &order, true), Ok(true));
+    }
+
+    #[test]
+    fn counter_staff_get_the_full_download() {
+        let order = order(1, None);
+        for role in [Role::Cashier, Role::Operator, Role::Manager] {
+            assert_eq!(
+                check_download(Some(&user(3, role)), &order, false),
+                Ok(false)
+            );
+        }
+    }
+
+    const EXPIRES: i64 = 1_700_000_000;
+
+    fn token(secret: &str, item_id: u64) -> String {
+        hex::encode(
+            download_token(secret, item_id, EXPIRES)
+                .finalize()
+                .into_bytes(),
+        )
+    }
+
+    #[test]
+    fn download_tokens_verify_until_they_expire() {
+        let token = token("secret", 5);
+        let (before, after) = (EXPIRES - 60, EXPIRES + 1);
+        assert!(verify_download_token("secret", 5, EXPIRES, &token, before));
+        assert!(verify_download_token("secret", 5, EXPIRES, &token, EXPIRES));
+        assert!(!verify_download_token("secret", 5, EXPIRES, &token, after));
+    }
+
+    #[test]
+    fn tampered_download_tokens_are_refused() {
+        let now = EXPIRES - 60;
+        let token = token("secret", 5);
+        let later = EXPIRES + 3600;
+        assert!(!verify_download_token("secret", 6, EXPIRES, &token, now));
+        assert!(!verify_download_token("secret", 5, later, &token, now));
+        assert!(!verify_download_token("other", 5, EXPIRES, &token, now));
+        assert!(!verify_download_token(
+            "secret",
+            5,
+            EXPIRES,
+            &token[2..],
+            now
+        ));
+        assert!(!verify_download_token("secret", 5, EXPIRES, "not-hex", now));
+    }
+}
@@ -1,9 +1,11 @@
+use std::net::SocketAddr;
+
 use axum::{
     body::Body as AxumBody,
-    extract::{Path, RawQuery, State},
+    extract::{ConnectInfo, Path, RawQuery, State},
     response::{IntoResponse, Response},
 };
-use http::{HeaderMap, Request};
+use http::{HeaderMap, Request, StatusCode};
 use leptos::{log, provide_context, *};
 use leptos_axum::handle_server_fns_with_context;
 
@@ -12,19 +14,24 @@ use crate::{
     components::app::App,
 };
 
-use super::app_state::AppState;
+use super::{app_state::AppState, login_guard::client_ip, policy};
 
 pub async fn server_fn_handler(
     //Extension(pool): Extension<MySqlPool>,
     State(AppState { pool, .. }): State<AppState>,
     session: Session,
     auth_session: AuthSession,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     path: Path<String>,
     headers: HeaderMap,
     raw_query: RawQuery,
     request: Request<AxumBody>,
 ) -> impl IntoResponse {
     log!("{:?}", path);
+    if policy::check(&path, auth_session.current_user.as_ref()).is_err() {
+        return (StatusCode::FORBIDDEN, policy::NOT_AUTHORIZED).into_response();
+    }
+    let ip = client_ip(&headers, addr);
 
     handle_server_fns_with_context(
         path,
@@ -34,10 +41,12 @@ pub async fn server_fn_handler(
             provide_context(cx, session.clone());
             provide_context(cx, auth_session.clone());
             provide_context(cx, pool.clone());
+            provide_context(cx, ip.clone());
         },
         request,
     )
     .await
+    .into_response()
 }
 
 pub async fn leptos_routes_handler(
@@ -0,0 +1,141 @@
+use std::io::Cursor;
+
+use image::{codecs::jpeg::JpegEncoder, DynamicImage};
+use leptos::{log, ServerFnError};
+use sqlx::MySqlPool;
+
+use crate::models::{
+    file_format::FileFormat,
+    order_item::{Derivative, OrderItem},
+};
+use crate::server::downloads::get_derivative_download_path;
+use crate::server::storage::{get_derivative_prefix, get_object, get_prefix, put_object};
+use crate::server::watermark::Watermark;
+use crate::to_server_fn_error;
+
+const DERIVATIVE_JPEG_QUALITY: u8 = 80;
+pub const DERIVATIVES: [Derivative; 3] = [
+    Derivative::Thumbnail,
+    Derivative::Preview,
+    Derivative::Watermarked,
+];
+
+pub fn get_derivative_file_name(file_name: &str) -> String {
+    let stem = file_name
+        .rsplit_once('.')
+        .map(|(stem, _)| stem)
+        .unwrap_or(file_name);
+    format!("{stem}.jpg")
+}
+
+pub fn render_derivative(
+    image: &DynamicImage,
+    derivative: Derivative,
+    watermark: Option<&Watermark>,
+) -> Result<Vec<u8>, ServerFnError> {
+    let size = derivative.max_dimension();
+    let resized = if image.width() > size || image.height() > size {
+        image.thumbnail(size, size)
+    } else {
+        image.clone()
+    };
+    match (derivative, watermark) {
+        (Derivative::Watermarked, Some(watermark)) => encode_jpeg(&watermark.apply(&resized)),
+        (Derivative::Watermarked, None) => Err(ServerFnError::ServerError(
+            "Watermark is not configured".to_string(),
+        )),
+        _ => encode_jpeg(&resized),
+    }
+}
+
+pub fn encode_jpeg(image: &DynamicImage) -> Result<Vec<u8>, ServerFnError> {
+    let mut buffer = Cursor::new(Vec::new());
+    JpegEncoder::new_with_quality(&mut buffer, DERIVATIVE_JPEG_QUALITY)
+        .encode_image(&DynamicImage::ImageRgb8(image.to_rgb8()))
+        .map_err(to_server_fn_error)?;
+    Ok(buffer.into_inner())
+}
+
+pub async fn generate_derivatives(
+    order_item: OrderItem,
+    pool: &MySqlPool,
+) -> Result<bool, ServerFnError> {
+    let OrderItem {
+        order_id,
+        mode,
+        file_name,
+        ..
+    } = order_item.clone();
+    let decodable = FileFormat::from_file_name(&file_name)
+        .map(|format| format.has_preview())
+        .unwrap_or(false);
+    if !decodable {
+        log!("No previews can be generated for {file_name}");
+        return order_item.set_preview_unavailable(pool).await;
+    }
+    let path = format!("{}/{}", get_prefix(order_id, mode), file_name);
+    let content = get_object(path).await?;
+    let image = match tokio::task::spawn_blocking(move || image::load_from_memory(&content))
+        .await
+        .map_err(to_server_fn_error)?
+    {
+        Ok(image) => image,
+        Err(e) => {
+            log!("Unable to decode {file_name}: {:#?}", e);
+            return order_item.set_preview_unavailable(pool).await;
+        }
+    };
+    let derivatives = tokio::task::spawn_blocking(move || {
+        let watermark = Watermark::from_env()
+            .map_err(|e| log!("Skipping watermarked preview: {:#?}", e))
+            .ok();
+        let mut derivatives = Vec::new();
+        for derivative in DERIVATIVES {
+            if derivative == Derivative::Watermarked && watermark.is_none() {
+                continue;
+            }
+            let bytes = render_derivative(&image, derivative, watermark.as_ref())?;
+            derivatives.push((derivative, bytes));
+        }
+        Ok::<_, ServerFnError>(derivatives)
+    })
+    .await
+    .map_err(to_server_fn_error)??;
+
+    let derivative_file_name = get_derivative_file_name(&file_name);
+    for (derivative, bytes) in derivatives.iter() {
+        let prefix = get_derivative_prefix(order_id, mode, *derivative);
+        put_object(
+            format!("{prefix}/{derivative_file_name}"),
+            bytes.clone(),
+            "image/jpeg".to_string(),
+        )
+        .await?;
+    }
+    let derivatives = derivatives
+        .into_iter()
+        .map(|(derivative, _)| derivative)
+        .collect::<Vec<_>>();
+    update_derivative_urls(&order_item, &derivatives, pool).await
+}
+
+// The stored urls point at the download route, which mints a presigned url on
+// every request
+async fn update_derivative_urls(
+    order_item: &OrderItem,
+    derivatives: &[Derivative],
+    pool: &MySqlPool,
+) -> Result<bool, ServerFnError> {
+    let (mut thumbnail_url, mut preview_url, mut watermarked_url) = (None, None, None);
+    for derivative in derivatives {
+        let url = Some(get_derivative_download_path(order_item.id, *derivative));
+        match derivative {
+            Derivative::Thumbnail => thumbnail_url = url,
+            Derivative::Preview => preview_url = url,
+            Derivative::Watermarked => watermarked_url = url,
+        }
+    }
+    order_item
+        .update_derivative_urls(thumbnail_url, preview_url, watermarked_url, pool)
+        .await
+}
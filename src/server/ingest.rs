@@ -0,0 +1,196 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use leptos::{log, ServerFnError};
+
+use crate::auth::AuthSession;
+use crate::components::auth::login::{authenticate, LoginResponse};
+use crate::models::{
+    ingest::{IngestFile, IngestLogin, IngestOrder},
+    order::{parse_order_reference, Order, OrderStatus},
+    order_item::{Mode, OrderItem},
+    user::{Role, User},
+};
+use crate::server::app_state::AppState;
+use crate::server::login_guard::{client_ip, ClientIp};
+use crate::server::uploads::{cancel_upload, confirm_upload, create_order_item};
+
+pub type IngestResult<T> = Result<Json<T>, (StatusCode, String)>;
+
+pub fn to_ingest_error(e: ServerFnError) -> (StatusCode, String) {
+    log!("Ingest request failed: {:#?}", e);
+    match e {
+        ServerFnError::Args(message) => (StatusCode::BAD_REQUEST, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+// Same rule as the uploader, only operators add originals
+fn require_operator(auth_session: &AuthSession) -> Result<User, (StatusCode, String)> {
+    match auth_session.current_user.clone() {
+        Some(user) if user.role == Role::Operator => Ok(user),
+        Some(user) if user.role != Role::Anonymous => Err((
+            StatusCode::FORBIDDEN,
+            "Only Operators can ingest photos".to_string(),
+        )),
+        _ => Err((StatusCode::UNAUTHORIZED, "Not logged in".to_string())),
+    }
+}
+
+async fn get_uploading_order(
+    order_id: u64,
+    pool: &sqlx::MySqlPool,
+) -> Result<Order, (StatusCode, String)> {
+    let order = Order::get_by_id(order_id, pool)
+        .await
+        .map_err(to_ingest_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Order not found".to_string()))?;
+    if order.status != OrderStatus::Uploading {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Order {order_id} is not open for uploads"),
+        ));
+    }
+    Ok(order)
+}
+
+async fn get_uploading_order_item(
+    item_id: u64,
+    pool: &sqlx::MySqlPool,
+) -> Result<OrderItem, (StatusCode, String)> {
+    let order_item = OrderItem::get_by_id(item_id, pool)
+        .await
+        .map_err(|_| (StatusCode::NOT_FOUND, "Photo not found".to_string()))?;
+    get_uploading_order(order_item.order_id, pool).await?;
+    if order_item.mode != Mode::Original {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Only original photos can be changed".to_string(),
+        ));
+    }
+    Ok(order_item)
+}
+
+async fn to_ingest_order(order: Order, pool: &sqlx::MySqlPool) -> IngestResult<IngestOrder> {
+    let remaining = order
+        .remaining_order_items(Mode::Original, pool)
+        .await
+        .map_err(to_ingest_error)?;
+    Ok(Json(IngestOrder {
+        order_id: order.id,
+        status: order.status,
+        no_of_photos: order.no_of_photos,
+        remaining,
+    }))
+}
+
+pub async fn login_handler(
+    State(AppState { pool, .. }): State<AppState>,
+    auth_session: AuthSession,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(login): Json<IngestLogin>,
+) -> IngestResult<User> {
+    let ClientIp(ip) = client_ip(&headers, addr);
+    match authenticate(login.username, login.password, &ip, &pool)
+        .await
+        .map_err(to_ingest_error)?
+    {
+        // Processors log in here too, for the processor sync client
+        LoginResponse::LoggedIn(user)
+            if user.role == Role::Operator || user.role == Role::Processor =>
+        {
+            crate::auth::login(&auth_session, user.id, &pool)
+                .await
+                .map_err(to_ingest_error)?;
+            Ok(Json(user))
+        }
+        LoginResponse::LoggedIn(_) => Err((
+            StatusCode::FORBIDDEN,
+            "Only Operators and Processors can use the ingest API".to_string(),
+        )),
+        LoginResponse::LockedOut => Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            "Account is locked, try again later".to_string(),
+        )),
+        _ => Err((StatusCode::UNAUTHORIZED, "Invalid Credentials".to_string())),
+    }
+}
+
+// Binding to an order starts its upload if the operator has not done so yet
+pub async fn bind_order_handler(
+    State(AppState { pool, .. }): State<AppState>,
+    auth_session: AuthSession,
+    Path(reference): Path<String>,
+) -> IngestResult<IngestOrder> {
+    require_operator(&auth_session)?;
+    let order_id = parse_order_reference(&reference)
+        .ok_or((StatusCode::BAD_REQUEST, "Invalid order reference".to_string()))?;
+    Order::update_status(order_id, OrderStatus::Paid, OrderStatus::Uploading, &pool)
+        .await
+        .map_err(to_ingest_error)?;
+    let order = get_uploading_order(order_id, &pool).await?;
+    to_ingest_order(order, &pool).await
+}
+
+pub async fn add_item_handler(
+    State(AppState { pool, .. }): State<AppState>,
+    auth_session: AuthSession,
+    Path(order_id): Path<u64>,
+    Json(file): Json<IngestFile>,
+) -> IngestResult<OrderItem> {
+    require_operator(&auth_session)?;
+    let order = get_uploading_order(order_id, &pool).await?;
+    create_order_item(&order, Mode::Original, file.file_name, file.file_size, &pool)
+        .await
+        .map(Json)
+        .map_err(to_ingest_error)
+}
+
+pub async fn confirm_item_handler(
+    State(AppState { pool, .. }): State<AppState>,
+    auth_session: AuthSession,
+    Path(item_id): Path<u64>,
+) -> IngestResult<bool> {
+    require_operator(&auth_session)?;
+    let order_item = get_uploading_order_item(item_id, &pool).await?;
+    confirm_upload(order_item.id, &pool)
+        .await
+        .map(Json)
+        .map_err(to_ingest_error)
+}
+
+pub async fn cancel_item_handler(
+    State(AppState { pool, .. }): State<AppState>,
+    auth_session: AuthSession,
+    Path(item_id): Path<u64>,
+) -> IngestResult<bool> {
+    require_operator(&auth_session)?;
+    let order_item = get_uploading_order_item(item_id, &pool).await?;
+    cancel_upload(order_item.id, &pool)
+        .await
+        .map(Json)
+        .map_err(to_ingest_error)
+}
+
+pub async fn mark_uploaded_handler(
+    State(AppState { pool, .. }): State<AppState>,
+    auth_session: AuthSession,
+    Path(order_id): Path<u64>,
+) -> IngestResult<IngestOrder> {
+    require_operator(&auth_session)?;
+    let order = get_uploading_order(order_id, &pool).await?;
+    order
+        .set_uploaded_for_zero_remaining(Mode::Original, &pool)
+        .await
+        .map_err(to_ingest_error)?;
+    let order = Order::get_by_id(order_id, &pool)
+        .await
+        .map_err(to_ingest_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Order not found".to_string()))?;
+    to_ingest_order(order, &pool).await
+}
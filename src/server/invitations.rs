@@ -0,0 +1,86 @@
+use chrono::Local;
+use leptos::ServerFnError;
+use sqlx::MySqlPool;
+
+use crate::components::auth::signup::validate_password;
+use crate::models::{
+    invitation::Invitation,
+    user::{Role, User, UserStatus},
+};
+use crate::server::mailer::send_invitation;
+use crate::server::tokens::{generate_token, hash_token};
+
+const INVITATION_DAYS: i64 = 7;
+
+pub async fn invite_staff(
+    email: String,
+    role: Role,
+    invited_by: &User,
+    pool: &MySqlPool,
+) -> Result<bool, ServerFnError> {
+    let email = email.trim().to_lowercase();
+    if [Role::Anonymous, Role::Customer].contains(&role) {
+        return Err(ServerFnError::Args(
+            "Invitations are only for staff roles".to_string(),
+        ));
+    }
+    if User::get_by_username(email.clone(), pool).await.is_ok() {
+        return Err(ServerFnError::Args(
+            "This email is already registered, change the user's role instead".to_string(),
+        ));
+    }
+    // A new invitation replaces any earlier one for the same email
+    Invitation::revoke_pending_for_email(&email, pool).await?;
+    let token = generate_token();
+    Invitation::create(
+        email.clone(),
+        role.clone(),
+        hash_token(&token),
+        invited_by.id,
+        Local::now().naive_local() + chrono::Duration::days(INVITATION_DAYS),
+        pool,
+    )
+    .await?;
+    let app_url = dotenvy::var("APP_URL").expect("APP_URL should be present");
+    let link = format!("{}/invite/{token}", app_url.trim_end_matches('/'));
+    send_invitation(email, invited_by.name.clone(), role, link).await
+}
+
+pub async fn get_invitation(
+    token: &str,
+    pool: &MySqlPool,
+) -> Result<Option<Invitation>, ServerFnError> {
+    Invitation::get_pending_by_token(&hash_token(token), pool).await
+}
+
+pub async fn accept_invitation(
+    token: &str,
+    name: String,
+    password: String,
+    confirm_password: String,
+    pool: &MySqlPool,
+) -> Result<User, ServerFnError> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(ServerFnError::Args("Name is required".to_string()));
+    }
+    validate_password(password.clone(), confirm_password)
+        .map_err(|errors| ServerFnError::Args(errors.join(" ")))?;
+    let invalid = || ServerFnError::Args("This invitation is invalid or has expired".to_string());
+    let invitation = get_invitation(token, pool).await?.ok_or_else(invalid)?;
+    if !invitation.set_accepted(pool).await? {
+        return Err(invalid());
+    }
+    let password_hash = bcrypt::hash(password, 12).map_err(crate::to_server_fn_error)?;
+    let id = User::create(
+        Some(invitation.email),
+        None,
+        password_hash,
+        name,
+        invitation.role,
+        pool,
+    )
+    .await?;
+    User::set_status(id, UserStatus::Active, pool).await?;
+    User::get_by_id(id, pool).await
+}
@@ -0,0 +1,115 @@
+use chrono::{Duration, Local, NaiveDateTime};
+use leptos::ServerFnError;
+use rand::Rng;
+use sqlx::MySqlPool;
+
+use crate::models::user_token::{TokenPurpose, UserToken};
+use crate::server::tokens::hash_token;
+
+const LOGIN_CODE_MINUTES: i64 = 10;
+const MAX_LOGIN_CODE_ATTEMPTS: u32 = 5;
+
+// Codes are short, so the hash includes the user id to keep it unique per account
+fn hash_login_code(user_id: u64, code: &str) -> String {
+    hash_token(&format!("{user_id}:{}", code.trim()))
+}
+
+// Replaces any earlier code, so only the latest one emailed works
+pub async fn issue_login_code(user_id: u64, pool: &MySqlPool) -> Result<String, ServerFnError> {
+    let code = format!("{:06}", rand::thread_rng().gen_range(0..1_000_000));
+    UserToken::delete_for_user(user_id, TokenPurpose::LoginCode, pool).await?;
+    UserToken::create(
+        user_id,
+        TokenPurpose::LoginCode,
+        hash_login_code(user_id, &code),
+        Local::now().naive_local() + Duration::minutes(LOGIN_CODE_MINUTES),
+        pool,
+    )
+    .await?;
+    Ok(code)
+}
+
+pub async fn verify_login_code(
+    user_id: u64,
+    code: &str,
+    pool: &MySqlPool,
+) -> Result<bool, ServerFnError> {
+    let Some(token) = UserToken::get_active(user_id, TokenPurpose::LoginCode, pool).await? else {
+        return Ok(false);
+    };
+    if !UserToken::add_attempt(token.id, MAX_LOGIN_CODE_ATTEMPTS, pool).await? {
+        UserToken::delete_for_user(user_id, TokenPurpose::LoginCode, pool).await?;
+        return Ok(false);
+    }
+    if !matches_login_code(&token, user_id, code, Local::now().naive_local()) {
+        return Ok(false);
+    }
+    // Only the first of two concurrent guesses gets to mark the code used
+    UserToken::set_used(token.id, pool).await
+}
+
+// Split from verify_login_code so the decision is testable without a database
+fn matches_login_code(token: &UserToken, user_id: u64, code: &str, now: NaiveDateTime) -> bool {
+    token.used_at.is_none()
+        && token.expires_at > now
+        && token.token_hash == hash_login_code(user_id, code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::default() + Duration::days(1)
+    }
+
+    fn token(user_id: u64, code: &str) -> UserToken {
+        UserToken {
+            id: 1,
+            user_id,
+            purpose: TokenPurpose::LoginCode,
+            token_hash: hash_login_code(user_id, code),
+            expires_at: now() + Duration::minutes(LOGIN_CODE_MINUTES),
+            attempts: 0,
+            new_email: None,
+            used_at: None,
+            created_at: now(),
+        }
+    }
+
+    #[test]
+    fn the_emailed_code_matches() {
+        let token = token(7, "042137");
+        assert!(matches_login_code(&token, 7, "042137", now()));
+        assert!(matches_login_code(&token, 7, " 042137\n", now()));
+    }
+
+    #[test]
+    fn wrong_codes_and_other_accounts_do_not_match() {
+        let token = token(7, "042137");
+        assert!(!matches_login_code(&token, 7, "042138", now()));
+        assert!(!matches_login_code(&token, 8, "042137", now()));
+    }
+
+    #[test]
+    fn expired_codes_do_not_match() {
+        let token = token(7, "042137");
+        let expired = now() + Duration::minutes(LOGIN_CODE_MINUTES);
+        assert!(matches_login_code(
+            &token,
+            7,
+            "042137",
+            expired - Duration::seconds(1)
+        ));
+        assert!(!matches_login_code(&token, 7, "042137", expired));
+    }
+
+    #[test]
+    fn used_codes_do_not_match_again() {
+        let token = UserToken {
+            used_at: Some(now()),
+            ..token(7, "042137")
+        };
+        assert!(!matches_login_code(&token, 7, "042137", now()));
+    }
+}
@@ -0,0 +1,227 @@
+use std::{net::SocketAddr, time::Duration};
+
+use chrono::{Local, NaiveDateTime};
+use http::HeaderMap;
+use leptos::{log, use_context, Scope, ServerFnError};
+use sqlx::MySqlPool;
+
+use crate::models::login_attempt::{AccountLockout, AttemptKind, LoginAttempt};
+
+const FAILURE_WINDOW_MINUTES: i64 = 15;
+const LOCKOUT_MINUTES: i64 = 15;
+// Failures allowed before responses start slowing down
+const FREE_ACCOUNT_FAILURES: i64 = 2;
+const MAX_ACCOUNT_FAILURES: i64 = 5;
+const MAX_IP_FAILURES: i64 = 20;
+const ATTEMPT_HISTORY_DAYS: i64 = 30;
+
+#[derive(Debug, Clone)]
+pub struct ClientIp(pub String);
+
+// X-Forwarded-For is only trusted when the app runs behind a proxy that sets it
+pub fn client_ip(headers: &HeaderMap, addr: SocketAddr) -> ClientIp {
+    let trust_proxy = dotenvy::var("TRUST_PROXY_HEADERS")
+        .map(|value| value == "true")
+        .unwrap_or(false);
+    let forwarded = headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    match forwarded {
+        Some(ip) if trust_proxy => ClientIp(ip),
+        _ => ClientIp(addr.ip().to_string()),
+    }
+}
+
+pub fn current_ip(cx: Scope) -> Result<String, ServerFnError> {
+    use_context::<ClientIp>(cx)
+        .map(|ClientIp(ip)| ip)
+        .ok_or(ServerFnError::ServerError("client address missing".into()))
+}
+
+pub async fn is_locked(
+    user_id: Option<u64>,
+    ip: &str,
+    pool: &MySqlPool,
+) -> Result<bool, ServerFnError> {
+    if AccountLockout::is_ip_locked(ip, pool).await? {
+        return Ok(true);
+    }
+    match user_id {
+        Some(user_id) => AccountLockout::is_user_locked(user_id, pool).await,
+        None => Ok(false),
+    }
+}
+
+pub async fn record_success(
+    user_id: u64,
+    ip: &str,
+    kind: AttemptKind,
+    pool: &MySqlPool,
+) -> Result<(), ServerFnError> {
+    LoginAttempt::create(Some(user_id), ip, kind, true, pool).await?;
+    Ok(())
+}
+
+// Split from record_failure so the decisions are testable without a database
+
+// Failures before the latest success, lockout or unlock no longer count
+fn failure_window_start(
+    now: NaiveDateTime,
+    resets: impl IntoIterator<Item = Option<NaiveDateTime>>,
+) -> NaiveDateTime {
+    let window_start = now - chrono::Duration::minutes(FAILURE_WINDOW_MINUTES);
+    resets
+        .into_iter()
+        .flatten()
+        .fold(window_start, NaiveDateTime::max)
+}
+
+fn locks_account(failures: i64) -> bool {
+    failures >= MAX_ACCOUNT_FAILURES
+}
+
+fn locks_ip(failures: i64) -> bool {
+    failures >= MAX_IP_FAILURES
+}
+
+// 1, 2, 4, 8 and at most 16 seconds once the free failures are used up
+fn failure_delay(account_failures: i64, ip_failures: i64) -> Option<Duration> {
+    let failures = account_failures.max(ip_failures / 4);
+    if failures <= FREE_ACCOUNT_FAILURES {
+        return None;
+    }
+    let exponent = (failures - FREE_ACCOUNT_FAILURES - 1).min(4) as u32;
+    Some(Duration::from_secs(2u64.pow(exponent)))
+}
+
+// Records the failure, locks the account or address once it has failed too often
+// and holds the response back longer with every consecutive failure
+pub async fn record_failure(
+    user_id: Option<u64>,
+    ip: &str,
+    kind: AttemptKind,
+    pool: &MySqlPool,
+) -> Result<(), ServerFnError> {
+    LoginAttempt::create(user_id, ip, kind, false, pool).await?;
+    let now = Local::now().naive_local();
+    let locked_until = now + chrono::Duration::minutes(LOCKOUT_MINUTES);
+
+    let ip_since = failure_window_start(now, [AccountLockout::last_ip_lockout_at(ip, pool).await?]);
+    let ip_failures = LoginAttempt::count_ip_failures(ip, ip_since, pool).await?;
+    if locks_ip(ip_failures) {
+        log!("Locking out {ip} after {ip_failures} failed login attempts");
+        AccountLockout::create(None, Some(ip), ip_failures as u32, locked_until, pool).await?;
+    }
+
+    let mut account_failures = 0;
+    if let Some(user_id) = user_id {
+        let since = failure_window_start(
+            now,
+            [
+                LoginAttempt::last_success_at(user_id, pool).await?,
+                AccountLockout::last_user_lockout_at(user_id, pool).await?,
+            ],
+        );
+        account_failures = LoginAttempt::count_user_failures(user_id, since, pool).await?;
+        if locks_account(account_failures) {
+            log!("Locking out user {user_id} after {account_failures} failed login attempts");
+            AccountLockout::create(
+                Some(user_id),
+                Some(ip),
+                account_failures as u32,
+                locked_until,
+                pool,
+            )
+            .await?;
+        }
+    }
+
+    if let Some(delay) = failure_delay(account_failures, ip_failures) {
+        tokio::time::sleep(delay).await;
+    }
+    Ok(())
+}
+
+pub async fn unlock(id: u64, unlocked_by: u64, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+    let unlocked = AccountLockout::unlock(id, unlocked_by, pool).await?;
+    if unlocked {
+        log!("Lockout {id} removed by user {unlocked_by}");
+    }
+    Ok(unlocked)
+}
+
+pub async fn prune_attempts(pool: &MySqlPool) -> Result<u64, ServerFnError> {
+    let before = Local::now().naive_local() - chrono::Duration::days(ATTEMPT_HISTORY_DAYS);
+    LoginAttempt::delete_before(before, pool).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::default() + chrono::Duration::days(1)
+    }
+
+    #[test]
+    fn accounts_lock_on_the_fifth_failure() {
+        assert!(!locks_account(4));
+        assert!(locks_account(5));
+        assert!(locks_account(6));
+    }
+
+    #[test]
+    fn addresses_lock_on_the_twentieth_failure() {
+        assert!(!locks_ip(19));
+        assert!(locks_ip(20));
+    }
+
+    #[test]
+    fn failures_only_count_within_the_window() {
+        let window_start = now() - chrono::Duration::minutes(FAILURE_WINDOW_MINUTES);
+        assert_eq!(failure_window_start(now(), [None, None]), window_start);
+        let old_lockout = now() - chrono::Duration::hours(2);
+        assert_eq!(
+            failure_window_start(now(), [Some(old_lockout)]),
+            window_start
+        );
+    }
+
+    #[test]
+    fn unlocking_restarts_the_failure_count() {
+        let locked_at = now() - chrono::Duration::minutes(10);
+        let unlocked_at = now() - chrono::Duration::minutes(2);
+        // last_user_lockout_at prefers the unlock time over the lockout time
+        assert_eq!(
+            failure_window_start(now(), [Some(unlocked_at)]),
+            unlocked_at
+        );
+        assert_eq!(failure_window_start(now(), [Some(locked_at)]), locked_at);
+    }
+
+    #[test]
+    fn a_successful_login_restarts_the_failure_count() {
+        let succeeded_at = now() - chrono::Duration::minutes(1);
+        let locked_at = now() - chrono::Duration::minutes(10);
+        assert_eq!(
+            failure_window_start(now(), [Some(succeeded_at), Some(locked_at)]),
+            succeeded_at
+        );
+    }
+
+    #[test]
+    fn responses_slow_down_after_the_free_failures() {
+        assert_eq!(failure_delay(0, 0), None);
+        assert_eq!(failure_delay(2, 0), None);
+        assert_eq!(failure_delay(3, 0), Some(Duration::from_secs(1)));
+        assert_eq!(failure_delay(4, 0), Some(Duration::from_secs(2)));
+        assert_eq!(failure_delay(7, 0), Some(Duration::from_secs(16)));
+        assert_eq!(failure_delay(50, 0), Some(Duration::from_secs(16)));
+        // Address failures count a quarter each
+        assert_eq!(failure_delay(0, 11), None);
+        assert_eq!(failure_delay(0, 12), Some(Duration::from_secs(1)));
+    }
+}
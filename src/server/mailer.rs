@@ -4,6 +4,8 @@ use lettre::message::MessageBuilder;
 use lettre::transport::smtp::authentication::Credentials;
 use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
 
+use crate::models::order_item::Mode;
+use crate::models::user::Role;
 use crate::to_server_fn_error;
 
 fn get_mailer() -> Result<AsyncSmtpTransport<Tokio1Executor>, ServerFnError> {
@@ -35,7 +37,7 @@ pub async fn send_otp(to: String, otp: String) -> Result<bool, ServerFnError> {
         .to(to.parse().unwrap())
         .subject("Login Code for Portrait Booth")
         .header(ContentType::TEXT_PLAIN)
-        .body(format!("Your login code is: {}.\nThis code will expire soon after which you will need to request a new login code.",otp))
+        .body(format!("Your login code is: {}.\nThis code can only be used once and expires in 10 minutes, after which you will need to request a new login code.",otp))
         .map_err(to_server_fn_error)? ;
 
     mailer
@@ -70,12 +72,10 @@ pub async fn send_processed(
         <p>Dear {name},</p>
         
         <p>Your portraits are ready.  To download your portraits, please click on the link(s) below.</p>
-        <p><strong>The below links will expire in 5 days.</strong></p>
 
         {links}
 
-        <p>Please download these portraits before the links expire. 
-        If you have any questions about your portraits, please send an email to {reply_to}.
+        <p>If you have any questions about your portraits, please send an email to {reply_to}.
         We will try and get back with you within a few days.</p>
 
         <p>Please DO NOT respond to this email as this mailbox is not monitored.</p>
@@ -92,3 +92,242 @@ pub async fn send_processed(
         .map(|_| true)
         .map_err(to_server_fn_error)
 }
+
+pub async fn send_retention_warning(
+    to: String,
+    name: String,
+    mode: Mode,
+    days_left: u32,
+) -> Result<bool, ServerFnError> {
+    let photos = match mode {
+        Mode::Original => "original photos",
+        Mode::Processed => "portraits",
+    };
+    let reply_to =
+        dotenvy::var("SMTP_REPLY_TO_EMAIL").expect("SMTP_REPLY_TO_EMAIL should be present");
+    let from_name = dotenvy::var("EMAIL_FROM_NAME").expect("EMAIL_FROM_NAME should be present");
+    let app_url = dotenvy::var("APP_URL").expect("APP_URL should be present");
+    let mailer = get_mailer()?;
+
+    let email = email_builder()
+        .to(to.parse().unwrap())
+        .subject(format!("Your {photos} will be removed soon"))
+        .header(ContentType::TEXT_HTML)
+        .body(format!(r#"
+        <p>Dear {name},</p>
+
+        <p>Your {photos} will be removed from our servers in {days_left} day(s).
+        Please <a href="{app_url}">sign in</a> and download any {photos} you would like to keep before then.</p>
+
+        <p>If you have any questions about your portraits, please send an email to {reply_to}.</p>
+
+        <p>Please DO NOT respond to this email as this mailbox is not monitored.</p>
+
+        <p>Regards,</p>
+
+        <p>{from_name}</p>
+        "#))
+        .map_err(to_server_fn_error)? ;
+
+    mailer
+        .send(email)
+        .await
+        .map(|_| true)
+        .map_err(to_server_fn_error)
+}
+
+pub async fn send_verification(
+    to: String,
+    name: String,
+    link: String,
+) -> Result<bool, ServerFnError> {
+    let from_name = dotenvy::var("EMAIL_FROM_NAME").expect("EMAIL_FROM_NAME should be present");
+    let mailer = get_mailer()?;
+
+    let email = email_builder()
+        .to(to.parse().map_err(to_server_fn_error)?)
+        .subject("Verify your email for Portrait Booth")
+        .header(ContentType::TEXT_HTML)
+        .body(format!(r#"
+        <p>Dear {name},</p>
+
+        <p>Please <a href="{link}">verify your email address</a> to activate your account.
+        This link can only be used once and expires in 24 hours.</p>
+
+        <p>If you did not sign up, you can ignore this email.</p>
+
+        <p>Regards,</p>
+
+        <p>{from_name}</p>
+        "#))
+        .map_err(to_server_fn_error)? ;
+
+    mailer
+        .send(email)
+        .await
+        .map(|_| true)
+        .map_err(to_server_fn_error)
+}
+
+pub async fn send_password_reset(
+    to: String,
+    name: String,
+    link: String,
+) -> Result<bool, ServerFnError> {
+    let from_name = dotenvy::var("EMAIL_FROM_NAME").expect("EMAIL_FROM_NAME should be present");
+    let mailer = get_mailer()?;
+
+    let email = email_builder()
+        .to(to.parse().map_err(to_server_fn_error)?)
+        .subject("Reset your Portrait Booth password")
+        .header(ContentType::TEXT_HTML)
+        .body(format!(r#"
+        <p>Dear {name},</p>
+
+        <p>We received a request to reset your password. <a href="{link}">Choose a new password</a>.
+        This link can only be used once and expires in 1 hour.</p>
+
+        <p>If you did not ask for this, you can ignore this email and your password will stay the same.</p>
+
+        <p>Regards,</p>
+
+        <p>{from_name}</p>
+        "#))
+        .map_err(to_server_fn_error)? ;
+
+    mailer
+        .send(email)
+        .await
+        .map(|_| true)
+        .map_err(to_server_fn_error)
+}
+
+pub async fn send_password_changed(to: String, name: String) -> Result<bool, ServerFnError> {
+    let reply_to =
+        dotenvy::var("SMTP_REPLY_TO_EMAIL").expect("SMTP_REPLY_TO_EMAIL should be present");
+    let from_name = dotenvy::var("EMAIL_FROM_NAME").expect("EMAIL_FROM_NAME should be present");
+    let mailer = get_mailer()?;
+
+    let email = email_builder()
+        .to(to.parse().map_err(to_server_fn_error)?)
+        .subject("Your Portrait Booth password was changed")
+        .header(ContentType::TEXT_HTML)
+        .body(format!(r#"
+        <p>Dear {name},</p>
+
+        <p>The password for your account was just changed and you have been signed out everywhere else.</p>
+
+        <p>If you did not make this change, please contact {reply_to} straight away.</p>
+
+        <p>Regards,</p>
+
+        <p>{from_name}</p>
+        "#))
+        .map_err(to_server_fn_error)? ;
+
+    mailer
+        .send(email)
+        .await
+        .map(|_| true)
+        .map_err(to_server_fn_error)
+}
+
+pub async fn send_email_change(
+    to: String,
+    name: String,
+    link: String,
+) -> Result<bool, ServerFnError> {
+    let from_name = dotenvy::var("EMAIL_FROM_NAME").expect("EMAIL_FROM_NAME should be present");
+    let mailer = get_mailer()?;
+
+    let email = email_builder()
+        .to(to.parse().map_err(to_server_fn_error)?)
+        .subject("Confirm your new email for Portrait Booth")
+        .header(ContentType::TEXT_HTML)
+        .body(format!(r#"
+        <p>Dear {name},</p>
+
+        <p>Please <a href="{link}">confirm this email address</a> to use it for your account.
+        This link can only be used once and expires in 24 hours.</p>
+
+        <p>If you did not ask for this, you can ignore this email.</p>
+
+        <p>Regards,</p>
+
+        <p>{from_name}</p>
+        "#))
+        .map_err(to_server_fn_error)? ;
+
+    mailer
+        .send(email)
+        .await
+        .map(|_| true)
+        .map_err(to_server_fn_error)
+}
+
+pub async fn send_email_changed(
+    to: String,
+    name: String,
+    new_email: String,
+) -> Result<bool, ServerFnError> {
+    let reply_to =
+        dotenvy::var("SMTP_REPLY_TO_EMAIL").expect("SMTP_REPLY_TO_EMAIL should be present");
+    let from_name = dotenvy::var("EMAIL_FROM_NAME").expect("EMAIL_FROM_NAME should be present");
+    let mailer = get_mailer()?;
+
+    let email = email_builder()
+        .to(to.parse().map_err(to_server_fn_error)?)
+        .subject("Your Portrait Booth email was changed")
+        .header(ContentType::TEXT_HTML)
+        .body(format!(r#"
+        <p>Dear {name},</p>
+
+        <p>Your account now uses {new_email}, emails will no longer be sent to this address.</p>
+
+        <p>If you did not make this change, please contact {reply_to} straight away.</p>
+
+        <p>Regards,</p>
+
+        <p>{from_name}</p>
+        "#))
+        .map_err(to_server_fn_error)? ;
+
+    mailer
+        .send(email)
+        .await
+        .map(|_| true)
+        .map_err(to_server_fn_error)
+}
+
+pub async fn send_invitation(
+    to: String,
+    invited_by: String,
+    role: Role,
+    link: String,
+) -> Result<bool, ServerFnError> {
+    let from_name = dotenvy::var("EMAIL_FROM_NAME").expect("EMAIL_FROM_NAME should be present");
+    let mailer = get_mailer()?;
+
+    let email = email_builder()
+        .to(to.parse().map_err(to_server_fn_error)?)
+        .subject("You are invited to join Portrait Booth")
+        .header(ContentType::TEXT_HTML)
+        .body(format!(r#"
+        <p>Hello,</p>
+
+        <p>{invited_by} has invited you to join Portrait Booth as a {role:?}.
+        <a href="{link}">Accept the invitation</a> to set your name and password.
+        This link can only be used once and expires in 7 days.</p>
+
+        <p>Regards,</p>
+
+        <p>{from_name}</p>
+        "#))
+        .map_err(to_server_fn_error)? ;
+
+    mailer
+        .send(email)
+        .await
+        .map(|_| true)
+        .map_err(to_server_fn_error)
+}
@@ -0,0 +1,57 @@
+use leptos::{log, ServerFnError};
+use sqlx::MySqlPool;
+
+use crate::components::auth::signup::validate_password;
+use crate::models::{
+    user::{User, UserStatus},
+    user_session::UserSession,
+    user_token::TokenPurpose,
+};
+use crate::server::mailer::{send_password_changed, send_password_reset};
+use crate::server::tokens::{consume_token, issue_token};
+
+const RESET_TOKEN_MINUTES: i64 = 60;
+
+// Silently does nothing for unknown or disabled accounts
+pub async fn request_password_reset(email: String, pool: &MySqlPool) -> Result<(), ServerFnError> {
+    let Ok(user) = User::get_by_username(email, pool).await else {
+        return Ok(());
+    };
+    if user.status == UserStatus::Disabled {
+        return Ok(());
+    }
+    let token = issue_token(
+        user.id,
+        TokenPurpose::PasswordReset,
+        chrono::Duration::minutes(RESET_TOKEN_MINUTES),
+        pool,
+    )
+    .await?;
+    let app_url = dotenvy::var("APP_URL").expect("APP_URL should be present");
+    let link = format!("{}/reset-password/{token}", app_url.trim_end_matches('/'));
+    send_password_reset(user.email, user.name, link).await?;
+    Ok(())
+}
+
+pub async fn reset_password(
+    token: &str,
+    password: String,
+    confirm_password: String,
+    pool: &MySqlPool,
+) -> Result<(), ServerFnError> {
+    validate_password(password.clone(), confirm_password)
+        .map_err(|errors| ServerFnError::Args(errors.join(" ")))?;
+    let Some(user_id) = consume_token(TokenPurpose::PasswordReset, token, pool).await? else {
+        return Err(ServerFnError::Args(
+            "This link is invalid or has expired".to_string(),
+        ));
+    };
+    let password_hash = bcrypt::hash(password, 12).map_err(crate::to_server_fn_error)?;
+    User::set_password(user_id, password_hash, pool).await?;
+    UserSession::revoke_all_for_user(user_id, pool).await?;
+    let user = User::get_by_id(user_id, pool).await?;
+    if let Err(e) = send_password_changed(user.email, user.name).await {
+        log!("Unable to send password changed email: {:#?}", e);
+    }
+    Ok(())
+}
@@ -0,0 +1,219 @@
+use leptos::{log, Scope, ServerFnError};
+use sqlx::MySqlPool;
+
+use crate::models::user::{Role, User};
+
+pub const NOT_AUTHORIZED: &str = "Not authorized";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    // Anyone, including visitors who are not logged in
+    Public,
+    // Any logged in account
+    SignedIn,
+    Roles(&'static [Role]),
+}
+
+const MANAGER: Access = Access::Roles(&[Role::Manager]);
+const PROCESSOR: Access = Access::Roles(&[Role::Processor]);
+const COUNTER: Access = Access::Roles(&[Role::Cashier, Role::Operator, Role::Manager]);
+const UPLOADER: Access = Access::Roles(&[Role::Operator, Role::Processor]);
+
+// Server functions missing from this list are denied, internal ones like the
+// mailer and payment link functions are left out on purpose
+const POLICIES: &[(&str, Access)] = &[
+    // Auth
+    ("GetLoggedInUser", Access::Public),
+    ("GetAppName", Access::Public),
+    ("GetUnitPrice", Access::Public),
+    ("HomePageRequest", Access::Public),
+    ("LoginRequest", Access::Public),
+    ("LoginOtpRequest", Access::Public),
+    ("LoginOtpVerifyRequest", Access::Public),
+    ("LogoutRequest", Access::Public),
+    ("SignupRequest", Access::Public),
+    ("VerifyEmailRequest", Access::Public),
+    ("ResendVerificationRequest", Access::Public),
+    ("ForgotPasswordRequest", Access::Public),
+    ("ResetPasswordRequest", Access::Public),
+    ("GetInvitationRequest", Access::Public),
+    ("AcceptInvitationRequest", Access::Public),
+    ("ConfirmEmailChangeRequest", Access::Public),
+    // Profile
+    ("GetProfile", Access::SignedIn),
+    ("UpdateProfileRequest", Access::SignedIn),
+    ("ChangeEmailRequest", Access::SignedIn),
+    ("SetPasswordRequest", Access::SignedIn),
+    ("DeleteAccountRequest", Access::SignedIn),
+    // Customer orders
+    ("GetAllowOrderCreationSetting", Access::SignedIn),
+    ("GetOrdersRequest", Access::SignedIn),
+    ("CreateOrderRequest", Access::SignedIn),
+    ("DeleteOrderRequest", Access::SignedIn),
+    ("StartCashPaymentRequest", Access::SignedIn),
+    ("StartStripePaymentRequest", Access::SignedIn),
+    ("StoreStripeConfirmation", Access::SignedIn),
+    ("GetOrderTicket", Access::SignedIn),
+    ("GetOrderRequest", Access::SignedIn),
+    ("GetOrderItems", Access::SignedIn),
+    // Counter
+    ("OrderSearchRequest", COUNTER),
+    (
+        "MarkPaidRequest",
+        Access::Roles(&[Role::Cashier, Role::Manager]),
+    ),
+    ("OrderStatusChangeRequest", Access::Roles(&[Role::Operator])),
+    // Uploads
+    ("GetFiles", UPLOADER),
+    ("GetUserOrder", UPLOADER),
+    ("GetMaxOriginalDimension", UPLOADER),
+    ("AddOrderItemRequest", UPLOADER),
+    ("ConfirmOrderItemUpload", UPLOADER),
+    ("StartMultipartUpload", UPLOADER),
+    ("GetUploadPartUrls", UPLOADER),
+    ("CompleteMultipartUpload", UPLOADER),
+    ("AbortMultipartUpload", UPLOADER),
+    ("CancelOrderItemUpload", UPLOADER),
+    ("GetRemainingUploads", UPLOADER),
+    ("UpdateOrderUploadStatus", UPLOADER),
+    ("DeleteOrderItemRequest", UPLOADER),
+    // Processing
+    ("FetchOrderRequest", PROCESSOR),
+    ("SkipOrderRequest", PROCESSOR),
+    ("MarkReadyForDeliveryRequest", PROCESSOR),
+    // Management
+    ("MarkStripePaidRequest", MANAGER),
+    ("ClearStripePendingStatus", MANAGER),
+    ("StatusChangeRequest", MANAGER),
+    ("GetOrderCountByStatusReport", MANAGER),
+    ("GetCollectionReport", MANAGER),
+    ("GetProcessorReport", MANAGER),
+    ("GetStorageReport", MANAGER),
+    ("ReconcileStorage", MANAGER),
+    ("RepairStorage", MANAGER),
+    ("ToggleAllowOrderCreation", MANAGER),
+    ("GetRetentionSettings", MANAGER),
+    ("SetRetentionSettings", MANAGER),
+    ("SearchUsers", MANAGER),
+    ("GetUserDetails", MANAGER),
+    ("ChangeUserRole", MANAGER),
+    ("SetUserStatus", MANAGER),
+    ("UpdateUserProfile", MANAGER),
+    ("ForceLogout", MANAGER),
+    ("InviteStaff", MANAGER),
+    ("GetPendingInvitations", MANAGER),
+    ("RevokeInvitation", MANAGER),
+    ("GetLockouts", MANAGER),
+    ("UnlockAccount", MANAGER),
+];
+
+// Server fn urls are the registered name, optionally followed by a numeric hash
+fn matches_name(path: &str, name: &str) -> bool {
+    path.trim_start_matches('/')
+        .strip_prefix(name)
+        .map_or(false, |rest| rest.chars().all(|c| c.is_ascii_digit()))
+}
+
+pub fn policy_for(path: &str) -> Option<(&'static str, Access)> {
+    POLICIES
+        .iter()
+        .find(|(name, _)| matches_name(path, name))
+        .copied()
+}
+
+pub fn is_allowed(access: Access, user: Option<&User>) -> bool {
+    let role = user.map_or(Role::Anonymous, |user| user.role.clone());
+    match access {
+        Access::Public => true,
+        Access::SignedIn => role != Role::Anonymous,
+        Access::Roles(roles) => roles.contains(&role),
+    }
+}
+
+pub fn check(path: &str, user: Option<&User>) -> Result<(), ServerFnError> {
+    let allowed = policy_for(path).map_or(false, |(_, access)| is_allowed(access, user));
+    if allowed {
+        return Ok(());
+    }
+    match user {
+        Some(user) => log!("Denied {path} to user {} ({:?})", user.id, user.role),
+        None => log!("Denied {path} to anonymous user"),
+    }
+    Err(ServerFnError::ServerError(NOT_AUTHORIZED.to_string()))
+}
+
+// For server fns that also run during server side rendering, where the
+// request does not go through the api handler
+pub fn authorize(cx: Scope, name: &str) -> Result<(MySqlPool, User), ServerFnError> {
+    let (pool, auth) = crate::server::pool_and_auth(cx)?;
+    check(name, auth.current_user.as_ref())?;
+    let user = auth
+        .current_user
+        .ok_or(ServerFnError::ServerError(NOT_AUTHORIZED.to_string()))?;
+    Ok((pool, user))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(role: Role) -> User {
+        User {
+            id: 1,
+            role,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unlisted_functions_are_denied() {
+        assert_eq!(policy_for("/SendOtpEmail"), None);
+        assert_eq!(policy_for("/GetPaymentLink123"), None);
+        assert!(check("/SendOtpEmail", Some(&user(Role::Manager))).is_err());
+        assert!(check("/SendProcessedEmail", None).is_err());
+    }
+
+    #[test]
+    fn names_match_with_a_hash_suffix() {
+        assert_eq!(
+            policy_for("/GetFiles1234567890").map(|(name, _)| name),
+            Some("GetFiles")
+        );
+        assert_eq!(
+            policy_for("GetFiles").map(|(name, _)| name),
+            Some("GetFiles")
+        );
+        assert_eq!(policy_for("/GetFilesAndMore"), None);
+        assert_eq!(policy_for("/GetFiles12x"), None);
+    }
+
+    #[test]
+    fn names_sharing_a_prefix_match_their_own_entry() {
+        assert_eq!(
+            policy_for("/LoginOtpRequest42").map(|(name, _)| name),
+            Some("LoginOtpRequest")
+        );
+        assert_eq!(
+            policy_for("/LoginOtpVerifyRequest42").map(|(name, _)| name),
+            Some("LoginOtpVerifyRequest")
+        );
+        assert_eq!(
+            policy_for("/GetOrderRequest").map(|(name, _)| name),
+            Some("GetOrderRequest")
+        );
+        assert_eq!(
+            policy_for("/GetOrdersRequest").map(|(name, _)| name),
+            Some("GetOrdersRequest")
+        );
+    }
+
+    #[test]
+    fn roles_are_checked() {
+        assert!(check("/GetFiles", Some(&user(Role::Operator))).is_ok());
+        assert!(check("/GetFiles", Some(&user(Role::Manager))).is_err());
+        assert!(check("/GetProfile", Some(&user(Role::Customer))).is_ok());
+        assert!(check("/GetProfile", Some(&user(Role::Anonymous))).is_err());
+        assert!(check("/GetProfile", None).is_err());
+        assert!(check("/LoginRequest", None).is_ok());
+    }
+}
@@ -0,0 +1,175 @@
+use leptos::{log, ServerFnError};
+use sqlx::MySqlPool;
+
+use crate::components::auth::signup::validate_password;
+use crate::models::{
+    order::Order,
+    profile::Profile,
+    user::{Role, User},
+    user_session::UserSession,
+    user_token::{TokenPurpose, UserToken},
+};
+use crate::server::mailer::{send_email_change, send_email_changed, send_password_changed};
+use crate::server::tokens::{hash_token, issue_token};
+
+const EMAIL_CHANGE_TOKEN_HOURS: i64 = 24;
+
+pub async fn get_profile(user: &User, pool: &MySqlPool) -> Result<Profile, ServerFnError> {
+    let pending_email = UserToken::get_active(user.id, TokenPurpose::EmailChange, pool)
+        .await?
+        .and_then(|token| token.new_email);
+    Ok(Profile {
+        name: user.name.clone(),
+        email: user.email.clone(),
+        phone: user.phone.clone(),
+        has_password: user.password_hash.is_some(),
+        pending_email,
+        can_delete: user.role == Role::Customer,
+    })
+}
+
+pub async fn update_profile(
+    user: &User,
+    name: String,
+    phone: Option<String>,
+    pool: &MySqlPool,
+) -> Result<bool, ServerFnError> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(ServerFnError::Args("Name is required".to_string()));
+    }
+    let phone = phone
+        .map(|phone| phone.trim().to_string())
+        .filter(|phone| !phone.is_empty());
+    User::update_profile(user.id, name, user.email.clone(), phone, pool).await
+}
+
+// The account keeps its current email until the link sent to the new one is opened
+pub async fn request_email_change(
+    user: &User,
+    new_email: String,
+    pool: &MySqlPool,
+) -> Result<bool, ServerFnError> {
+    let new_email = new_email.trim().to_lowercase();
+    if !new_email.contains('@') {
+        return Err(ServerFnError::Args("Enter a valid email".to_string()));
+    }
+    if new_email == user.email.to_lowercase() {
+        return Err(ServerFnError::Args(
+            "This is already your email".to_string(),
+        ));
+    }
+    if User::get_by_username(new_email.clone(), pool).await.is_ok() {
+        return Err(ServerFnError::Args(
+            "This email is already registered".to_string(),
+        ));
+    }
+    let token = issue_token(
+        user.id,
+        TokenPurpose::EmailChange,
+        chrono::Duration::hours(EMAIL_CHANGE_TOKEN_HOURS),
+        pool,
+    )
+    .await?;
+    UserToken::set_new_email(&hash_token(&token), &new_email, pool).await?;
+    let app_url = dotenvy::var("APP_URL").expect("APP_URL should be present");
+    let link = format!("{}/change-email/{token}", app_url.trim_end_matches('/'));
+    send_email_change(new_email, user.name.clone(), link).await
+}
+
+// Returns the updated user, or None when the link is unknown, used or expired
+pub async fn confirm_email_change(
+    token: &str,
+    pool: &MySqlPool,
+) -> Result<Option<User>, ServerFnError> {
+    let Some(user_token) =
+        UserToken::get_valid(TokenPurpose::EmailChange, &hash_token(token), pool).await?
+    else {
+        return Ok(None);
+    };
+    let Some(new_email) = user_token.new_email.clone() else {
+        return Ok(None);
+    };
+    if User::get_by_username(new_email.clone(), pool).await.is_ok() {
+        return Err(ServerFnError::Args(
+            "This email is already registered".to_string(),
+        ));
+    }
+    if !UserToken::set_used(user_token.id, pool).await? {
+        return Ok(None);
+    }
+    let user = User::get_by_id(user_token.user_id, pool).await?;
+    User::update_profile(
+        user.id,
+        user.name.clone(),
+        new_email.clone(),
+        user.phone.clone(),
+        pool,
+    )
+    .await?;
+    if let Err(e) = send_email_changed(user.email, user.name, new_email).await {
+        log!("Unable to send email changed notice: {:#?}", e);
+    }
+    User::get_by_id(user_token.user_id, pool).await.map(Some)
+}
+
+// Accounts created with login codes have no password yet, everyone else has to
+// confirm the current one. Every session is ended, the caller signs back in.
+pub async fn set_password(
+    user: &User,
+    current_password: String,
+    password: String,
+    confirm_password: String,
+    pool: &MySqlPool,
+) -> Result<(), ServerFnError> {
+    if let Some(password_hash) = &user.password_hash {
+        if !bcrypt::verify(current_password, password_hash).unwrap_or(false) {
+            return Err(ServerFnError::Args(
+                "Current password is incorrect".to_string(),
+            ));
+        }
+    }
+    validate_password(password.clone(), confirm_password)
+        .map_err(|errors| ServerFnError::Args(errors.join(" ")))?;
+    let password_hash = bcrypt::hash(password, 12).map_err(crate::to_server_fn_error)?;
+    User::set_password(user.id, password_hash, pool).await?;
+    UserSession::revoke_all_for_user(user.id, pool).await?;
+    if let Err(e) = send_password_changed(user.email.clone(), user.name.clone()).await {
+        log!("Unable to send password changed email: {:#?}", e);
+    }
+    Ok(())
+}
+
+// Orders and their payments stay for the books, the account is anonymized and
+// undelivered orders start their retention period today
+pub async fn delete_account(
+    user: &User,
+    confirm_email: String,
+    pool: &MySqlPool,
+) -> Result<(), ServerFnError> {
+    if user.role != Role::Customer {
+        return Err(ServerFnError::Args(
+            "Staff accounts are removed by a manager".to_string(),
+        ));
+    }
+    if confirm_email.trim().to_lowercase() != user.email.to_lowercase() {
+        return Err(ServerFnError::Args(
+            "Enter your email to confirm".to_string(),
+        ));
+    }
+    if Order::count_in_progress_for_customer(user.id, pool).await? > 0 {
+        return Err(ServerFnError::Args(
+            "Your account can be deleted once your orders are delivered".to_string(),
+        ));
+    }
+    let orders = Order::schedule_purge_for_customer(user.id, pool).await?;
+    Order::anonymize_order_refs_for_customer(user.id, pool).await?;
+    User::anonymize(user.id, pool).await?;
+    UserSession::revoke_all_for_user(user.id, pool).await?;
+    UserToken::delete_all_for_user(user.id, pool).await?;
+    log!(
+        "User {} deleted their account, {orders} orders scheduled for purge",
+        user.id
+    );
+    Ok(())
+}
@@ -0,0 +1,443 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use leptos::{log, ServerFnError};
+use sqlx::MySqlPool;
+
+use crate::models::{
+    order::Order,
+    order_item::{Mode, OrderItem},
+    reconciliation::{
+        ChecksumMismatch, DuplicateItem, MissingObject, OrderReconciliation, ReconcileMode,
+        SizeMismatch,
+    },
+};
+use crate::server::delivery::{get_exif_allowed_tags, prepare_for_delivery};
+use crate::server::imaging::{get_derivative_file_name, DERIVATIVES};
+use crate::server::storage::{
+    copy_object, delete_file, get_delivery_prefix, get_derivative_prefix, get_files,
+    get_order_item_path, head_object,
+};
+use crate::server::validation::compute_checksum;
+
+const QUARANTINE_PREFIX: &str = "/quarantine";
+const MODES: [Mode; 2] = [Mode::Original, Mode::Processed];
+
+fn normalize_key(key: &str) -> String {
+    format!("/{}", key.trim_start_matches('/'))
+}
+
+fn get_delivery_path(order_item: &OrderItem) -> Option<String> {
+    order_item
+        .delivery_file_name
+        .as_ref()
+        .map(|delivery_file_name| {
+            format!(
+                "{}/{delivery_file_name}",
+                get_delivery_prefix(order_item.order_id, order_item.mode)
+            )
+        })
+}
+
+async fn list_all(prefix: String) -> Result<Vec<String>, ServerFnError> {
+    let mut keys = Vec::new();
+    let mut prefixes = vec![prefix];
+    while let Some(prefix) = prefixes.pop() {
+        for key in get_files(prefix.clone()).await? {
+            if key.ends_with('/') {
+                if key != prefix {
+                    prefixes.push(key);
+                }
+            } else {
+                keys.push(normalize_key(&key));
+            }
+        }
+    }
+    Ok(keys)
+}
+
+async fn get_storage_order_ids() -> Result<Vec<u64>, ServerFnError> {
+    Ok(get_files(String::new())
+        .await?
+        .into_iter()
+        .filter_map(|key| key.trim_matches('/').parse::<u64>().ok())
+        .collect())
+}
+
+pub async fn reconcile(
+    order_id: Option<u64>,
+    mode: ReconcileMode,
+    verify_checksums: bool,
+    pool: &MySqlPool,
+) -> Result<Vec<OrderReconciliation>, ServerFnError> {
+    let order_ids = match order_id {
+        Some(order_id) => vec![order_id],
+        None => OrderItem::get_order_ids(pool)
+            .await?
+            .into_iter()
+            .chain(get_storage_order_ids().await?)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect(),
+    };
+    let mut reports = Vec::new();
+    for order_id in order_ids {
+        let report = reconcile_order(order_id, mode, verify_checksums, None, pool).await?;
+        if !report.is_clean() {
+            reports.push(report);
+        }
+    }
+    Ok(reports)
+}
+
+// Only what the manager reviewed is repaired, drift found since is left for the next check
+pub async fn repair_reviewed(
+    reviewed: Vec<OrderReconciliation>,
+    mode: ReconcileMode,
+    verify_checksums: bool,
+    pool: &MySqlPool,
+) -> Result<Vec<OrderReconciliation>, ServerFnError> {
+    if mode == ReconcileMode::Report {
+        return Err(ServerFnError::Args(
+            "Choose Repair or Quarantine".to_string(),
+        ));
+    }
+    let mut reports = Vec::new();
+    for reviewed in reviewed.iter() {
+        let report = reconcile_order(
+            reviewed.order_id,
+            mode,
+            verify_checksums,
+            Some(reviewed),
+            pool,
+        )
+        .await?;
+        if !report.is_clean() {
+            reports.push(report);
+        }
+    }
+    Ok(reports)
+}
+
+pub async fn reconcile_order(
+    order_id: u64,
+    mode: ReconcileMode,
+    verify_checksums: bool,
+    reviewed: Option<&OrderReconciliation>,
+    pool: &MySqlPool,
+) -> Result<OrderReconciliation, ServerFnError> {
+    let mut report = OrderReconciliation {
+        order_id,
+        ..Default::default()
+    };
+    let mut order_items = Vec::new();
+    for item_mode in MODES {
+        order_items
+            .extend(OrderItem::get_order_items_by_order_id(order_id, item_mode, pool).await?);
+    }
+    // Purged items keep their rows, so anything left behind for them is orphaned
+    let order_items = order_items
+        .into_iter()
+        .filter(|order_item| order_item.purged_at.is_none())
+        .collect::<Vec<_>>();
+
+    let mut known = HashSet::new();
+    for order_item in order_items.iter() {
+        known.insert(get_order_item_path(order_item));
+        let derivative_file_name = get_derivative_file_name(&order_item.file_name);
+        for derivative in DERIVATIVES {
+            known.insert(format!(
+                "{}/{derivative_file_name}",
+                get_derivative_prefix(order_item.order_id, order_item.mode, derivative)
+            ));
+        }
+        if let Some(path) = get_delivery_path(order_item) {
+            known.insert(path);
+        }
+    }
+    report.orphaned = list_all(format!("{:0>6}/", order_id))
+        .await?
+        .into_iter()
+        .filter(|key| !known.contains(key))
+        .collect();
+
+    let mut missing_delivery = Vec::new();
+    for order_item in order_items.iter().filter(|order_item| order_item.uploaded) {
+        let path = get_order_item_path(order_item);
+        let Some(info) = head_object(path.clone()).await? else {
+            report.missing.push(MissingObject {
+                item_id: order_item.id,
+                path,
+            });
+            continue;
+        };
+        if let Some(expected) = order_item.file_size.filter(|size| *size != info.size) {
+            report.size_mismatches.push(SizeMismatch {
+                item_id: order_item.id,
+                path: path.clone(),
+                expected,
+                actual: info.size,
+            });
+        }
+        match &order_item.checksum {
+            Some(expected) if verify_checksums => {
+                match compute_checksum(path.clone(), info.size).await {
+                    Ok(actual) if &actual != expected => {
+                        report.checksum_mismatches.push(ChecksumMismatch {
+                            item_id: order_item.id,
+                            path: path.clone(),
+                            expected: expected.clone(),
+                            actual,
+                        })
+                    }
+                    Ok(_) => {}
+                    Err(e) => report
+                        .errors
+                        .push(format!("Unable to verify checksum of {path}: {e}")),
+                }
+            }
+            None if mode != ReconcileMode::Report => {
+                let result = match compute_checksum(path.clone(), info.size).await {
+                    Ok(checksum) => match order_item.set_checksum(checksum.clone(), pool).await {
+                        Ok(true) => Ok(None),
+                        Ok(false) => order_item.find_duplicate(&checksum, pool).await,
+                        Err(e) => Err(e),
+                    },
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(None) => {}
+                    // Left without a checksum, the unique index only takes one copy
+                    Ok(Some(duplicate)) => report.duplicates.push(DuplicateItem {
+                        item_id: order_item.id,
+                        duplicate_of: duplicate.id,
+                        path: path.clone(),
+                    }),
+                    Err(e) => report
+                        .errors
+                        .push(format!("Unable to store checksum of {path}: {e}")),
+                }
+            }
+            _ => {}
+        }
+        if let Some(path) = get_delivery_path(order_item) {
+            if head_object(path.clone()).await?.is_none() {
+                report.missing.push(MissingObject {
+                    item_id: order_item.id,
+                    path,
+                });
+                missing_delivery.push(order_item.id);
+            }
+        }
+    }
+
+    // Duplicates are only reported, choosing which copy to keep is left to staff
+    let mut first_seen = HashMap::new();
+    for order_item in order_items.iter().filter(|order_item| order_item.uploaded) {
+        let Some(checksum) = &order_item.checksum else {
+            continue;
+        };
+        match first_seen.get(&(order_item.mode as i16, checksum)) {
+            Some(duplicate_of) => report.duplicates.push(DuplicateItem {
+                item_id: order_item.id,
+                duplicate_of: *duplicate_of,
+                path: get_order_item_path(order_item),
+            }),
+            None => {
+                first_seen.insert((order_item.mode as i16, checksum), order_item.id);
+            }
+        }
+    }
+
+    if mode != ReconcileMode::Report {
+        if let Some(reviewed) = reviewed {
+            limit_to_reviewed(&mut report, reviewed);
+        }
+        repair_order(&mut report, &order_items, &missing_delivery, mode, pool).await;
+    }
+    Ok(report)
+}
+
+fn limit_to_reviewed(report: &mut OrderReconciliation, reviewed: &OrderReconciliation) {
+    report
+        .orphaned
+        .retain(|key| reviewed.orphaned.contains(key));
+    report.missing.retain(|missing| {
+        reviewed
+            .missing
+            .iter()
+            .any(|seen| seen.item_id == missing.item_id && seen.path == missing.path)
+    });
+    report.size_mismatches.retain(|mismatch| {
+        reviewed.size_mismatches.iter().any(|seen| {
+            seen.item_id == mismatch.item_id
+                && seen.expected == mismatch.expected
+                && seen.actual == mismatch.actual
+        })
+    });
+}
+
+#[derive(Debug, PartialEq)]
+enum MissingRepair {
+    // Only the delivery copy is gone, it is prepared again from the original
+    Redeliver,
+    // The original is gone, its slot is freed so the photo can be uploaded again
+    Reupload,
+}
+
+fn repair_for_missing(item_id: u64, missing_delivery: &[u64]) -> MissingRepair {
+    if missing_delivery.contains(&item_id) {
+        MissingRepair::Redeliver
+    } else {
+        MissingRepair::Reupload
+    }
+}
+
+// Same as cancelling an upload, the order goes back to accepting uploads
+async fn reopen_for_upload(
+    order_item: &OrderItem,
+    pool: &MySqlPool,
+) -> Result<bool, ServerFnError> {
+    OrderItem::delete(order_item.id, pool).await?;
+    if let Some(order) = Order::get_by_id(order_item.order_id, pool).await? {
+        order.revert_uploaded_status(order_item.mode, pool).await?;
+    }
+    Ok(true)
+}
+
+async fn repair_order(
+    report: &mut OrderReconciliation,
+    order_items: &[OrderItem],
+    missing_delivery: &[u64],
+    mode: ReconcileMode,
+    pool: &MySqlPool,
+) {
+    let find = |item_id: u64| {
+        order_items
+            .iter()
+            .find(|order_item| order_item.id == item_id)
+    };
+    let mut errors = Vec::new();
+
+    for key in report.orphaned.iter() {
+        let result = match mode {
+            ReconcileMode::Quarantine => {
+                match copy_object(key.clone(), format!("{QUARANTINE_PREFIX}{key}")).await {
+                    Ok(_) => delete_file(key.clone()).await,
+                    Err(e) => Err(e),
+                }
+            }
+            _ => delete_file(key.clone()).await,
+        };
+        if let Err(e) = result {
+            errors.push(format!("Unable to remove {key}: {e}"));
+        }
+    }
+
+    for missing in report.missing.iter() {
+        let Some(order_item) = find(missing.item_id) else {
+            continue;
+        };
+        let result = match repair_for_missing(order_item.id, missing_delivery) {
+            MissingRepair::Redeliver => {
+                prepare_for_delivery(order_item.clone(), get_exif_allowed_tags(), pool).await
+            }
+            MissingRepair::Reupload => reopen_for_upload(order_item, pool).await,
+        };
+        if let Err(e) = result {
+            errors.push(format!("Unable to repair {}: {e}", missing.path));
+        }
+    }
+
+    for mismatch in report.size_mismatches.iter() {
+        let Some(order_item) = find(mismatch.item_id) else {
+            continue;
+        };
+        // The stored size is only corrected when the object still matches its checksum
+        let verified = match &order_item.checksum {
+            Some(expected) => compute_checksum(mismatch.path.clone(), mismatch.actual)
+                .await
+                .map(|actual| &actual == expected),
+            None => Ok(false),
+        };
+        match verified {
+            Ok(true) => {
+                if let Err(e) = order_item.set_file_size(mismatch.actual, pool).await {
+                    errors.push(format!("Unable to update size of {}: {e}", mismatch.path));
+                }
+            }
+            Ok(false) => errors.push(format!(
+                "Size of {} left for review, the object does not match its checksum",
+                mismatch.path
+            )),
+            Err(e) => errors.push(format!(
+                "Unable to verify checksum of {}: {e}",
+                mismatch.path
+            )),
+        }
+    }
+
+    for error in errors.iter() {
+        log!("{error}");
+    }
+    report.errors.extend(errors);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn missing(item_id: u64) -> MissingObject {
+        MissingObject {
+            item_id,
+            path: format!("/000001/original/{item_id}.jpg"),
+        }
+    }
+
+    fn size_mismatch(item_id: u64, actual: u64) -> SizeMismatch {
+        SizeMismatch {
+            item_id,
+            path: format!("/000001/original/{item_id}.jpg"),
+            expected: 100,
+            actual,
+        }
+    }
+
+    #[test]
+    fn missing_originals_are_reopened_for_upload() {
+        assert_eq!(repair_for_missing(1, &[2]), MissingRepair::Reupload);
+        assert_eq!(repair_for_missing(2, &[2]), MissingRepair::Redeliver);
+    }
+
+    #[test]
+    fn repairs_are_limited_to_the_reviewed_report() {
+        let reviewed = OrderReconciliation {
+            order_id: 1,
+            orphaned: vec!["/000001/original/a.jpg".to_string()],
+            missing: vec![missing(1)],
+            size_mismatches: vec![size_mismatch(3, 90)],
+            ..Default::default()
+        };
+        let mut report = OrderReconciliation {
+            order_id: 1,
+            orphaned: vec![
+                "/000001/original/a.jpg".to_string(),
+                "/000001/original/b.jpg".to_string(),
+            ],
+            missing: vec![missing(1), missing(2)],
+            size_mismatches: vec![size_mismatch(3, 80), size_mismatch(4, 90)],
+            ..Default::default()
+        };
+        limit_to_reviewed(&mut report, &reviewed);
+        assert_eq!(report.orphaned, vec!["/000001/original/a.jpg".to_string()]);
+        assert_eq!(
+            report
+                .missing
+                .iter()
+                .map(|missing| missing.item_id)
+                .collect::<Vec<_>>(),
+            vec![1]
+        );
+        // The size changed again since the review
+        assert!(report.size_mismatches.is_empty());
+    }
+}
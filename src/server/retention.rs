@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use chrono::{Days, Local, NaiveDateTime};
+use leptos::{log, ServerFnError};
+use sqlx::MySqlPool;
+
+use crate::models::{
+    order::Order,
+    order_item::{Mode, OrderItem},
+    setting::{RetentionSettings, Setting},
+};
+use crate::server::imaging::{get_derivative_file_name, DERIVATIVES};
+use crate::server::mailer::send_retention_warning;
+use crate::server::storage::{
+    delete_file, get_delivery_prefix, get_derivative_prefix, get_order_item_path, head_object,
+};
+
+const RETENTION_WARNING_DAYS: u32 = 7;
+const MODES: [Mode; 2] = [Mode::Original, Mode::Processed];
+
+fn get_job_interval() -> Duration {
+    let interval = dotenvy::var("RETENTION_JOB_INTERVAL")
+        .ok()
+        .and_then(|interval| interval.parse().ok())
+        .unwrap_or(3600);
+    Duration::from_secs(interval)
+}
+
+fn days_ago(days: u32) -> NaiveDateTime {
+    Local::now()
+        .naive_local()
+        .checked_sub_days(Days::new(days.into()))
+        .unwrap_or(NaiveDateTime::MIN)
+}
+
+pub fn spawn_retention_job(pool: MySqlPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(get_job_interval());
+        loop {
+            interval.tick().await;
+            if let Err(e) = run_retention_job(&pool).await {
+                log!("Retention job failed: {:#?}", e);
+            }
+        }
+    });
+}
+
+pub async fn run_retention_job(pool: &MySqlPool) -> Result<(), ServerFnError> {
+    backfill_file_sizes(pool).await?;
+    let retention = Setting::get_retention(pool).await?;
+    send_retention_warnings(&retention, pool).await?;
+    purge_expired_items(&retention, pool).await
+}
+
+async fn backfill_file_sizes(pool: &MySqlPool) -> Result<(), ServerFnError> {
+    for order_item in OrderItem::get_missing_file_size(pool).await? {
+        let result = match head_object(get_order_item_path(&order_item)).await {
+            Ok(Some(info)) => order_item.set_file_size(info.size, pool).await.map(|_| ()),
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            log!(
+                "Unable to backfill the size of order item {}: {:#?}",
+                order_item.id,
+                e
+            );
+        }
+    }
+    Ok(())
+}
+
+// A purge waits for its warning to be RETENTION_WARNING_DAYS old, so the
+// warning always gives the customer that many days
+async fn send_retention_warnings(
+    retention: &RetentionSettings,
+    pool: &MySqlPool,
+) -> Result<(), ServerFnError> {
+    for mode in MODES {
+        let days = retention.days_for(mode);
+        if days == 0 {
+            continue;
+        }
+        let delivered_before = days_ago(days.saturating_sub(RETENTION_WARNING_DAYS));
+        for order in Order::get_due_for_retention_warning(mode, delivered_before, pool).await? {
+            let customer = order.get_customer(pool).await?;
+            match send_retention_warning(
+                customer.email,
+                customer.name,
+                mode,
+                RETENTION_WARNING_DAYS,
+            )
+            .await
+            {
+                Ok(_) => {
+                    order.set_retention_warning_sent(mode, pool).await?;
+                }
+                Err(e) => log!(
+                    "Unable to send retention warning for {}: {:#?}",
+                    order.id,
+                    e
+                ),
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn purge_expired_items(
+    retention: &RetentionSettings,
+    pool: &MySqlPool,
+) -> Result<(), ServerFnError> {
+    for mode in MODES {
+        let days = retention.days_for(mode);
+        if days == 0 {
+            continue;
+        }
+        let due = OrderItem::get_due_for_purge(
+            mode,
+            days_ago(days),
+            days_ago(RETENTION_WARNING_DAYS),
+            pool,
+        )
+        .await?;
+        for order_item in due {
+            if let Err(e) = purge_order_item(&order_item, pool).await {
+                log!("Unable to purge order item {}: {:#?}", order_item.id, e);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn purge_order_item(
+    order_item: &OrderItem,
+    pool: &MySqlPool,
+) -> Result<bool, ServerFnError> {
+    let derivative_file_name = get_derivative_file_name(&order_item.file_name);
+    let mut paths = DERIVATIVES
+        .iter()
+        .map(|derivative| {
+            format!(
+                "{}/{derivative_file_name}",
+                get_derivative_prefix(order_item.order_id, order_item.mode, *derivative)
+            )
+        })
+        .collect::<Vec<_>>();
+    if let Some(delivery_file_name) = &order_item.delivery_file_name {
+        paths.push(format!(
+            "{}/{delivery_file_name}",
+            get_delivery_prefix(order_item.order_id, order_item.mode)
+        ));
+    }
+    paths.push(get_order_item_path(order_item));
+    for path in paths {
+        delete_file(path).await?;
+    }
+    order_item.set_purged(pool).await
+}
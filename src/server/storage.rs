@@ -1,111 +1,233 @@
+pub mod bucket;
+pub mod local;
+
 use std::collections::HashMap;
 
+use axum::async_trait;
 use leptos::ServerFnError;
-use s3::bucket::Bucket;
-use s3::creds::Credentials;
-use s3::region::Region;
+use serde::{Deserialize, Serialize};
+
+use crate::components::files::uploader::UploadedPart;
+use crate::models::order_item::{Derivative, Mode, OrderItem};
+
+const UPLOAD_URL_EXPIRY: u32 = 3600;
+const DOWNLOAD_URL_EXPIRY: u32 = 300;
+const UPLOAD_PART_URL_EXPIRY: u32 = 900;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectInfo {
+    pub size: u64,
+    pub content_type: Option<String>,
+}
+
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    async fn list(&self, prefix: String) -> Result<Vec<String>, ServerFnError>;
+    async fn delete(&self, path: String) -> Result<bool, ServerFnError>;
+    async fn head(&self, path: String) -> Result<Option<ObjectInfo>, ServerFnError>;
+    async fn copy(&self, from: String, to: String) -> Result<bool, ServerFnError>;
+    async fn get(&self, path: String) -> Result<Vec<u8>, ServerFnError>;
+    async fn get_range(&self, path: String, start: u64, end: u64)
+        -> Result<Vec<u8>, ServerFnError>;
+    async fn put(
+        &self,
+        path: String,
+        content: Vec<u8>,
+        content_type: String,
+    ) -> Result<bool, ServerFnError>;
+    async fn presign_get(
+        &self,
+        path: String,
+        expiry_secs: u32,
+        queries: HashMap<String, String>,
+    ) -> Result<String, ServerFnError>;
+    async fn presign_put(&self, path: String, expiry_secs: u32) -> Result<String, ServerFnError>;
+    async fn create_multipart_upload(
+        &self,
+        path: String,
+        content_type: String,
+    ) -> Result<String, ServerFnError>;
+    async fn presign_upload_part(
+        &self,
+        path: String,
+        upload_id: String,
+        part_number: u32,
+        expiry_secs: u32,
+    ) -> Result<String, ServerFnError>;
+    async fn complete_multipart_upload(
+        &self,
+        path: String,
+        upload_id: String,
+        parts: Vec<UploadedPart>,
+    ) -> Result<bool, ServerFnError>;
+    async fn abort_multipart_upload(
+        &self,
+        path: String,
+        upload_id: String,
+    ) -> Result<bool, ServerFnError>;
+}
 
-use crate::models::order_item::Mode;
-use crate::to_server_fn_error;
+#[derive(Debug, Clone, PartialEq)]
+pub enum StorageBackendKind {
+    S3,
+    Local,
+}
+
+pub fn get_backend_kind() -> StorageBackendKind {
+    match dotenvy::var("STORAGE_BACKEND")
+        .unwrap_or("s3".into())
+        .to_lowercase()
+        .as_str()
+    {
+        "local" => StorageBackendKind::Local,
+        _ => StorageBackendKind::S3,
+    }
+}
+
+pub async fn get_backend() -> Result<Box<dyn StorageBackend>, ServerFnError> {
+    match get_backend_kind() {
+        StorageBackendKind::S3 => Ok(Box::new(bucket::S3Storage::new().await?)),
+        StorageBackendKind::Local => Ok(Box::new(local::LocalStorage::new()?)),
+    }
+}
 
 pub fn get_prefix(order_id: u64, mode: Mode) -> String {
     let prefix = format!("/{:0>6}/{:?}", order_id, mode).to_lowercase();
     prefix
 }
 
-pub async fn get_bucket() -> Result<Bucket, ServerFnError> {
-    let bucket_name = dotenvy::var("S3_BUCKET_NAME").expect("should be present");
-    let endpoint = dotenvy::var("S3_ENDPOINT").expect("should be present");
-    let region = dotenvy::var("S3_REGION").expect("should be present");
-    let region = Region::Custom { region, endpoint };
-    let access_key = dotenvy::var("S3_ACCESS_KEY").expect("should be present");
-    let secret_key = dotenvy::var("S3_SECRET_KEY").expect("should be present");
-    let credentials = Credentials::new(Some(&access_key), Some(&secret_key), None, None, None)
-        .expect("should work");
-    let bucket = Bucket::new(&bucket_name, region, credentials);
-    bucket.map_err(|e| ServerFnError::ServerError(e.to_string()))
+pub fn get_order_item_path(order_item: &OrderItem) -> String {
+    format!(
+        "{}/{}",
+        get_prefix(order_item.order_id, order_item.mode),
+        order_item.file_name
+    )
 }
 
-pub async fn get_files(prefix: String) -> Result<Vec<String>, ServerFnError> {
-    let bucket = get_bucket().await?;
-    bucket
-        .list(prefix, Some("/".into()))
-        .await
-        .map_err(|e| ServerFnError::ServerError(e.to_string()))
-        .map(|r| {
-            r.iter()
-                .flat_map(|i| {
-                    let mut common_prefixes: Vec<String> = i
-                        .common_prefixes
-                        .clone()
-                        .map(|p| p.iter().map(|p| p.prefix.clone()).collect())
-                        .unwrap_or(Vec::new());
-                    let mut content: Vec<String> =
-                        i.contents.iter().map(|c| c.key.clone()).collect();
-                    common_prefixes.append(&mut content);
-                    common_prefixes
-                })
-                .collect()
-        })
+pub fn get_derivative_prefix(order_id: u64, mode: Mode, derivative: Derivative) -> String {
+    let prefix = format!("/{:0>6}/{:?}/{:?}", order_id, derivative, mode).to_lowercase();
+    prefix
 }
 
-pub async fn delete_file(path: String) -> Result<bool, ServerFnError> {
-    leptos::log!("Deleting {path:?}");
-    let bucket = get_bucket().await?;
-    bucket
-        .delete_object(path)
-        .await
-        .map_err(to_server_fn_error)
-        .map(|response| {
-            leptos::log!("{:#?}", response);
-            true
-        })
+pub fn get_delivery_prefix(order_id: u64, mode: Mode) -> String {
+    let prefix = format!("/{:0>6}/delivery/{:?}", order_id, mode).to_lowercase();
+    prefix
 }
 
-pub async fn create_presigned_url(
-    prefix: String,
-    file_name: String,
-    mime_type: String,
-) -> Result<String, ServerFnError> {
-    let path = format!("{prefix}/{file_name}");
+fn get_url_queries(file_name: &str, mime_type: String) -> HashMap<String, String> {
     let mut get_queries = HashMap::new();
     get_queries.insert(
         "response-content-disposition".into(),
         format!("attachment; filename=\"{file_name}\""),
     );
-    get_queries.insert("content-type".into(), mime_type.clone());
-    let bucket = get_bucket().await?;
-    bucket
-        .presign_get(path, 604800, Some(get_queries))
-        .map_err(to_server_fn_error)
+    get_queries.insert("content-type".into(), mime_type);
+    get_queries
+}
+
+pub async fn get_files(prefix: String) -> Result<Vec<String>, ServerFnError> {
+    get_backend().await?.list(prefix).await
+}
+
+pub async fn delete_file(path: String) -> Result<bool, ServerFnError> {
+    leptos::log!("Deleting {path:?}");
+    get_backend().await?.delete(path).await
+}
+
+pub async fn head_object(path: String) -> Result<Option<ObjectInfo>, ServerFnError> {
+    get_backend().await?.head(path).await
+}
+
+pub async fn copy_object(from: String, to: String) -> Result<bool, ServerFnError> {
+    get_backend().await?.copy(from, to).await
+}
+
+pub async fn get_object(path: String) -> Result<Vec<u8>, ServerFnError> {
+    get_backend().await?.get(path).await
 }
-pub async fn create_presigned_put_url(path: String) -> Result<String, ServerFnError> {
-    let bucket = get_bucket().await?;
-    bucket
-        .presign_put(path, 300, None)
-        .map_err(to_server_fn_error)
+
+pub async fn get_object_range(
+    path: String,
+    start: u64,
+    end: u64,
+) -> Result<Vec<u8>, ServerFnError> {
+    get_backend().await?.get_range(path, start, end).await
 }
 
-pub async fn create_presigned_url_pair(
+pub async fn put_object(
+    path: String,
+    content: Vec<u8>,
+    content_type: String,
+) -> Result<bool, ServerFnError> {
+    get_backend().await?.put(path, content, content_type).await
+}
+
+pub async fn create_upload_url(path: String) -> Result<String, ServerFnError> {
+    get_backend().await?.presign_put(path, UPLOAD_URL_EXPIRY).await
+}
+
+pub async fn create_download_url(
     prefix: String,
     file_name: String,
     mime_type: String,
-) -> Result<(String, String), ServerFnError> {
+) -> Result<String, ServerFnError> {
     let path = format!("{prefix}/{file_name}");
+    let get_queries = get_url_queries(&file_name, mime_type);
+    get_backend()
+        .await?
+        .presign_get(path, DOWNLOAD_URL_EXPIRY, get_queries)
+        .await
+}
 
-    let mut get_queries = HashMap::new();
-    get_queries.insert(
-        "response-content-disposition".into(),
-        format!("attachment; filename=\"{file_name}\""),
-    );
-    get_queries.insert("content-type".into(), mime_type.clone());
-    let bucket = get_bucket().await?;
-    bucket
-        .presign_put(path.clone(), 3600, None)
-        .and_then(|put_url| {
-            bucket
-                .presign_get(path, 604800, Some(get_queries))
-                .map(|get_url| (get_url, put_url))
-        })
-        .map_err(to_server_fn_error)
+pub async fn create_multipart_upload(
+    path: String,
+    content_type: String,
+) -> Result<String, ServerFnError> {
+    get_backend()
+        .await?
+        .create_multipart_upload(path, content_type)
+        .await
+}
+
+pub async fn create_presigned_upload_part_urls(
+    path: String,
+    upload_id: String,
+    part_numbers: Vec<u32>,
+) -> Result<Vec<(u32, String)>, ServerFnError> {
+    let backend = get_backend().await?;
+    let mut urls = Vec::new();
+    for part_number in part_numbers {
+        let url = backend
+            .presign_upload_part(
+                path.clone(),
+                upload_id.clone(),
+                part_number,
+                UPLOAD_PART_URL_EXPIRY,
+            )
+            .await?;
+        urls.push((part_number, url));
+    }
+    Ok(urls)
+}
+
+pub async fn complete_multipart_upload(
+    path: String,
+    upload_id: String,
+    mut parts: Vec<UploadedPart>,
+) -> Result<bool, ServerFnError> {
+    parts.sort_by_key(|part| part.part_number);
+    get_backend()
+        .await?
+        .complete_multipart_upload(path, upload_id, parts)
+        .await
+}
+
+pub async fn abort_multipart_upload(
+    path: String,
+    upload_id: String,
+) -> Result<bool, ServerFnError> {
+    leptos::log!("Aborting multipart upload {upload_id} for {path:?}");
+    get_backend()
+        .await?
+        .abort_multipart_upload(path, upload_id)
+        .await
 }
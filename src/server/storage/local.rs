@@ -0,0 +1,605 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Component, Path as FsPath, PathBuf};
+
+use axum::{
+    async_trait,
+    body::{boxed, Body, BoxBody, HttpBody},
+    extract::{Path, Query},
+    http::{header, HeaderValue, Request, Response, StatusCode},
+    response::IntoResponse,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use leptos::{log, ServerFnError};
+use sha2::Sha256;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+
+use super::{ObjectInfo, StorageBackend};
+use crate::components::files::uploader::UploadedPart;
+use crate::to_server_fn_error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const EXPIRES: &str = "expires";
+const SIGNATURE: &str = "signature";
+const CONTENT_DISPOSITION: &str = "response-content-disposition";
+const CONTENT_TYPE: &str = "content-type";
+const PART_NUMBER: &str = "partNumber";
+const UPLOAD_ID: &str = "uploadId";
+const MULTIPART_DIR: &str = ".multipart";
+
+pub struct LocalStorage {
+    root: PathBuf,
+    secret: String,
+    base_url: String,
+}
+
+impl LocalStorage {
+    pub fn new() -> Result<Self, ServerFnError> {
+        let root = dotenvy::var("STORAGE_LOCAL_ROOT").unwrap_or("./storage".into());
+        let secret = dotenvy::var("STORAGE_LOCAL_SECRET")
+            .map_err(|_| ServerFnError::ServerError("STORAGE_LOCAL_SECRET is not set".into()))?;
+        let base_url = dotenvy::var("APP_URL").map_err(to_server_fn_error)?;
+        Ok(Self {
+            root: PathBuf::from(root),
+            secret,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    fn resolve(&self, key: &str) -> Result<PathBuf, ServerFnError> {
+        let relative = FsPath::new(key.trim_start_matches('/'));
+        if relative
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(ServerFnError::Args(format!("Invalid storage path: {key}")));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    fn resolve_part(
+        &self,
+        upload_id: &str,
+        part_number: Option<u32>,
+    ) -> Result<PathBuf, ServerFnError> {
+        let dir = self.resolve(&format!("{MULTIPART_DIR}/{upload_id}"))?;
+        Ok(match part_number {
+            Some(part_number) => dir.join(format!("{part_number:05}")),
+            None => dir,
+        })
+    }
+
+    // Form-encoding every name and value keeps them from passing for separators,
+    // so no two different requests share a canonical string
+    fn canonical_request(
+        method: &str,
+        key: &str,
+        expires: i64,
+        queries: &BTreeMap<String, String>,
+    ) -> Result<String, serde_urlencoded::ser::Error> {
+        let key = format!("/{}", key.trim_start_matches('/'));
+        let expires = expires.to_string();
+        let parts = [("method", method), ("key", &key), ("expires", &expires)]
+            .into_iter()
+            .chain(queries.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+            .collect::<Vec<_>>();
+        serde_urlencoded::to_string(parts)
+    }
+
+    fn signature(&self, canonical_request: &str) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(self.secret.as_bytes()).expect("HMAC accepts any key size");
+        mac.update(canonical_request.as_bytes());
+        mac
+    }
+
+    fn signed_queries(
+        &self,
+        method: &str,
+        key: &str,
+        expires: i64,
+        queries: HashMap<String, String>,
+    ) -> Result<BTreeMap<String, String>, ServerFnError> {
+        let mut queries = queries.into_iter().collect::<BTreeMap<_, _>>();
+        let canonical_request =
+            Self::canonical_request(method, key, expires, &queries).map_err(to_server_fn_error)?;
+        let signature = self.signature(&canonical_request).finalize().into_bytes();
+        queries.insert(EXPIRES.into(), expires.to_string());
+        queries.insert(SIGNATURE.into(), hex::encode(signature));
+        Ok(queries)
+    }
+
+    fn presign(
+        &self,
+        method: &str,
+        key: &str,
+        expiry_secs: u32,
+        queries: HashMap<String, String>,
+    ) -> Result<String, ServerFnError> {
+        self.resolve(key)?;
+        let expires = Utc::now().timestamp() + expiry_secs as i64;
+        let queries = self.signed_queries(method, key, expires, queries)?;
+        let query_string = serde_urlencoded::to_string(&queries).map_err(to_server_fn_error)?;
+        Ok(format!(
+            "{}/storage/{}?{query_string}",
+            self.base_url,
+            key.trim_start_matches('/')
+        ))
+    }
+
+    fn verify(&self, method: &str, key: &str, mut queries: HashMap<String, String>) -> bool {
+        let (Some(expires), Some(signature)) = (queries.remove(EXPIRES), queries.remove(SIGNATURE))
+        else {
+            return false;
+        };
+        let Ok(expires) = expires.parse::<i64>() else {
+            return false;
+        };
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+        if expires < Utc::now().timestamp() {
+            return false;
+        }
+        let queries = queries.into_iter().collect::<BTreeMap<_, _>>();
+        let Ok(canonical_request) = Self::canonical_request(method, key, expires, &queries) else {
+            return false;
+        };
+        self.signature(&canonical_request)
+            .verify_slice(&signature)
+            .is_ok()
+    }
+}
+
+fn list_dir(root: &FsPath, dir: &FsPath, keys: &mut Vec<String>) -> Result<(), std::io::Error> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if path
+                .file_name()
+                .map(|name| name == MULTIPART_DIR)
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            list_dir(root, &path, keys)?;
+        } else if path.extension().map(|ext| ext == "part").unwrap_or(false) {
+            continue;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            keys.push(format!("/{}", relative.to_string_lossy()));
+        }
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl StorageBackend for LocalStorage {
+    async fn list(&self, prefix: String) -> Result<Vec<String>, ServerFnError> {
+        let root = self.root.clone();
+        let keys = tokio::task::spawn_blocking(move || {
+            let mut keys = Vec::new();
+            if root.is_dir() {
+                list_dir(&root, &root, &mut keys)?;
+            }
+            Ok::<_, std::io::Error>(keys)
+        })
+        .await
+        .map_err(to_server_fn_error)?
+        .map_err(to_server_fn_error)?;
+
+        let prefix = format!("/{}", prefix.trim_start_matches('/'));
+        let mut common_prefixes = BTreeSet::new();
+        let mut contents = BTreeSet::new();
+        for key in keys.into_iter().filter(|key| key.starts_with(&prefix)) {
+            match key[prefix.len()..].find('/') {
+                Some(index) => {
+                    common_prefixes.insert(key[..prefix.len() + index + 1].to_string());
+                }
+                None => {
+                    contents.insert(key);
+                }
+            }
+        }
+        Ok(common_prefixes.into_iter().chain(contents).collect())
+    }
+
+    async fn delete(&self, path: String) -> Result<bool, ServerFnError> {
+        match tokio::fs::remove_file(self.resolve(&path)?).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(to_server_fn_error(e)),
+        }
+    }
+
+    async fn head(&self, path: String) -> Result<Option<ObjectInfo>, ServerFnError> {
+        match tokio::fs::metadata(self.resolve(&path)?).await {
+            Ok(metadata) if metadata.is_file() => Ok(Some(ObjectInfo {
+                size: metadata.len(),
+                content_type: crate::components::files::uploader::get_mime_type(path).ok(),
+            })),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(to_server_fn_error(e)),
+        }
+    }
+
+    async fn copy(&self, from: String, to: String) -> Result<bool, ServerFnError> {
+        let (from, to) = (self.resolve(&from)?, self.resolve(&to)?);
+        if let Some(parent) = to.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(to_server_fn_error)?;
+        }
+        tokio::fs::copy(from, to)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|_| true)
+    }
+
+    async fn get(&self, path: String) -> Result<Vec<u8>, ServerFnError> {
+        tokio::fs::read(self.resolve(&path)?)
+            .await
+            .map_err(to_server_fn_error)
+    }
+
+    async fn get_range(
+        &self,
+        path: String,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<u8>, ServerFnError> {
+        let mut file = tokio::fs::File::open(self.resolve(&path)?)
+            .await
+            .map_err(to_server_fn_error)?;
+        file.seek(std::io::SeekFrom::Start(start))
+            .await
+            .map_err(to_server_fn_error)?;
+        let mut content = Vec::new();
+        file.take(end.saturating_sub(start) + 1)
+            .read_to_end(&mut content)
+            .await
+            .map_err(to_server_fn_error)?;
+        Ok(content)
+    }
+
+    async fn put(
+        &self,
+        path: String,
+        content: Vec<u8>,
+        _content_type: String,
+    ) -> Result<bool, ServerFnError> {
+        let path = self.resolve(&path)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(to_server_fn_error)?;
+        }
+        tokio::fs::write(path, content)
+            .await
+            .map_err(to_server_fn_error)
+            .map(|_| true)
+    }
+
+    async fn presign_get(
+        &self,
+        path: String,
+        expiry_secs: u32,
+        queries: HashMap<String, String>,
+    ) -> Result<String, ServerFnError> {
+        self.presign("GET", &path, expiry_secs, queries)
+    }
+
+    async fn presign_put(&self, path: String, expiry_secs: u32) -> Result<String, ServerFnError> {
+        self.presign("PUT", &path, expiry_secs, HashMap::new())
+    }
+
+    async fn create_multipart_upload(
+        &self,
+        _path: String,
+        _content_type: String,
+    ) -> Result<String, ServerFnError> {
+        let upload_id = uuid::Uuid::new_v4().as_simple().to_string();
+        tokio::fs::create_dir_all(self.resolve_part(&upload_id, None)?)
+            .await
+            .map_err(to_server_fn_error)?;
+        Ok(upload_id)
+    }
+
+    async fn presign_upload_part(
+        &self,
+        path: String,
+        upload_id: String,
+        part_number: u32,
+        expiry_secs: u32,
+    ) -> Result<String, ServerFnError> {
+        self.resolve_part(&upload_id, Some(part_number))?;
+        let queries = HashMap::from([
+            (PART_NUMBER.to_string(), part_number.to_string()),
+            (UPLOAD_ID.to_string(), upload_id),
+        ]);
+        self.presign("PUT", &path, expiry_secs, queries)
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        path: String,
+        upload_id: String,
+        parts: Vec<UploadedPart>,
+    ) -> Result<bool, ServerFnError> {
+        let destination = self.resolve(&path)?;
+        if let Some(parent) = destination.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(to_server_fn_error)?;
+        }
+        let partial = destination.with_extension(format!("{upload_id}.part"));
+        let mut file = tokio::fs::File::create(&partial)
+            .await
+            .map_err(to_server_fn_error)?;
+        for part in parts.iter() {
+            let mut part_file =
+                tokio::fs::File::open(self.resolve_part(&upload_id, Some(part.part_number))?)
+                    .await
+                    .map_err(to_server_fn_error)?;
+            tokio::io::copy(&mut part_file, &mut file)
+                .await
+                .map_err(to_server_fn_error)?;
+        }
+        file.flush().await.map_err(to_server_fn_error)?;
+        tokio::fs::rename(&partial, destination)
+            .await
+            .map_err(to_server_fn_error)?;
+        self.abort_multipart_upload(path, upload_id).await
+    }
+
+    async fn abort_multipart_upload(
+        &self,
+        _path: String,
+        upload_id: String,
+    ) -> Result<bool, ServerFnError> {
+        match tokio::fs::remove_dir_all(self.resolve_part(&upload_id, None)?).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(to_server_fn_error(e)),
+        }
+    }
+}
+
+fn error_response(status: StatusCode) -> Response<BoxBody> {
+    (status, status.canonical_reason().unwrap_or_default()).into_response()
+}
+
+pub async fn get_object_handler(
+    Path(key): Path<String>,
+    Query(queries): Query<HashMap<String, String>>,
+    request: Request<Body>,
+) -> Response<BoxBody> {
+    let Ok(storage) = LocalStorage::new() else {
+        return error_response(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+    if !storage.verify("GET", &key, queries.clone()) {
+        return error_response(StatusCode::FORBIDDEN);
+    }
+    let Ok(path) = storage.resolve(&key) else {
+        return error_response(StatusCode::BAD_REQUEST);
+    };
+    let mut response = match ServeFile::new(path).oneshot(request).await {
+        Ok(response) => response.map(boxed),
+        Err(e) => {
+            log!("Unable to serve {key}: {e:#?}");
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let headers = response.headers_mut();
+    for (query, header) in [
+        (CONTENT_DISPOSITION, header::CONTENT_DISPOSITION),
+        (CONTENT_TYPE, header::CONTENT_TYPE),
+    ] {
+        if let Some(value) = queries
+            .get(query)
+            .and_then(|value| HeaderValue::from_str(value).ok())
+        {
+            headers.insert(header, value);
+        }
+    }
+    response
+}
+
+pub async fn put_object_handler(
+    Path(key): Path<String>,
+    Query(queries): Query<HashMap<String, String>>,
+    request: Request<Body>,
+) -> Response<BoxBody> {
+    let Ok(storage) = LocalStorage::new() else {
+        return error_response(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+    if !storage.verify("PUT", &key, queries.clone()) {
+        return error_response(StatusCode::FORBIDDEN);
+    }
+    let part = match (queries.get(UPLOAD_ID), queries.get(PART_NUMBER)) {
+        (Some(upload_id), Some(part_number)) => match part_number.parse::<u32>() {
+            Ok(part_number) => Some((upload_id, part_number)),
+            Err(_) => return error_response(StatusCode::BAD_REQUEST),
+        },
+        _ => None,
+    };
+    let path = match part {
+        Some((upload_id, part_number)) => storage.resolve_part(upload_id, Some(part_number)),
+        None => storage.resolve(&key),
+    };
+    let Ok(path) = path else {
+        return error_response(StatusCode::BAD_REQUEST);
+    };
+    match write_body(&path, request.into_body()).await {
+        Ok(_) => match part {
+            Some((upload_id, part_number)) => (
+                StatusCode::OK,
+                [(header::ETAG, format!("\"{upload_id}-{part_number}\""))],
+            )
+                .into_response(),
+            None => StatusCode::OK.into_response(),
+        },
+        Err(e) => {
+            log!("Unable to store {key}: {e:#?}");
+            error_response(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn write_body(path: &FsPath, body: Body) -> Result<(), ServerFnError> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(to_server_fn_error)?;
+    }
+    let partial = path.with_extension(format!("{}.part", uuid::Uuid::new_v4().as_simple()));
+    match write_partial(&partial, body).await {
+        Ok(_) => tokio::fs::rename(&partial, path)
+            .await
+            .map_err(to_server_fn_error),
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            Err(e)
+        }
+    }
+}
+
+async fn write_partial(partial: &FsPath, mut body: Body) -> Result<(), ServerFnError> {
+    let mut file = tokio::fs::File::create(partial)
+        .await
+        .map_err(to_server_fn_error)?;
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(to_server_fn_error)?;
+        file.write_all(&chunk).await.map_err(to_server_fn_error)?;
+    }
+    file.flush().await.map_err(to_server_fn_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "/orders/42/original/IMG_0001.jpg";
+
+    fn storage() -> LocalStorage {
+        LocalStorage {
+            root: PathBuf::from("./storage"),
+            secret: "test-secret".into(),
+            base_url: "https://booth.example".into(),
+        }
+    }
+
+    // Splits a presigned URL back into the key and queries the handlers receive
+    fn parse(url: &str) -> (String, Vec<(String, String)>) {
+        let (path, query) = url.split_once('?').unwrap();
+        let key = path.trim_start_matches("https://booth.example/storage/");
+        (key.to_string(), serde_urlencoded::from_str(query).unwrap())
+    }
+
+    fn disposition() -> HashMap<String, String> {
+        HashMap::from([(
+            CONTENT_DISPOSITION.to_string(),
+            "attachment; filename=\"IMG_0001.jpg\"".to_string(),
+        )])
+    }
+
+    #[test]
+    fn presigned_urls_verify() {
+        let storage = storage();
+        let url = storage.presign("GET", KEY, 60, disposition()).unwrap();
+        let (key, queries) = parse(&url);
+        assert!(storage.verify("GET", &key, queries.into_iter().collect()));
+    }
+
+    #[test]
+    fn tampered_urls_are_refused() {
+        let storage = storage();
+        let url = storage.presign("GET", KEY, 60, disposition()).unwrap();
+        let (key, queries) = parse(&url);
+        let queries = queries.into_iter().collect::<HashMap<_, _>>();
+
+        assert!(!storage.verify("PUT", &key, queries.clone()));
+        assert!(!storage.verify("GET", "/orders/43/original/IMG_0001.jpg", queries.clone()));
+
+        let mut changed = queries.clone();
+        changed.insert(CONTENT_TYPE.into(), "text/html".into());
+        assert!(!storage.verify("GET", &key, changed));
+
+        let mut changed = queries.clone();
+        changed.insert(CONTENT_DISPOSITION.into(), "inline".into());
+        assert!(!storage.verify("GET", &key, changed));
+
+        let mut changed = queries.clone();
+        let expires = changed[EXPIRES].parse::<i64>().unwrap() + 3600;
+        changed.insert(EXPIRES.into(), expires.to_string());
+        assert!(!storage.verify("GET", &key, changed));
+
+        let mut changed = queries;
+        changed.insert(SIGNATURE.into(), hex::encode([0u8; 32]));
+        assert!(!storage.verify("GET", &key, changed));
+    }
+
+    #[test]
+    fn expired_urls_are_refused() {
+        let storage = storage();
+        let now = Utc::now().timestamp();
+        let valid = storage
+            .signed_queries("GET", KEY, now + 60, HashMap::new())
+            .unwrap();
+        assert!(storage.verify("GET", KEY, valid.into_iter().collect()));
+        let expired = storage
+            .signed_queries("GET", KEY, now - 1, HashMap::new())
+            .unwrap();
+        assert!(!storage.verify("GET", KEY, expired.into_iter().collect()));
+    }
+
+    #[test]
+    fn reordered_queries_still_verify() {
+        let storage = storage();
+        let queries = HashMap::from([
+            (PART_NUMBER.to_string(), "3".to_string()),
+            (UPLOAD_ID.to_string(), "abc123".to_string()),
+        ]);
+        let url = storage.presign("PUT", KEY, 60, queries).unwrap();
+        let (key, mut queries) = parse(&url);
+        queries.reverse();
+        assert!(storage.verify("PUT", &key, queries.into_iter().collect()));
+    }
+
+    #[test]
+    fn values_cannot_pass_for_other_queries() {
+        let storage = storage();
+        let swapped = storage
+            .signed_queries(
+                "PUT",
+                KEY,
+                Utc::now().timestamp() + 60,
+                HashMap::from([
+                    (PART_NUMBER.to_string(), "abc123".to_string()),
+                    (UPLOAD_ID.to_string(), "3".to_string()),
+                ]),
+            )
+            .unwrap();
+        let mut queries = swapped.into_iter().collect::<HashMap<_, _>>();
+        queries.insert(PART_NUMBER.into(), "3".into());
+        queries.insert(UPLOAD_ID.into(), "abc123".into());
+        assert!(!storage.verify("PUT", KEY, queries));
+
+        // A value holding a separator must not sign for two separate queries
+        let smuggled = storage
+            .signed_queries(
+                "PUT",
+                KEY,
+                Utc::now().timestamp() + 60,
+                HashMap::from([(PART_NUMBER.to_string(), "3&uploadId=abc123".to_string())]),
+            )
+            .unwrap();
+        let mut queries = smuggled.into_iter().collect::<HashMap<_, _>>();
+        queries.insert(PART_NUMBER.into(), "3".into());
+        queries.insert(UPLOAD_ID.into(), "abc123".into());
+        assert!(!storage.verify("PUT", KEY, queries));
+    }
+}
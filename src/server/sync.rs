@@ -0,0 +1,209 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+
+use crate::auth::AuthSession;
+use crate::models::{
+    ingest::{IngestFile, SyncOrder},
+    order::{Order, OrderStatus},
+    order_item::{Mode, OrderItem},
+    user::{Role, User},
+};
+use crate::server::app_state::AppState;
+use crate::server::delivery::complete_order;
+use crate::server::ingest::{to_ingest_error, IngestResult};
+use crate::server::uploads::{cancel_upload, confirm_upload, create_order_item};
+
+fn require_processor(auth_session: &AuthSession) -> Result<User, (StatusCode, String)> {
+    match auth_session.current_user.clone() {
+        Some(user) if user.role == Role::Processor => Ok(user),
+        Some(user) if user.role != Role::Anonymous => Err((
+            StatusCode::FORBIDDEN,
+            "Only processors are allowed to make this request".to_string(),
+        )),
+        _ => Err((StatusCode::UNAUTHORIZED, "Not logged in".to_string())),
+    }
+}
+
+async fn get_claimed_order(
+    order_id: u64,
+    processor: &User,
+    pool: &sqlx::MySqlPool,
+) -> Result<Order, (StatusCode, String)> {
+    let order = Order::get_by_id(order_id, pool)
+        .await
+        .map_err(to_ingest_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Order not found".to_string()))?;
+    if order.processor_id != Some(processor.id) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("Order {order_id} is not claimed by you"),
+        ));
+    }
+    Ok(order)
+}
+
+async fn get_claimed_order_item(
+    item_id: u64,
+    processor: &User,
+    pool: &sqlx::MySqlPool,
+) -> Result<OrderItem, (StatusCode, String)> {
+    let order_item = OrderItem::get_by_id(item_id, pool)
+        .await
+        .map_err(|_| (StatusCode::NOT_FOUND, "Photo not found".to_string()))?;
+    get_claimed_order(order_item.order_id, processor, pool).await?;
+    if order_item.mode != Mode::Processed {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Only processed photos can be changed".to_string(),
+        ));
+    }
+    Ok(order_item)
+}
+
+async fn to_sync_order(
+    order: Order,
+    pool: &sqlx::MySqlPool,
+) -> Result<SyncOrder, (StatusCode, String)> {
+    let remaining = order
+        .remaining_order_items(Mode::Processed, pool)
+        .await
+        .map_err(to_ingest_error)?;
+    let originals = order
+        .get_order_items(Mode::Original, pool)
+        .await
+        .map_err(to_ingest_error)?
+        .into_iter()
+        .filter(|order_item| order_item.uploaded && order_item.purged_at.is_none())
+        .collect();
+    Ok(SyncOrder {
+        order_id: order.id,
+        status: order.status,
+        no_of_photos: order.no_of_photos,
+        remaining,
+        originals,
+    })
+}
+
+pub async fn orders_handler(
+    State(AppState { pool, .. }): State<AppState>,
+    auth_session: AuthSession,
+) -> IngestResult<Vec<SyncOrder>> {
+    let processor = require_processor(&auth_session)?;
+    let mut orders = Vec::new();
+    for order in Order::get_claimed_by_processor(processor.id, &pool)
+        .await
+        .map_err(to_ingest_error)?
+    {
+        orders.push(to_sync_order(order, &pool).await?);
+    }
+    Ok(Json(orders))
+}
+
+// Same as the Fetch Order button, an order already in progress is returned
+// instead of claiming another one
+pub async fn claim_handler(
+    State(AppState { pool, .. }): State<AppState>,
+    auth_session: AuthSession,
+) -> IngestResult<Option<SyncOrder>> {
+    let processor = require_processor(&auth_session)?;
+    let order = Order::fetch_order_for_processor(processor.id, &pool)
+        .await
+        .map_err(to_ingest_error)?;
+    match order {
+        Some(order) => Ok(Json(Some(to_sync_order(order, &pool).await?))),
+        None => Ok(Json(None)),
+    }
+}
+
+pub async fn add_item_handler(
+    State(AppState { pool, .. }): State<AppState>,
+    auth_session: AuthSession,
+    Path(order_id): Path<u64>,
+    Json(file): Json<IngestFile>,
+) -> IngestResult<OrderItem> {
+    let processor = require_processor(&auth_session)?;
+    let order = get_claimed_order(order_id, &processor, &pool).await?;
+    if order.status != OrderStatus::InProcess {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Order {order_id} is not open for uploads"),
+        ));
+    }
+    create_order_item(
+        &order,
+        Mode::Processed,
+        file.file_name,
+        file.file_size,
+        &pool,
+    )
+    .await
+    .map(Json)
+    .map_err(to_ingest_error)
+}
+
+pub async fn confirm_item_handler(
+    State(AppState { pool, .. }): State<AppState>,
+    auth_session: AuthSession,
+    Path(item_id): Path<u64>,
+) -> IngestResult<bool> {
+    let processor = require_processor(&auth_session)?;
+    let order_item = get_claimed_order_item(item_id, &processor, &pool).await?;
+    confirm_upload(order_item.id, &pool)
+        .await
+        .map(Json)
+        .map_err(to_ingest_error)
+}
+
+pub async fn cancel_item_handler(
+    State(AppState { pool, .. }): State<AppState>,
+    auth_session: AuthSession,
+    Path(item_id): Path<u64>,
+) -> IngestResult<bool> {
+    let processor = require_processor(&auth_session)?;
+    let order_item = get_claimed_order_item(item_id, &processor, &pool).await?;
+    cancel_upload(order_item.id, &pool)
+        .await
+        .map(Json)
+        .map_err(to_ingest_error)
+}
+
+// Same completion as the Complete Order button once every processed photo is in
+pub async fn complete_handler(
+    State(AppState { pool, .. }): State<AppState>,
+    auth_session: AuthSession,
+    Path(order_id): Path<u64>,
+) -> IngestResult<SyncOrder> {
+    let processor = require_processor(&auth_session)?;
+    let order = get_claimed_order(order_id, &processor, &pool).await?;
+    // Photos never confirmed would otherwise count as uploaded, they are
+    // cancelled so their slots are free to send them again
+    let pending = OrderItem::get_order_items_by_order_id(order_id, Mode::Processed, &pool)
+        .await
+        .map_err(to_ingest_error)?
+        .into_iter()
+        .filter(|order_item| !order_item.uploaded);
+    for order_item in pending {
+        cancel_upload(order_item.id, &pool)
+            .await
+            .map_err(to_ingest_error)?;
+    }
+    order
+        .set_uploaded_for_zero_remaining(Mode::Processed, &pool)
+        .await
+        .map_err(to_ingest_error)?;
+    let order = get_claimed_order(order_id, &processor, &pool).await?;
+    if order.status != OrderStatus::Processed {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Order {order_id} still has photos to upload"),
+        ));
+    }
+    complete_order(&order, &pool)
+        .await
+        .map_err(to_ingest_error)?;
+    let order = get_claimed_order(order_id, &processor, &pool).await?;
+    Ok(Json(to_sync_order(order, &pool).await?))
+}
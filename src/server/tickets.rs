@@ -0,0 +1,166 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use leptos::{log, ServerFnError};
+use printpdf::{BuiltinFont, Line, Mm, PdfDocument, Point};
+use qrcode::{render::svg, Color, QrCode};
+use sqlx::MySqlPool;
+
+use crate::auth::AuthSession;
+use crate::models::{
+    order::get_order_reference,
+    ticket::OrderTicket,
+    user::User,
+    user_order::UserOrder,
+};
+use crate::server::{access::get_viewable_order, app_state::AppState};
+use crate::to_server_fn_error;
+
+const TICKET_WIDTH: f64 = 105.0;
+const TICKET_HEIGHT: f64 = 148.0;
+const QR_SIZE: f64 = 60.0;
+
+pub async fn get_ticket(
+    order_id: u64,
+    user: &User,
+    pool: &MySqlPool,
+) -> Result<OrderTicket, ServerFnError> {
+    get_viewable_order(order_id, user, pool).await?;
+    let order = UserOrder::get_by_order_id(order_id, pool).await?;
+    let reference = get_order_reference(order.id);
+    let qr_svg = QrCode::new(reference.as_bytes())
+        .map_err(to_server_fn_error)?
+        .render::<svg::Color>()
+        .min_dimensions(200, 200)
+        .build();
+    Ok(OrderTicket {
+        order_id: order.id,
+        reference,
+        name: order.name,
+        no_of_photos: order.no_of_photos,
+        order_total: order.order_total,
+        mode_of_payment: order.mode_of_payment,
+        status: order.status,
+        qr_svg,
+    })
+}
+
+fn to_pdf_error(e: printpdf::Error) -> ServerFnError {
+    ServerFnError::ServerError(format!("{:?}", e))
+}
+
+fn square(x: f64, y: f64, width: f64, height: f64) -> Line {
+    let points = [
+        (x, y),
+        (x + width, y),
+        (x + width, y + height),
+        (x, y + height),
+    ]
+    .into_iter()
+    .map(|(x, y)| (Point::new(Mm(x), Mm(y)), false))
+    .collect();
+    Line {
+        points,
+        is_closed: true,
+        has_fill: true,
+        has_stroke: false,
+        is_clipping_path: false,
+    }
+}
+
+pub fn render_ticket_pdf(ticket: &OrderTicket) -> Result<Vec<u8>, ServerFnError> {
+    let (doc, page, layer) = PdfDocument::new(
+        format!("Order {}", ticket.order_id),
+        Mm(TICKET_WIDTH),
+        Mm(TICKET_HEIGHT),
+        "Ticket",
+    );
+    let layer = doc.get_page(page).get_layer(layer);
+    let bold = doc
+        .add_builtin_font(BuiltinFont::HelveticaBold)
+        .map_err(to_pdf_error)?;
+    let regular = doc
+        .add_builtin_font(BuiltinFont::Helvetica)
+        .map_err(to_pdf_error)?;
+
+    layer.use_text(
+        format!("Order #{}", ticket.order_id),
+        24.0,
+        Mm(10.0),
+        Mm(TICKET_HEIGHT - 20.0),
+        &bold,
+    );
+    let lines = [
+        format!("Name: {}", ticket.name),
+        format!("Photos: {}", ticket.no_of_photos),
+        format!("Total: ${}", ticket.order_total),
+        format!("Payment: {}", ticket.payment_status()),
+    ];
+    for (index, line) in lines.into_iter().enumerate() {
+        let y = TICKET_HEIGHT - 32.0 - index as f64 * 7.0;
+        layer.use_text(line, 12.0, Mm(10.0), Mm(y), &regular);
+    }
+
+    // Each row of dark modules is drawn as runs to keep the file small
+    let code = QrCode::new(ticket.reference.as_bytes()).map_err(to_server_fn_error)?;
+    let width = code.width();
+    let module = QR_SIZE / width as f64;
+    let left = (TICKET_WIDTH - QR_SIZE) / 2.0;
+    let top = 15.0 + QR_SIZE;
+    let colors = code.to_colors();
+    for (row, modules) in colors.chunks(width).enumerate() {
+        let y = top - (row + 1) as f64 * module;
+        let mut column = 0;
+        while column < width {
+            if modules[column] != Color::Dark {
+                column += 1;
+                continue;
+            }
+            let start = column;
+            while column < width && modules[column] == Color::Dark {
+                column += 1;
+            }
+            layer.add_shape(square(
+                left + start as f64 * module,
+                y,
+                (column - start) as f64 * module,
+                module,
+            ));
+        }
+    }
+    layer.use_text(ticket.reference.clone(), 10.0, Mm(left), Mm(8.0), &regular);
+    doc.save_to_bytes().map_err(to_pdf_error)
+}
+
+pub async fn ticket_pdf_handler(
+    State(AppState { pool, .. }): State<AppState>,
+    auth_session: AuthSession,
+    Path(order_id): Path<u64>,
+) -> Response {
+    let Some(user) = auth_session.current_user else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let pdf = match get_ticket(order_id, &user, &pool).await {
+        Ok(ticket) => render_ticket_pdf(&ticket),
+        Err(e) => Err(e),
+    };
+    match pdf {
+        Ok(pdf) => (
+            [
+                (header::CONTENT_TYPE, "application/pdf".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("inline; filename=\"order-{:0>6}.pdf\"", order_id),
+                ),
+            ],
+            pdf,
+        )
+            .into_response(),
+        Err(e) => {
+            log!("Unable to create ticket for order {order_id}: {:#?}", e);
+            StatusCode::NOT_FOUND.into_response()
+        }
+    }
+}
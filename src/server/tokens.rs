@@ -0,0 +1,83 @@
+use chrono::{Duration, Local};
+use leptos::ServerFnError;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::MySqlPool;
+
+use crate::models::user_token::{TokenPurpose, UserToken};
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+// The token goes out by email, only its hash is kept
+fn token_and_hash() -> (String, String) {
+    let token = generate_token();
+    let token_hash = hash_token(&token);
+    (token, token_hash)
+}
+
+// Replaces any earlier token for the same purpose, so only the latest link works
+pub async fn issue_token(
+    user_id: u64,
+    purpose: TokenPurpose,
+    ttl: Duration,
+    pool: &MySqlPool,
+) -> Result<String, ServerFnError> {
+    let (token, token_hash) = token_and_hash();
+    UserToken::delete_for_user(user_id, purpose, pool).await?;
+    UserToken::create(
+        user_id,
+        purpose,
+        token_hash,
+        Local::now().naive_local() + ttl,
+        pool,
+    )
+    .await?;
+    Ok(token)
+}
+
+pub async fn consume_token(
+    purpose: TokenPurpose,
+    token: &str,
+    pool: &MySqlPool,
+) -> Result<Option<u64>, ServerFnError> {
+    UserToken::consume(purpose, &hash_token(token), pool).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_hashed_with_sha256() {
+        assert_eq!(
+            hash_token("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn emailed_tokens_hash_to_the_stored_hash() {
+        let (token, token_hash) = token_and_hash();
+        assert_eq!(token.len(), 64);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(hash_token(&token), token_hash);
+        assert_ne!(token, token_hash);
+    }
+
+    #[test]
+    fn every_token_is_different() {
+        let (first, first_hash) = token_and_hash();
+        let (second, second_hash) = token_and_hash();
+        assert_ne!(first, second);
+        assert_ne!(first_hash, second_hash);
+        assert_ne!(hash_token(&first), second_hash);
+    }
+}
@@ -0,0 +1,109 @@
+use leptos::{log, ServerFnError};
+use sqlx::MySqlPool;
+
+use crate::models::{
+    file_format::FileFormat,
+    order::Order,
+    order_item::{Mode, OrderItem},
+};
+use crate::server::downloads::get_download_path;
+use crate::server::storage::{create_upload_url, delete_file, get_order_item_path, get_prefix};
+use crate::server::validation::{compute_checksum, validate_upload};
+
+// Originals are only scaled down in the browser when this is set
+pub fn get_max_original_dimension() -> Option<u32> {
+    dotenvy::var("MAX_ORIGINAL_DIMENSION")
+        .ok()
+        .and_then(|dimension| dimension.parse().ok())
+        .filter(|dimension| *dimension > 0)
+}
+
+pub async fn create_order_item(
+    order: &Order,
+    mode: Mode,
+    file_name: String,
+    file_size: u64,
+    pool: &MySqlPool,
+) -> Result<OrderItem, ServerFnError> {
+    FileFormat::for_upload(&file_name, mode, file_size).map_err(ServerFnError::Args)?;
+    let Some((_, ext)) = file_name.rsplit_once('.') else {
+        return Err(ServerFnError::Args("Invalid File Name".to_string()));
+    };
+    let file_name = format!("{}.{}", uuid::Uuid::new_v4().as_hyphenated(), ext);
+    if order.remaining_order_items(mode, pool).await? < 1 {
+        return Err(ServerFnError::ServerError(
+            "No more uploads allowed".to_string(),
+        ));
+    }
+    let put_url = create_upload_url(format!("{}/{file_name}", get_prefix(order.id, mode))).await?;
+    let order_item = order
+        .add_order_item(file_name, mode, String::new(), put_url, pool)
+        .await?;
+    order_item
+        .update_get_url(get_download_path(order_item.id), pool)
+        .await?;
+    OrderItem::get_by_id(order_item.id, pool).await
+}
+
+pub async fn confirm_upload(item_id: u64, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+    let order_item = OrderItem::get_by_id(item_id, pool).await?;
+    if order_item.uploaded {
+        return Err(ServerFnError::Args(
+            "Upload was already confirmed".to_string(),
+        ));
+    }
+    let upload = match validate_upload(&order_item).await {
+        Ok(upload) => upload,
+        Err(e) => {
+            log!("Rejecting upload {}: {:#?}", order_item.file_name, e);
+            _ = delete_file(get_order_item_path(&order_item)).await;
+            OrderItem::delete(order_item.id, pool).await?;
+            return Err(e);
+        }
+    };
+    order_item.set_file_size(upload.size, pool).await?;
+    let path = get_order_item_path(&order_item);
+    match compute_checksum(path.clone(), upload.size).await {
+        Ok(checksum) => {
+            if !order_item.set_checksum(checksum.clone(), pool).await? {
+                let duplicate = order_item.find_duplicate(&checksum, pool).await?;
+                log!(
+                    "Rejecting upload {} as a duplicate of {}",
+                    order_item.file_name,
+                    duplicate.map_or(String::from("another photo"), |duplicate| duplicate
+                        .file_name)
+                );
+                _ = delete_file(path).await;
+                OrderItem::delete(order_item.id, pool).await?;
+                return Err(ServerFnError::Args(
+                    "This photo was already uploaded to this order".to_string(),
+                ));
+            }
+        }
+        // Reconciliation fills in checksums that could not be computed here
+        Err(e) => log!(
+            "Unable to compute checksum for {}: {:#?}",
+            order_item.file_name,
+            e
+        ),
+    }
+    let response = order_item.set_uploaded(pool).await?;
+    let pool = pool.clone();
+    tokio::spawn(async move {
+        if let Err(e) = crate::server::imaging::generate_derivatives(order_item, &pool).await {
+            log!("Unable to generate derivatives: {:#?}", e);
+        }
+    });
+    Ok(response)
+}
+
+pub async fn cancel_upload(item_id: u64, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+    let order_item = OrderItem::get_by_id(item_id, pool).await?;
+    if order_item.uploaded {
+        return Err(ServerFnError::Args(
+            "Upload was already confirmed".to_string(),
+        ));
+    }
+    _ = delete_file(get_order_item_path(&order_item)).await;
+    OrderItem::delete(order_item.id, pool).await
+}
@@ -0,0 +1,186 @@
+use leptos::{log, ServerFnError};
+use sqlx::MySqlPool;
+
+use crate::models::{
+    audit_log::{AuditAction, AuditEntry},
+    order::Order,
+    user::{Role, User, UserStatus},
+    user_admin::{UserDetails, UserPage},
+    user_session::UserSession,
+};
+
+pub const PAGE_SIZE: u64 = 20;
+const HISTORY_LIMIT: u64 = 50;
+
+pub async fn record(
+    actor: &User,
+    target_user_id: Option<u64>,
+    action: AuditAction,
+    details: String,
+    pool: &MySqlPool,
+) -> Result<(), ServerFnError> {
+    log!(
+        "User {} {:?} {:?}: {details}",
+        actor.id,
+        action,
+        target_user_id
+    );
+    AuditEntry::create(actor.id, target_user_id, action, details, pool).await?;
+    Ok(())
+}
+
+// Managers must not lock themselves out, another manager has to do it
+fn not_self(actor: &User, id: u64) -> Result<(), ServerFnError> {
+    if actor.id == id {
+        return Err(ServerFnError::Args(
+            "You can not change your own role or status".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn search_users(
+    query: String,
+    role: Option<Role>,
+    page: u64,
+    pool: &MySqlPool,
+) -> Result<UserPage, ServerFnError> {
+    let (users, total) = User::search(&query, role, page * PAGE_SIZE, PAGE_SIZE, pool).await?;
+    Ok(UserPage {
+        users,
+        total,
+        page,
+        page_size: PAGE_SIZE,
+    })
+}
+
+pub async fn get_user_details(id: u64, pool: &MySqlPool) -> Result<UserDetails, ServerFnError> {
+    let user = User::get_by_id(id, pool).await?;
+    Ok(UserDetails {
+        orders: user.orders(pool).await?,
+        handled_orders: Order::get_handled_by(id, HISTORY_LIMIT, pool).await?,
+        changes: AuditEntry::get_for_target(id, HISTORY_LIMIT, pool).await?,
+        actions: AuditEntry::get_by_actor(id, HISTORY_LIMIT, pool).await?,
+        user,
+    })
+}
+
+pub async fn change_role(
+    actor: &User,
+    id: u64,
+    role: Role,
+    pool: &MySqlPool,
+) -> Result<bool, ServerFnError> {
+    not_self(actor, id)?;
+    if role == Role::Anonymous {
+        return Err(ServerFnError::Args("Invalid role".to_string()));
+    }
+    let user = User::get_by_id(id, pool).await?;
+    let details = format!("{:?} -> {:?}", user.role, role);
+    let changed = User::change_role(id, role, pool).await?;
+    if changed {
+        record(actor, Some(id), AuditAction::ChangeRole, details, pool).await?;
+    }
+    Ok(changed)
+}
+
+// Disabling also ends every session, so the account is out straight away
+pub async fn set_status(
+    actor: &User,
+    id: u64,
+    status: UserStatus,
+    pool: &MySqlPool,
+) -> Result<bool, ServerFnError> {
+    not_self(actor, id)?;
+    let action = match status {
+        UserStatus::Disabled => AuditAction::DisableUser,
+        UserStatus::Active => AuditAction::EnableUser,
+        UserStatus::NotActivatedYet => {
+            return Err(ServerFnError::Args("Invalid status".to_string()))
+        }
+    };
+    let user = User::get_by_id(id, pool).await?;
+    if user.status == status {
+        return Ok(false);
+    }
+    User::set_status(id, status.clone(), pool).await?;
+    let mut details = format!("{:?} -> {:?}", user.status, status);
+    if status == UserStatus::Disabled {
+        let sessions = UserSession::revoke_all_for_user(id, pool).await?;
+        details.push_str(&format!(", {sessions} sessions ended"));
+    }
+    record(actor, Some(id), action, details, pool).await?;
+    Ok(true)
+}
+
+pub async fn update_profile(
+    actor: &User,
+    id: u64,
+    name: String,
+    email: String,
+    phone: Option<String>,
+    pool: &MySqlPool,
+) -> Result<bool, ServerFnError> {
+    let name = name.trim().to_string();
+    let email = email.trim().to_lowercase();
+    let phone = phone
+        .map(|phone| phone.trim().to_string())
+        .filter(|phone| !phone.is_empty());
+    if name.is_empty() {
+        return Err(ServerFnError::Args("Name is required".to_string()));
+    }
+    if !email.contains('@') {
+        return Err(ServerFnError::Args("Enter a valid email".to_string()));
+    }
+    let user = User::get_by_id(id, pool).await?;
+    if email != user.email.to_lowercase() {
+        if let Ok(other) = User::get_by_username(email.clone(), pool).await {
+            if other.id != id {
+                return Err(ServerFnError::Args(
+                    "This email is already registered".to_string(),
+                ));
+            }
+        }
+    }
+    let mut changes = Vec::new();
+    if name != user.name {
+        changes.push(format!("name: {} -> {}", user.name, name));
+    }
+    if email != user.email {
+        changes.push(format!("email: {} -> {}", user.email, email));
+    }
+    if phone != user.phone {
+        changes.push(format!(
+            "phone: {} -> {}",
+            user.phone.unwrap_or_default(),
+            phone.clone().unwrap_or_default()
+        ));
+    }
+    if changes.is_empty() {
+        return Ok(false);
+    }
+    User::update_profile(id, name, email, phone, pool).await?;
+    record(
+        actor,
+        Some(id),
+        AuditAction::UpdateProfile,
+        changes.join(", "),
+        pool,
+    )
+    .await?;
+    Ok(true)
+}
+
+pub async fn force_logout(actor: &User, id: u64, pool: &MySqlPool) -> Result<u64, ServerFnError> {
+    User::get_by_id(id, pool).await?;
+    let sessions = UserSession::revoke_all_for_user(id, pool).await?;
+    record(
+        actor,
+        Some(id),
+        AuditAction::ForceLogout,
+        format!("{sessions} sessions ended"),
+        pool,
+    )
+    .await?;
+    Ok(sessions)
+}
@@ -0,0 +1,128 @@
+use leptos::ServerFnError;
+use sha2::{Digest, Sha256};
+
+use crate::models::{
+    file_format::FileFormat,
+    order_item::{Mode, OrderItem},
+};
+use crate::server::storage::{get_object_range, get_order_item_path, head_object};
+
+const SNIFF_LENGTH: u64 = 64;
+const CHECKSUM_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+pub struct ValidatedUpload {
+    pub format: FileFormat,
+    pub size: u64,
+}
+
+pub async fn compute_checksum(path: String, size: u64) -> Result<String, ServerFnError> {
+    let mut hasher = Sha256::new();
+    let mut start = 0;
+    while start < size {
+        let end = (start + CHECKSUM_CHUNK_SIZE).min(size) - 1;
+        let chunk = get_object_range(path.clone(), start, end).await?;
+        // A truncated response would otherwise hash as a different file
+        if chunk.len() as u64 != end - start + 1 {
+            return Err(ServerFnError::ServerError(format!(
+                "Read {} bytes of {path} at {start}, expected {}",
+                chunk.len(),
+                end - start + 1
+            )));
+        }
+        hasher.update(chunk);
+        start = end + 1;
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+// Split from validate_upload so the decision is testable without storage
+fn check_upload(
+    file_name: &str,
+    mode: Mode,
+    size: u64,
+    content: &[u8],
+) -> Result<FileFormat, ServerFnError> {
+    let format = FileFormat::for_upload(file_name, mode, size).map_err(ServerFnError::Args)?;
+    if !format.matches(content) {
+        return Err(ServerFnError::Args(format!(
+            "Uploaded file content does not match the {:?} format",
+            format
+        )));
+    }
+    Ok(format)
+}
+
+pub async fn validate_upload(order_item: &OrderItem) -> Result<ValidatedUpload, ServerFnError> {
+    let path = get_order_item_path(order_item);
+    let object = head_object(path.clone()).await?.ok_or(ServerFnError::Args(
+        "Uploaded file was not found".to_string(),
+    ))?;
+    if object.size == 0 {
+        return Err(ServerFnError::Args("Uploaded file is empty".to_string()));
+    }
+    let content = get_object_range(path, 0, SNIFF_LENGTH.min(object.size) - 1).await?;
+    let format = check_upload(
+        &order_item.file_name,
+        order_item.mode,
+        object.size,
+        &content,
+    )?;
+    Ok(ValidatedUpload {
+        format,
+        size: object.size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JPEG: &[u8] = &[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F'];
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+    const TIFF: &[u8] = b"II*\0\x08\0\0\0";
+    const HEIC: &[u8] = b"\0\0\0\x18ftypheic\0\0\0\0";
+    const CR3: &[u8] = b"\0\0\0\x18ftypcrx \0\0\0\x01";
+
+    #[test]
+    fn content_matching_the_extension_is_accepted() {
+        let accepted = [
+            ("IMG_0001.JPG", Mode::Original, JPEG, FileFormat::Jpeg),
+            ("IMG_0001.png", Mode::Processed, PNG, FileFormat::Png),
+            ("IMG_0001.tif", Mode::Processed, TIFF, FileFormat::Tiff),
+            ("IMG_0001.nef", Mode::Original, TIFF, FileFormat::Nef),
+            ("IMG_0001.heic", Mode::Original, HEIC, FileFormat::Heic),
+            ("IMG_0001.cr3", Mode::Original, CR3, FileFormat::Cr3),
+        ];
+        for (file_name, mode, content, format) in accepted {
+            assert_eq!(
+                check_upload(file_name, mode, 1024, content).ok(),
+                Some(format)
+            );
+        }
+    }
+
+    #[test]
+    fn content_not_matching_the_extension_is_refused() {
+        let refused = [
+            ("IMG_0001.jpg", PNG),
+            ("IMG_0001.png", JPEG),
+            ("IMG_0001.heic", CR3),
+            ("IMG_0001.cr3", HEIC),
+            ("IMG_0001.nef", JPEG),
+            ("IMG_0001.jpg", b"<html></html>".as_slice()),
+        ];
+        for (file_name, content) in refused {
+            assert!(
+                check_upload(file_name, Mode::Original, 1024, content).is_err(),
+                "{file_name} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn the_extension_rules_apply_before_the_content() {
+        assert!(check_upload("IMG_0001.heic", Mode::Processed, 1024, HEIC).is_err());
+        assert!(check_upload("IMG_0001.jpg", Mode::Original, 60 * 1024 * 1024, JPEG).is_err());
+        assert!(check_upload("IMG_0001.gif", Mode::Original, 1024, b"GIF89a").is_err());
+    }
+}
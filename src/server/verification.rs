@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use chrono::Local;
+use leptos::{log, ServerFnError};
+use sqlx::MySqlPool;
+
+use crate::models::{
+    user::{User, UserStatus},
+    user_token::{TokenPurpose, UserToken},
+};
+use crate::server::mailer::send_verification;
+use crate::server::tokens::{consume_token, issue_token};
+
+const VERIFICATION_TOKEN_HOURS: i64 = 24;
+const UNVERIFIED_ACCOUNT_DAYS: i64 = 7;
+const CLEANUP_INTERVAL: u64 = 3600;
+
+pub async fn send_verification_email(user: &User, pool: &MySqlPool) -> Result<bool, ServerFnError> {
+    let token = issue_token(
+        user.id,
+        TokenPurpose::EmailVerification,
+        chrono::Duration::hours(VERIFICATION_TOKEN_HOURS),
+        pool,
+    )
+    .await?;
+    let app_url = dotenvy::var("APP_URL").expect("APP_URL should be present");
+    let link = format!("{}/verify/{token}", app_url.trim_end_matches('/'));
+    send_verification(user.email.clone(), user.name.clone(), link).await
+}
+
+// Returns the activated user, or None when the link is unknown, used or expired
+pub async fn verify_email(token: &str, pool: &MySqlPool) -> Result<Option<User>, ServerFnError> {
+    let Some(user_id) = consume_token(TokenPurpose::EmailVerification, token, pool).await? else {
+        return Ok(None);
+    };
+    User::activate(user_id, pool).await?;
+    let user = User::get_by_id(user_id, pool).await?;
+    Ok((user.status == UserStatus::Active).then_some(user))
+}
+
+pub fn spawn_cleanup_job(pool: MySqlPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(CLEANUP_INTERVAL));
+        loop {
+            interval.tick().await;
+            if let Err(e) = cleanup_unverified(&pool).await {
+                log!("Account cleanup failed: {:#?}", e);
+            }
+        }
+    });
+}
+
+pub async fn cleanup_unverified(pool: &MySqlPool) -> Result<(), ServerFnError> {
+    let created_before =
+        Local::now().naive_local() - chrono::Duration::days(UNVERIFIED_ACCOUNT_DAYS);
+    let deleted = User::delete_unverified(created_before, pool).await?;
+    if deleted > 0 {
+        log!("Removed {deleted} unverified account(s)");
+    }
+    UserToken::delete_expired(pool).await?;
+    crate::server::login_guard::prune_attempts(pool).await?;
+    Ok(())
+}
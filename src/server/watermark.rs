@@ -0,0 +1,105 @@
+use image::{imageops, DynamicImage, GrayImage, Luma, RgbaImage};
+use imageproc::drawing::{draw_text_mut, text_size};
+use leptos::ServerFnError;
+use rusttype::{Font, Scale};
+
+use crate::to_server_fn_error;
+
+pub struct Watermark {
+    pub text: Option<String>,
+    pub font: Option<Font<'static>>,
+    pub logo: Option<RgbaImage>,
+    pub opacity: f32,
+}
+
+impl Watermark {
+    pub fn from_env() -> Result<Self, ServerFnError> {
+        let text = dotenvy::var("WATERMARK_TEXT")
+            .ok()
+            .or(dotenvy::var("APP_NAME").ok())
+            .filter(|text| !text.trim().is_empty());
+        let font = match dotenvy::var("WATERMARK_FONT") {
+            Ok(path) => {
+                let bytes = std::fs::read(path).map_err(to_server_fn_error)?;
+                Some(Font::try_from_vec(bytes).ok_or(ServerFnError::ServerError(
+                    "Unable to load WATERMARK_FONT".to_string(),
+                ))?)
+            }
+            Err(_) => None,
+        };
+        let logo = match dotenvy::var("WATERMARK_LOGO") {
+            Ok(path) => Some(image::open(path).map_err(to_server_fn_error)?.to_rgba8()),
+            Err(_) => None,
+        };
+        let opacity = dotenvy::var("WATERMARK_OPACITY")
+            .unwrap_or("0.35".into())
+            .parse::<f32>()
+            .map_err(to_server_fn_error)?
+            .clamp(0.0, 1.0);
+        if logo.is_none() && (text.is_none() || font.is_none()) {
+            return Err(ServerFnError::ServerError(
+                "Either WATERMARK_LOGO or WATERMARK_TEXT with WATERMARK_FONT should be configured"
+                    .to_string(),
+            ));
+        }
+        Ok(Self {
+            text,
+            font,
+            logo,
+            opacity,
+        })
+    }
+
+    pub fn apply(&self, image: &DynamicImage) -> DynamicImage {
+        let mut canvas = image.to_rgba8();
+        if let (Some(text), Some(font)) = (&self.text, &self.font) {
+            let mask = self.text_mask(canvas.width(), canvas.height(), text, font);
+            for (x, y, Luma([coverage])) in mask.enumerate_pixels() {
+                if *coverage == 0 {
+                    continue;
+                }
+                let alpha = *coverage as f32 / 255.0 * self.opacity;
+                let pixel = canvas.get_pixel_mut(x, y);
+                for channel in pixel.0.iter_mut().take(3) {
+                    *channel = (*channel as f32 * (1.0 - alpha) + 255.0 * alpha) as u8;
+                }
+            }
+        }
+        if let Some(logo) = &self.logo {
+            let width = (canvas.width() / 3).max(1);
+            let height = (logo.height() as u64 * width as u64 / logo.width().max(1) as u64)
+                .max(1) as u32;
+            let mut logo = imageops::resize(logo, width, height, imageops::FilterType::Triangle);
+            for pixel in logo.pixels_mut() {
+                pixel.0[3] = (pixel.0[3] as f32 * self.opacity) as u8;
+            }
+            let x = (canvas.width() as i64 - width as i64) / 2;
+            let y = (canvas.height() as i64 - height as i64) / 2;
+            imageops::overlay(&mut canvas, &logo, x, y);
+        }
+        DynamicImage::ImageRgba8(canvas)
+    }
+
+    fn text_mask(&self, width: u32, height: u32, text: &str, font: &Font<'static>) -> GrayImage {
+        let mut mask = GrayImage::new(width, height);
+        let scale = Scale::uniform((width.min(height) as f32 / 10.0).max(12.0));
+        let (text_width, text_height) = text_size(scale, font, text);
+        let step_x = text_width + text_height * 2;
+        let step_y = text_height * 3;
+        if step_x <= 0 || step_y <= 0 {
+            return mask;
+        }
+        let mut y = 0;
+        let mut row = 0;
+        while y < height as i32 {
+            let mut x = -((row % 2) * step_x / 2);
+            while x < width as i32 {
+                draw_text_mut(&mut mask, Luma([255]), x, y, scale, font, text);
+                x += step_x;
+            }
+            y += step_y;
+            row += 1;
+        }
+        mask
+    }
+}